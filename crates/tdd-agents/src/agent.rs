@@ -2,11 +2,15 @@ use anyhow::{Context, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
-use tdd_core::{Agent, Role, StepContext, StepResult};
-use tdd_llm::{LlmClient, Message};
+use std::sync::Mutex;
+use tdd_core::{Agent, Language, Role, StepContext, StepResult};
+use tdd_llm::{ChatRequest, GenerationParams, LlmClient, Message, ResponseFormat};
 use tokio::fs;
+use tokio::io::AsyncWriteExt;
 
-use crate::prompts::{IMPLEMENTOR_SYSTEM_PROMPT, REFACTORER_SYSTEM_PROMPT, TESTER_SYSTEM_PROMPT};
+use crate::chain::{ModelChain, ModelChoice};
+use crate::prompts::{system_prompt, TOOLS_PROMPT};
+use crate::tools::{ToolExecution, ToolSettings, Toolbox};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct FileEdit {
@@ -22,12 +26,61 @@ struct EditPlan {
     notes: String,
 }
 
+/// JSON schema of [`EditPlan`], in the strict form structured output requires
+/// (every property required, no extra properties).
+pub fn edit_plan_schema() -> serde_json::Value {
+    serde_json::json!({
+        "type": "object",
+        "properties": {
+            "edits": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "path": {"type": "string"},
+                        "action": {"type": "string", "enum": ["upsert"]},
+                        "content": {"type": "string"}
+                    },
+                    "required": ["path", "action", "content"],
+                    "additionalProperties": false
+                }
+            },
+            "commit_message": {"type": "string"},
+            "notes": {"type": "string"}
+        },
+        "required": ["edits", "commit_message", "notes"],
+        "additionalProperties": false
+    })
+}
+
+fn edit_plan_format() -> ResponseFormat {
+    ResponseFormat::JsonSchema {
+        name: "edit_plan".to_string(),
+        schema: edit_plan_schema(),
+        strict: true,
+    }
+}
+
+/// Clean up a model response (strip markdown code blocks if present).
+pub(crate) fn strip_code_fences(response: &str) -> &str {
+    response
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim()
+}
+
 pub struct LlmAgent {
     role: Role,
-    llm: LlmClient,
-    model: String,
-    temperature: f32,
+    models: ModelChain,
+    /// Model that wrote the last plan, reported with the step it produced.
+    last_model: Mutex<Option<String>>,
+    params: GenerationParams,
     work_dir: PathBuf,
+    tools: Option<ToolSettings>,
+    language: Language,
+    prompt_override: Option<String>,
 }
 
 impl LlmAgent {
@@ -35,18 +88,48 @@ impl LlmAgent {
         role: Role,
         llm: LlmClient,
         model: String,
-        temperature: f32,
+        params: GenerationParams,
         work_dir: PathBuf,
     ) -> Self {
+        let choice = ModelChoice::new(model.clone(), llm, model);
         Self {
             role,
-            llm,
-            model,
-            temperature,
+            models: ModelChain::new(choice),
+            last_model: Mutex::new(None),
+            params,
             work_dir,
+            tools: None,
+            language: Language::default(),
+            prompt_override: None,
         }
     }
 
+    /// Escalate to stronger models on later attempts and fall back to others
+    /// when a provider is unavailable, instead of the single model from `new`.
+    pub fn with_models(mut self, models: ModelChain) -> Self {
+        self.models = models;
+        self
+    }
+
+    /// Prompt for a kata in `language` (Rust by default).
+    pub fn with_language(mut self, language: Language) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// Replace the built-in system prompt for this role.
+    pub fn with_system_prompt(mut self, prompt: String) -> Self {
+        self.prompt_override = Some(prompt);
+        self
+    }
+
+    /// Let the model explore the repo and run tests through function calls
+    /// before it answers, instead of receiving every file up front.
+    pub fn with_tools(mut self, settings: ToolSettings) -> Self {
+        self.tools = Some(settings);
+        self
+    }
+
     async fn read_files(&self, paths: &[String]) -> Result<String> {
         let mut content = String::new();
         for path in paths {
@@ -61,12 +144,93 @@ impl LlmAgent {
         Ok(content)
     }
 
-    fn system_prompt(&self) -> &'static str {
-        match self.role {
-            Role::Tester => TESTER_SYSTEM_PROMPT,
-            Role::Implementor => IMPLEMENTOR_SYSTEM_PROMPT,
-            Role::Refactorer => REFACTORER_SYSTEM_PROMPT,
+    fn system_prompt(&self) -> String {
+        let prompt = self
+            .prompt_override
+            .clone()
+            .unwrap_or_else(|| system_prompt(self.role, self.language));
+        match self.tools {
+            Some(_) => format!("{}{}", prompt, TOOLS_PROMPT),
+            None => prompt,
+        }
+    }
+
+    /// Run the tool-calling loop until the model answers without tool calls.
+    async fn chat_with_tools(
+        &self,
+        choice: &ModelChoice,
+        ctx: &StepContext,
+        settings: &ToolSettings,
+        mut messages: Vec<Message>,
+    ) -> Result<String> {
+        let toolbox = Toolbox::new(self.work_dir.clone(), settings);
+        let definitions = Toolbox::definitions();
+
+        for turn in 1..=settings.max_turns {
+            let reply = choice
+                .client
+                .chat_with_tools(&choice.model, &messages, &self.params, &definitions)
+                .await?;
+            if reply.tool_calls.is_empty() {
+                return Ok(reply.content);
+            }
+
+            let calls = reply.tool_calls.clone();
+            messages.push(reply);
+            let mut executions = Vec::new();
+            for call in &calls {
+                println!(
+                    "Tool call: {}({})",
+                    call.function.name, call.function.arguments
+                );
+                let (ok, output) = toolbox.execute(call).await;
+                messages.push(Message::tool(&call.id, &output));
+                executions.push(ToolExecution {
+                    turn,
+                    name: call.function.name.clone(),
+                    arguments: call.function.arguments.clone(),
+                    ok,
+                    output,
+                });
+            }
+            self.log_tool_executions(ctx, &executions).await?;
+        }
+
+        // Out of tool turns: ask for the final answer with tools disabled.
+        messages.push(Message::user(
+            "Tool budget exhausted. Reply now with the final JSON edit plan.",
+        ));
+        let request = ChatRequest::new(&choice.model, messages)
+            .params(self.params.clone())
+            .response_format(edit_plan_format());
+        Ok(choice.client.complete(&request).await?.message.content)
+    }
+
+    async fn log_tool_executions(
+        &self,
+        ctx: &StepContext,
+        executions: &[ToolExecution],
+    ) -> Result<()> {
+        let log_dir = self.work_dir.join(".tdd").join("logs");
+        fs::create_dir_all(&log_dir).await?;
+        let path = log_dir.join(format!(
+            "step-{}-{}-tools.jsonl",
+            ctx.step_index,
+            self.role.as_str()
+        ));
+        let mut lines = String::new();
+        for execution in executions {
+            lines.push_str(&serde_json::to_string(execution)?);
+            lines.push('\n');
         }
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await
+            .context(format!("Failed to open tool log: {:?}", path))?;
+        file.write_all(lines.as_bytes()).await?;
+        Ok(())
     }
 }
 
@@ -76,10 +240,18 @@ impl Agent for LlmAgent {
         self.role
     }
 
+    fn instructions(&self) -> Option<String> {
+        Some(self.system_prompt())
+    }
+
     async fn plan(&self, ctx: &StepContext) -> Result<String> {
-        let file_contents = self.read_files(&ctx.repo_snapshot_paths).await?;
+        let file_contents = match self.tools {
+            // In tool mode the model reads what it needs itself.
+            Some(_) => ctx.repo_snapshot_paths.join("\n"),
+            None => self.read_files(&ctx.repo_snapshot_paths).await?,
+        };
 
-        let user_prompt = format!(
+        let mut user_prompt = format!(
             "Step: {}\nRole: {:?}\nKata: {}\n\nLast Commit: {}\n\nLast Diff:\n{}\n\nCurrent Files:\n{}",
             ctx.step_index,
             self.role,
@@ -88,32 +260,51 @@ impl Agent for LlmAgent {
             ctx.git_last_diff,
             file_contents
         );
+        if let Some(next_test) = &ctx.next_test {
+            user_prompt.push_str(&format!("\n\nNext Test-List Item: {}", next_test));
+        }
+        if !ctx.journal.is_empty() {
+            user_prompt.push_str(&format!("\n\nSession Journal:\n{}", ctx.journal.render()));
+        }
+        if let Some(feedback) = &ctx.feedback {
+            user_prompt.push_str(&format!(
+                "\n\nYour previous attempt was rejected. Fix these problems:\n{}",
+                feedback
+            ));
+        }
 
         let messages = vec![
-            Message {
-                role: "system".to_string(),
-                content: self.system_prompt().to_string(),
-            },
-            Message {
-                role: "user".to_string(),
-                content: user_prompt,
-            },
+            Message::system(self.system_prompt()),
+            Message::user(user_prompt),
         ];
 
-        let response = self
-            .llm
-            .chat(&self.model, messages, self.temperature)
+        let (response, choice) = self
+            .models
+            .run(ctx.attempt, |choice| {
+                let messages = messages.clone();
+                async move {
+                    match &self.tools {
+                        Some(settings) => {
+                            self.chat_with_tools(choice, ctx, settings, messages).await
+                        }
+                        None => {
+                            choice
+                                .client
+                                .chat_json(
+                                    &choice.model,
+                                    messages,
+                                    &self.params,
+                                    edit_plan_format(),
+                                )
+                                .await
+                        }
+                    }
+                }
+            })
             .await?;
+        *self.last_model.lock().unwrap() = Some(choice.name.clone());
 
-        // Clean up response (strip markdown code blocks if present)
-        let cleaned = response
-            .trim()
-            .trim_start_matches("```json")
-            .trim_start_matches("```")
-            .trim_end_matches("```")
-            .trim();
-
-        Ok(cleaned.to_string())
+        Ok(strip_code_fences(&response).to_string())
     }
 
     async fn edit(&self, ctx: &StepContext) -> Result<StepResult> {
@@ -156,6 +347,7 @@ impl Agent for LlmAgent {
             files_changed,
             commit_message: plan.commit_message,
             notes: plan.notes,
+            model: self.last_model.lock().unwrap().clone(),
         })
     }
 }
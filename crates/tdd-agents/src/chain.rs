@@ -0,0 +1,140 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tdd_llm::{LlmClient, LlmError};
+
+/// How long a model that turned out to be unavailable is skipped in favour of
+/// its fallbacks.
+const UNAVAILABLE_COOLDOWN: Duration = Duration::from_secs(300);
+
+/// One model on one endpoint.
+#[derive(Debug, Clone)]
+pub struct ModelChoice {
+    /// How the model is named in logs and commits, e.g. `openai:gpt-4o`.
+    pub name: String,
+    pub client: LlmClient,
+    /// Bare model name sent to the provider.
+    pub model: String,
+}
+
+impl ModelChoice {
+    pub fn new(name: impl Into<String>, client: LlmClient, model: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            client,
+            model: model.into(),
+        }
+    }
+}
+
+/// The models a role may use: a primary model, stronger ones taking over from
+/// later attempts of a step, and fallbacks for when a provider is unavailable.
+#[derive(Debug)]
+pub struct ModelChain {
+    /// Sorted by the first attempt each model serves.
+    escalation: Vec<(u32, ModelChoice)>,
+    fallback: Vec<ModelChoice>,
+    /// Models that failed with a provider error, by name, and when.
+    unavailable: Mutex<HashMap<String, Instant>>,
+}
+
+impl ModelChain {
+    pub fn new(primary: ModelChoice) -> Self {
+        Self {
+            escalation: vec![(1, primary)],
+            fallback: Vec::new(),
+            unavailable: Mutex::default(),
+        }
+    }
+
+    /// Use `choice` from attempt `from_attempt` of a step on.
+    pub fn escalate(mut self, from_attempt: u32, choice: ModelChoice) -> Self {
+        self.escalation.push((from_attempt.max(1), choice));
+        self.escalation.sort_by_key(|(from, _)| *from);
+        self
+    }
+
+    /// Try `choice`, in the order added, when the models before it are unavailable.
+    pub fn fallback(mut self, choice: ModelChoice) -> Self {
+        self.fallback.push(choice);
+        self
+    }
+
+    /// The model the chain starts with on `attempt` (1-based).
+    pub fn for_attempt(&self, attempt: u32) -> &ModelChoice {
+        self.escalation
+            .iter()
+            .rev()
+            .find(|(from, _)| *from <= attempt)
+            .map(|(_, choice)| choice)
+            .unwrap_or(&self.escalation[0].1)
+    }
+
+    /// Models to try on `attempt`: the escalation model, then the fallbacks,
+    /// with recently unavailable ones moved to the end.
+    fn candidates(&self, attempt: u32) -> Vec<&ModelChoice> {
+        let mut candidates = vec![self.for_attempt(attempt)];
+        for choice in &self.fallback {
+            if candidates.iter().all(|c| c.name != choice.name) {
+                candidates.push(choice);
+            }
+        }
+        let unavailable = self.unavailable.lock().unwrap();
+        let cooling = |choice: &&ModelChoice| {
+            unavailable
+                .get(&choice.name)
+                .is_some_and(|since| since.elapsed() < UNAVAILABLE_COOLDOWN)
+        };
+        let (cooling, ready): (Vec<_>, Vec<_>) = candidates.into_iter().partition(cooling);
+        ready.into_iter().chain(cooling).collect()
+    }
+
+    /// Run `call` with the model for `attempt`, moving on to the next
+    /// candidate when a provider is unavailable. Returns the model that answered.
+    pub async fn run<'a, T, F, Fut>(&'a self, attempt: u32, call: F) -> Result<(T, &'a ModelChoice)>
+    where
+        F: Fn(&'a ModelChoice) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let candidates = self.candidates(attempt);
+        let mut remaining = candidates.len();
+        for choice in candidates {
+            remaining -= 1;
+            match call(choice).await {
+                Ok(value) => {
+                    self.unavailable.lock().unwrap().remove(&choice.name);
+                    return Ok((value, choice));
+                }
+                Err(error) if remaining > 0 && provider_unavailable(&error) => {
+                    println!("{} unavailable ({:#}), falling back", choice.name, error);
+                    self.unavailable
+                        .lock()
+                        .unwrap()
+                        .insert(choice.name.clone(), Instant::now());
+                }
+                Err(error) => return Err(error),
+            }
+        }
+        unreachable!("a model chain always has a primary model")
+    }
+}
+
+/// Failures of the endpoint rather than the request: outages, rate limits
+/// that outlasted the retries, and rejected credentials.
+fn provider_unavailable(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<LlmError>())
+        .is_some_and(|error| {
+            error.is_retryable()
+                || matches!(
+                    error,
+                    LlmError::Api {
+                        status: 401 | 403,
+                        ..
+                    }
+                )
+        })
+}
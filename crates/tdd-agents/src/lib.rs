@@ -1,4 +1,14 @@
 pub mod agent;
+pub mod chain;
+pub mod planner;
 pub mod prompts;
+pub mod reviewer;
+pub mod tools;
+pub mod usage;
 
-pub use agent::LlmAgent;
+pub use agent::{edit_plan_schema, LlmAgent};
+pub use chain::{ModelChain, ModelChoice};
+pub use planner::LlmPlanner;
+pub use reviewer::LlmReviewer;
+pub use tools::{ToolExecution, ToolSettings, Toolbox};
+pub use usage::LlmUsageMeter;
@@ -0,0 +1,64 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use tdd_core::{Language, Planner};
+use tdd_llm::{GenerationParams, LlmClient, Message, ResponseFormat};
+
+use crate::agent::strip_code_fences;
+use crate::prompts::planner_prompt;
+
+#[derive(Deserialize)]
+struct PlannedTests {
+    tests: Vec<String>,
+}
+
+pub struct LlmPlanner {
+    llm: LlmClient,
+    model: String,
+    params: GenerationParams,
+    language: Language,
+}
+
+impl LlmPlanner {
+    pub fn new(llm: LlmClient, model: String, params: GenerationParams) -> Self {
+        Self {
+            llm,
+            model,
+            params,
+            language: Language::default(),
+        }
+    }
+
+    /// Plan for a kata in `language` (Rust by default).
+    pub fn with_language(mut self, language: Language) -> Self {
+        self.language = language;
+        self
+    }
+}
+
+#[async_trait]
+impl Planner for LlmPlanner {
+    async fn plan_tests(&self, kata_description: &str) -> Result<Vec<String>> {
+        let messages = vec![
+            Message::system(planner_prompt(self.language)),
+            Message::user(format!("Kata: {}", kata_description)),
+        ];
+
+        let response = self
+            .llm
+            .chat_json(
+                &self.model,
+                messages,
+                &self.params,
+                ResponseFormat::JsonObject,
+            )
+            .await?;
+
+        let planned: PlannedTests = serde_json::from_str(strip_code_fences(&response))
+            .context("Failed to parse test list JSON")?;
+        if planned.tests.is_empty() {
+            anyhow::bail!("Planner returned an empty test list");
+        }
+        Ok(planned.tests)
+    }
+}
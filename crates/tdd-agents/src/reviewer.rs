@@ -0,0 +1,97 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use tdd_core::{Language, ReviewRequest, ReviewVerdict, Reviewer, Role, RunnerOutcome};
+use tdd_llm::{GenerationParams, LlmClient, Message, ResponseFormat};
+
+use crate::agent::strip_code_fences;
+use crate::prompts::{reviewer_prompt, system_prompt};
+
+/// Verification output is cut to this many characters in the review prompt.
+const MAX_OUTPUT_CHARS: usize = 4000;
+
+pub struct LlmReviewer {
+    llm: LlmClient,
+    model: String,
+    params: GenerationParams,
+    language: Language,
+    prompt_override: Option<String>,
+}
+
+impl LlmReviewer {
+    pub fn new(llm: LlmClient, model: String, params: GenerationParams) -> Self {
+        Self {
+            llm,
+            model,
+            params,
+            language: Language::default(),
+            prompt_override: None,
+        }
+    }
+
+    /// Review with the prompts and conventions of `language` (Rust by default).
+    pub fn with_language(mut self, language: Language) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// Replace the built-in reviewer prompt.
+    pub fn with_system_prompt(mut self, prompt: String) -> Self {
+        self.prompt_override = Some(prompt);
+        self
+    }
+}
+
+fn render_outcome(outcome: &RunnerOutcome) -> String {
+    let output = format!("{}{}", outcome.stdout, outcome.stderr);
+    let skip = output.chars().count().saturating_sub(MAX_OUTPUT_CHARS);
+    format!(
+        "{}\n{}",
+        outcome.headline(),
+        output.chars().skip(skip).collect::<String>()
+    )
+}
+
+#[async_trait]
+impl Reviewer for LlmReviewer {
+    async fn review(&self, request: &ReviewRequest) -> Result<ReviewVerdict> {
+        let expectation = match request.role {
+            Role::Tester => "The new test must compile and fail.",
+            Role::Implementor | Role::Refactorer => "All tests must pass.",
+        };
+        let user_prompt = format!(
+            "Step: {}\nActing Role: {:?}\nKata: {}\n\nInstructions given to the {}:\n{}\n\nCommit Message: {}\nNotes: {}\n\nDiff:\n{}\n\nVerification ({})\nCheck: {}\nTests: {}",
+            request.step_index,
+            request.role,
+            request.kata_description,
+            request.role.as_str(),
+            request
+                .instructions
+                .clone()
+                .unwrap_or_else(|| system_prompt(request.role, self.language)),
+            request.commit_message,
+            request.notes,
+            request.diff,
+            expectation,
+            render_outcome(&request.check),
+            render_outcome(&request.test),
+        );
+
+        let prompt = self
+            .prompt_override
+            .clone()
+            .unwrap_or_else(|| reviewer_prompt(self.language));
+        let messages = vec![Message::system(prompt), Message::user(user_prompt)];
+
+        let response = self
+            .llm
+            .chat_json(
+                &self.model,
+                messages,
+                &self.params,
+                ResponseFormat::JsonObject,
+            )
+            .await?;
+
+        serde_json::from_str(strip_code_fences(&response)).context("Failed to parse review JSON")
+    }
+}
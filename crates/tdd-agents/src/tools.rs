@@ -0,0 +1,230 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use tdd_core::{Runner, RunnerOutcome};
+use tdd_llm::{ToolCall, ToolDefinition};
+use tokio::fs;
+
+/// Tool output is cut to this many bytes before it is sent back to the model.
+const MAX_OUTPUT_BYTES: usize = 16 * 1024;
+const SKIPPED_DIRS: [&str; 3] = [".git", ".tdd", "target"];
+
+/// Settings for the tool-calling agent mode.
+#[derive(Clone)]
+pub struct ToolSettings {
+    /// Maximum number of model turns that may request tools before the final answer.
+    pub max_turns: u32,
+    /// Runs the tests and the check for the model, with the stage timeouts,
+    /// output limits and sandbox of the verification stages.
+    pub runner: Arc<dyn Runner>,
+}
+
+/// One tool invocation, as written to the per-step tool log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolExecution {
+    pub turn: u32,
+    pub name: String,
+    pub arguments: String,
+    pub ok: bool,
+    pub output: String,
+}
+
+/// Read-only exploration tools plus test/check runs against the work dir.
+pub struct Toolbox {
+    work_dir: PathBuf,
+    runner: Arc<dyn Runner>,
+}
+
+#[derive(Deserialize)]
+struct PathArgs {
+    #[serde(default)]
+    path: String,
+}
+
+#[derive(Deserialize)]
+struct GrepArgs {
+    pattern: String,
+    #[serde(default)]
+    path: String,
+}
+
+impl Toolbox {
+    pub fn new(work_dir: PathBuf, settings: &ToolSettings) -> Self {
+        Self {
+            work_dir,
+            runner: settings.runner.clone(),
+        }
+    }
+
+    pub fn definitions() -> Vec<ToolDefinition> {
+        let path_only = |description: &str| {
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string", "description": description}
+                },
+                "required": ["path"]
+            })
+        };
+        let no_args = serde_json::json!({"type": "object", "properties": {}});
+        vec![
+            ToolDefinition {
+                name: "read_file".to_string(),
+                description: "Read a file of the kata repository.".to_string(),
+                parameters: path_only("Path relative to the repository root"),
+            },
+            ToolDefinition {
+                name: "list_files".to_string(),
+                description: "List files under a directory of the kata repository.".to_string(),
+                parameters: path_only("Directory relative to the repository root, \"\" for all"),
+            },
+            ToolDefinition {
+                name: "grep".to_string(),
+                description: "Search files for a regular expression; returns path:line: text."
+                    .to_string(),
+                parameters: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "pattern": {"type": "string"},
+                        "path": {"type": "string", "description": "Directory to search, \"\" for all"}
+                    },
+                    "required": ["pattern"]
+                }),
+            },
+            ToolDefinition {
+                name: "run_tests".to_string(),
+                description: "Run the test suite on the current files.".to_string(),
+                parameters: no_args.clone(),
+            },
+            ToolDefinition {
+                name: "cargo_check".to_string(),
+                description: "Compile and lint the current files.".to_string(),
+                parameters: no_args,
+            },
+        ]
+    }
+
+    /// Execute a tool call. Tool failures are reported to the model, not raised.
+    pub async fn execute(&self, call: &ToolCall) -> (bool, String) {
+        let result = match call.function.name.as_str() {
+            "read_file" => self.read_file(&call.function.arguments).await,
+            "list_files" => self.list_files(&call.function.arguments),
+            "grep" => self.grep(&call.function.arguments),
+            "run_tests" => self.runner.test().await.map(|outcome| render(&outcome)),
+            "cargo_check" => self.runner.check().await.map(|outcome| render(&outcome)),
+            other => Err(anyhow::anyhow!("Unknown tool: {}", other)),
+        };
+        let (ok, mut output) = match result {
+            Ok(output) => (true, output),
+            Err(e) => (false, format!("Error: {:#}", e)),
+        };
+        if output.len() > MAX_OUTPUT_BYTES {
+            let mut cut = MAX_OUTPUT_BYTES;
+            while !output.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            output.truncate(cut);
+            output.push_str("\n... (truncated)");
+        }
+        (ok, output)
+    }
+
+    /// Resolve a model-supplied path, refusing anything outside the work dir.
+    fn resolve(&self, path: &str) -> Result<PathBuf> {
+        let relative = Path::new(path);
+        if relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            anyhow::bail!("Path must be relative to the repository root: {}", path);
+        }
+        Ok(self.work_dir.join(relative))
+    }
+
+    async fn read_file(&self, arguments: &str) -> Result<String> {
+        let args: PathArgs = serde_json::from_str(arguments).context("Invalid arguments")?;
+        let path = self.resolve(&args.path)?;
+        fs::read_to_string(&path)
+            .await
+            .context(format!("Failed to read file: {}", args.path))
+    }
+
+    fn list_files(&self, arguments: &str) -> Result<String> {
+        let args: PathArgs = serde_json::from_str(arguments).unwrap_or(PathArgs {
+            path: String::new(),
+        });
+        let files = self.walk(&self.resolve(&args.path)?)?;
+        Ok(files
+            .iter()
+            .map(|p| self.display(p))
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    fn grep(&self, arguments: &str) -> Result<String> {
+        let args: GrepArgs = serde_json::from_str(arguments).context("Invalid arguments")?;
+        let regex = Regex::new(&args.pattern).context("Invalid pattern")?;
+        let mut matches = Vec::new();
+        for file in self.walk(&self.resolve(&args.path)?)? {
+            let Ok(content) = std::fs::read_to_string(&file) else {
+                continue;
+            };
+            for (index, line) in content.lines().enumerate() {
+                if regex.is_match(line) {
+                    matches.push(format!("{}:{}: {}", self.display(&file), index + 1, line));
+                }
+            }
+        }
+        Ok(matches.join("\n"))
+    }
+
+    fn walk(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        if dir.is_file() {
+            files.push(dir.to_path_buf());
+            return Ok(files);
+        }
+        let mut pending = vec![dir.to_path_buf()];
+        while let Some(current) = pending.pop() {
+            for entry in std::fs::read_dir(&current).context(format!(
+                "Failed to list directory: {}",
+                self.display(&current)
+            ))? {
+                let path = entry?.path();
+                let skipped = path
+                    .file_name()
+                    .is_some_and(|name| SKIPPED_DIRS.iter().any(|d| name == *d));
+                if path.is_dir() && !skipped {
+                    pending.push(path);
+                } else if path.is_file() {
+                    files.push(path);
+                }
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    fn display(&self, path: &Path) -> String {
+        path.strip_prefix(&self.work_dir)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string()
+    }
+}
+
+fn render(outcome: &RunnerOutcome) -> String {
+    let status = if outcome.timed_out() {
+        "timed out"
+    } else if outcome.ok {
+        "success"
+    } else {
+        "failed"
+    };
+    format!(
+        "status: {}\n--- stdout ---\n{}\n--- stderr ---\n{}",
+        status, outcome.stdout, outcome.stderr
+    )
+}
@@ -0,0 +1,35 @@
+use std::sync::Arc;
+use tdd_core::{TokenUsage, UsageMeter};
+use tdd_llm::UsageRecorder;
+
+/// Feeds the calls collected by a [`UsageRecorder`] to the orchestrator.
+pub struct LlmUsageMeter {
+    recorder: Arc<UsageRecorder>,
+}
+
+impl LlmUsageMeter {
+    pub fn new(recorder: Arc<UsageRecorder>) -> Self {
+        Self { recorder }
+    }
+}
+
+impl UsageMeter for LlmUsageMeter {
+    fn take(&self) -> TokenUsage {
+        let mut total = TokenUsage::default();
+        for record in self.recorder.drain() {
+            if record.cache_hit {
+                total.cache_hits += 1;
+                continue;
+            }
+            total += TokenUsage {
+                calls: 1,
+                prompt_tokens: record.usage.prompt_tokens,
+                completion_tokens: record.usage.completion_tokens,
+                cached_tokens: record.usage.cached_tokens,
+                cost_usd: record.cost_usd.unwrap_or(0.0),
+                cache_hits: 0,
+            };
+        }
+        total
+    }
+}
@@ -0,0 +1,124 @@
+use anyhow::Result;
+use tdd_agents::{LlmAgent, ModelChain, ModelChoice};
+use tdd_core::{Agent, Role, SessionJournal, StepContext};
+use tdd_fixtures::{MockLlmServer, Reply};
+use tdd_llm::{GenerationParams, LlmClient, RetryPolicy};
+use tempfile::TempDir;
+
+fn context(attempt: u32) -> StepContext {
+    StepContext {
+        role: Role::Tester,
+        step_index: 1,
+        attempt,
+        kata_description: "add numbers".to_string(),
+        git_last_commit_msg: String::new(),
+        git_last_diff: String::new(),
+        repo_snapshot_paths: Vec::new(),
+        next_test: None,
+        journal: SessionJournal::default(),
+        feedback: None,
+    }
+}
+
+fn client(server: &MockLlmServer) -> LlmClient {
+    LlmClient::new(server.base_url(), String::new()).with_retry(RetryPolicy::none())
+}
+
+async fn plan_and_edit(agent: &LlmAgent, dir: &TempDir, ctx: &StepContext) -> Result<String> {
+    let plan = agent.plan(ctx).await?;
+    let plan_dir = dir.path().join(".tdd/plan");
+    std::fs::create_dir_all(&plan_dir)?;
+    std::fs::write(plan_dir.join("step-1-tester.md"), plan)?;
+    Ok(agent.edit(ctx).await?.model.unwrap_or_default())
+}
+
+#[tokio::test]
+async fn test_later_attempts_escalate_to_stronger_models() -> Result<()> {
+    let dir = TempDir::new()?;
+    let server = MockLlmServer::builder()
+        .role(
+            "tester",
+            Reply::edit_plan(&[("src/lib.rs", "")], "test: add"),
+        )
+        .start()
+        .await?;
+    let models = ModelChain::new(ModelChoice::new("mock:cheap", client(&server), "cheap"))
+        .escalate(
+            3,
+            ModelChoice::new("mock:strong", client(&server), "strong"),
+        );
+    let agent = LlmAgent::new(
+        Role::Tester,
+        client(&server),
+        "cheap".to_string(),
+        GenerationParams::default(),
+        dir.path().to_path_buf(),
+    )
+    .with_models(models);
+
+    assert_eq!(
+        plan_and_edit(&agent, &dir, &context(2)).await?,
+        "mock:cheap"
+    );
+    assert_eq!(
+        plan_and_edit(&agent, &dir, &context(3)).await?,
+        "mock:strong"
+    );
+
+    let sent: Vec<_> = server
+        .requests()
+        .iter()
+        .map(|request| {
+            request.body["model"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string()
+        })
+        .collect();
+    assert_eq!(sent, vec!["cheap", "strong"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unavailable_provider_falls_back() -> Result<()> {
+    let dir = TempDir::new()?;
+    let down = MockLlmServer::builder()
+        .role(
+            "tester",
+            Reply::Error {
+                status: 503,
+                message: "overloaded".to_string(),
+            },
+        )
+        .start()
+        .await?;
+    let backup = MockLlmServer::builder()
+        .role(
+            "tester",
+            Reply::edit_plan(&[("src/lib.rs", "")], "test: add"),
+        )
+        .start()
+        .await?;
+    let models = ModelChain::new(ModelChoice::new("down:model", client(&down), "model"))
+        .fallback(ModelChoice::new("backup:model", client(&backup), "model"));
+    let agent = LlmAgent::new(
+        Role::Tester,
+        client(&down),
+        "model".to_string(),
+        GenerationParams::default(),
+        dir.path().to_path_buf(),
+    )
+    .with_models(models);
+
+    assert_eq!(
+        plan_and_edit(&agent, &dir, &context(1)).await?,
+        "backup:model"
+    );
+    // The failed provider is skipped while it cools down.
+    assert_eq!(
+        plan_and_edit(&agent, &dir, &context(2)).await?,
+        "backup:model"
+    );
+    assert_eq!(down.requests().len(), 1);
+    Ok(())
+}
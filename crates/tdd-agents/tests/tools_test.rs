@@ -0,0 +1,90 @@
+use anyhow::Result;
+use std::fs;
+use std::sync::Arc;
+use tdd_agents::{ToolSettings, Toolbox};
+use tdd_exec::{ProcessRunner, StageTimeouts};
+use tdd_llm::{FunctionCall, ToolCall};
+use tempfile::TempDir;
+
+fn call(name: &str, arguments: &str) -> ToolCall {
+    ToolCall {
+        id: "call-1".to_string(),
+        kind: "function".to_string(),
+        function: FunctionCall {
+            name: name.to_string(),
+            arguments: arguments.to_string(),
+        },
+    }
+}
+
+#[tokio::test]
+async fn test_toolbox_explores_work_dir() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    fs::create_dir_all(temp_dir.path().join("src"))?;
+    fs::write(
+        temp_dir.path().join("src/lib.rs"),
+        "pub fn add() -> i32 {\n    0\n}\n",
+    )?;
+    fs::create_dir_all(temp_dir.path().join("target"))?;
+    fs::write(temp_dir.path().join("target/ignored.rs"), "pub fn add() {}")?;
+
+    let toolbox = Toolbox::new(
+        temp_dir.path().to_path_buf(),
+        &ToolSettings {
+            max_turns: 3,
+            runner: Arc::new(ProcessRunner::new(
+                vec![],
+                vec![],
+                vec!["echo".to_string(), "tests ran".to_string()],
+            )),
+        },
+    );
+
+    let (ok, output) = toolbox
+        .execute(&call("list_files", r#"{"path": ""}"#))
+        .await;
+    assert!(ok);
+    assert_eq!(output, "src/lib.rs");
+
+    let (ok, output) = toolbox
+        .execute(&call("grep", r#"{"pattern": "fn add"}"#))
+        .await;
+    assert!(ok);
+    assert_eq!(output, "src/lib.rs:1: pub fn add() -> i32 {");
+
+    let (ok, output) = toolbox
+        .execute(&call("read_file", r#"{"path": "../secret"}"#))
+        .await;
+    assert!(!ok);
+    assert!(output.contains("relative to the repository root"));
+
+    let (ok, output) = toolbox.execute(&call("run_tests", "{}")).await;
+    assert!(ok);
+    assert!(output.contains("tests ran"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tool_commands_are_killed_after_the_stage_timeout() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let runner = ProcessRunner::new(vec![], vec![], vec!["sleep".to_string(), "30".to_string()])
+        .with_timeouts(StageTimeouts {
+            test: 1,
+            ..StageTimeouts::default()
+        });
+    let toolbox = Toolbox::new(
+        temp_dir.path().to_path_buf(),
+        &ToolSettings {
+            max_turns: 3,
+            runner: Arc::new(runner),
+        },
+    );
+
+    let started = std::time::Instant::now();
+    let (ok, output) = toolbox.execute(&call("run_tests", "{}")).await;
+    assert!(ok);
+    assert!(output.starts_with("status: timed out"));
+    assert!(started.elapsed() < std::time::Duration::from_secs(10));
+    Ok(())
+}
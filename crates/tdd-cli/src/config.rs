@@ -1,26 +1,283 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tdd_core::Language;
+use tdd_exec::{CoverageConfig, SandboxConfig, StageTimeouts, DEFAULT_MAX_OUTPUT_BYTES};
+use tdd_llm::{
+    ApiKind, Auth, CacheConfig, GenerationParams, ModelCapabilities, ModelPrice, ModelRef,
+    OllamaOptions, ProviderConfig, RateLimits, RetryPolicy, StructuredOutput, TranscriptLevel,
+};
 use tokio::fs;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RoleConfig {
     pub model: String,
-    pub temperature: f32,
+    /// temperature, max_tokens, top_p, seed, stop and reasoning_effort; those
+    /// the model does not support are left out of its requests.
+    #[serde(flatten)]
+    pub params: GenerationParams,
+    /// Replaces the built-in system prompt for the role.
+    #[serde(default)]
+    pub prompt: Option<String>,
+    /// Enables the tool-calling agent mode when present.
+    #[serde(default)]
+    pub tools: Option<ToolsConfig>,
+    /// Stronger models taking over on later attempts of a step.
+    #[serde(default)]
+    pub escalate: Vec<EscalationConfig>,
+    /// Models tried in order when the current one's provider is unavailable.
+    #[serde(default)]
+    pub fallback: Vec<String>,
 }
 
+impl RoleConfig {
+    /// Every model the role may use.
+    pub fn models(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.model.as_str())
+            .chain(self.escalate.iter().map(|step| step.model.as_str()))
+            .chain(self.fallback.iter().map(String::as_str))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscalationConfig {
+    /// First attempt (1-based) that uses `model`.
+    pub from_attempt: u32,
+    pub model: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolsConfig {
+    #[serde(default = "default_max_tool_turns")]
+    pub max_turns: u32,
+}
+
+fn default_plan_tests() -> bool {
+    true
+}
+
+fn default_max_tool_turns() -> u32 {
+    8
+}
+
+fn default_stream() -> bool {
+    true
+}
+
+fn default_idle_timeout_secs() -> u64 {
+    60
+}
+
+fn default_connect_timeout_secs() -> u64 {
+    30
+}
+
+fn default_request_timeout_secs() -> u64 {
+    120
+}
+
+/// One LLM endpoint: the legacy `llm` section or an entry of `providers`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LlmConfig {
     pub base_url: String,
-    pub api_key_env: String,
+    /// Environment variable holding the API key; none for keyless local servers.
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+    /// Wire protocol of `base_url`: openai, anthropic, gemini or ollama.
+    #[serde(default)]
+    pub api: ApiKind,
+    /// How the key is sent: bearer, api-key (Azure) or `{header: NAME}`.
+    /// Defaults to the protocol's own scheme.
+    #[serde(default)]
+    pub auth: Option<Auth>,
+    /// Extra headers sent with every request.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Query parameters added to every request, e.g. `api-version`.
+    #[serde(default)]
+    pub query: BTreeMap<String, String>,
+    /// Proxy URL (http, https or socks5) for all requests to this provider.
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// PEM file of extra trusted CA certificates, e.g. for a TLS-inspecting gateway.
+    #[serde(default)]
+    pub ca_bundle: Option<PathBuf>,
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    /// Upper bound for non-streaming requests.
+    #[serde(default = "default_request_timeout_secs")]
+    pub request_timeout_secs: u64,
+    /// Backoff for rate limits, overload and network failures.
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    /// Requests per minute, tokens per minute and concurrent requests, shared
+    /// by every role using this provider. Requests over the limit are queued.
+    #[serde(default)]
+    pub limits: RateLimits,
+    /// Stream responses; stalled streams are cut off after `idle_timeout_secs`.
+    #[serde(default = "default_stream")]
+    pub stream: bool,
+    #[serde(default = "default_idle_timeout_secs")]
+    pub idle_timeout_secs: u64,
+    /// JSON response formats the endpoint accepts: json_schema, json_object or
+    /// unsupported. Defaults per `api`; lowered automatically when rejected.
+    #[serde(default)]
+    pub structured_output: Option<StructuredOutput>,
+    /// `num_ctx` and `keep_alive` for `api: ollama`.
+    #[serde(default)]
+    pub ollama: OllamaOptions,
 }
 
+impl LlmConfig {
+    fn builtin(name: &str) -> Option<Self> {
+        let (base_url, api_key_env, api) = match name {
+            "openai" => (
+                "https://api.openai.com/v1",
+                Some("OPENAI_API_KEY"),
+                ApiKind::OpenAi,
+            ),
+            "anthropic" => (
+                "https://api.anthropic.com/v1",
+                Some("ANTHROPIC_API_KEY"),
+                ApiKind::Anthropic,
+            ),
+            "gemini" => (
+                "https://generativelanguage.googleapis.com/v1beta",
+                Some("GEMINI_API_KEY"),
+                ApiKind::Gemini,
+            ),
+            "ollama" => ("http://localhost:11434", None, ApiKind::Ollama),
+            _ => return None,
+        };
+        Some(Self {
+            base_url: base_url.to_string(),
+            api_key_env: api_key_env.map(str::to_string),
+            api,
+            auth: None,
+            headers: BTreeMap::new(),
+            query: BTreeMap::new(),
+            proxy: None,
+            ca_bundle: None,
+            connect_timeout_secs: default_connect_timeout_secs(),
+            request_timeout_secs: default_request_timeout_secs(),
+            retry: RetryPolicy::default(),
+            limits: RateLimits::default(),
+            stream: default_stream(),
+            idle_timeout_secs: default_idle_timeout_secs(),
+            structured_output: None,
+            ollama: OllamaOptions::default(),
+        })
+    }
+
+    pub fn provider_config(&self) -> Result<ProviderConfig> {
+        let api_key = match &self.api_key_env {
+            Some(var) => env::var(var).context(format!("{} not set", var))?,
+            None => String::new(),
+        };
+        let mut provider = ProviderConfig::new(self.api, self.base_url.clone(), api_key)
+            .connect_timeout(Duration::from_secs(self.connect_timeout_secs))
+            .request_timeout(Duration::from_secs(self.request_timeout_secs))
+            .idle_timeout(Duration::from_secs(self.idle_timeout_secs))
+            .ollama(self.ollama.clone());
+        if let Some(auth) = &self.auth {
+            provider = provider.auth(auth.clone());
+        }
+        for (name, value) in &self.query {
+            provider = provider.query(name, value);
+        }
+        if let Some(proxy) = &self.proxy {
+            provider = provider.proxy(proxy)?;
+        }
+        if let Some(ca_bundle) = &self.ca_bundle {
+            provider = provider.ca_bundle(ca_bundle)?;
+        }
+        if let Some(structured_output) = self.structured_output {
+            provider = provider.structured_output(structured_output);
+        }
+        for (name, value) in &self.headers {
+            provider = provider.header(name, value);
+        }
+        Ok(provider)
+    }
+}
+
+/// Verification commands; each defaults to the one of the kata's language.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CiConfig {
-    pub test_cmd: Vec<String>,
-    pub check_cmd: Vec<String>,
-    pub fmt_cmd: Vec<String>,
+    #[serde(default)]
+    pub test_cmd: Option<Vec<String>>,
+    #[serde(default)]
+    pub check_cmd: Option<Vec<String>>,
+    #[serde(default)]
+    pub fmt_cmd: Option<Vec<String>>,
+    /// Seconds each stage may run before it is killed with everything it started.
+    #[serde(default)]
+    pub timeouts: StageTimeouts,
+    /// Output kept per stream of each command; the middle of longer output is dropped.
+    #[serde(default = "default_max_output_bytes")]
+    pub max_output_bytes: usize,
+    /// Run a cargo `check_cmd` with `--message-format=json` and parse its diagnostics.
+    #[serde(default = "default_json_diagnostics")]
+    pub json_diagnostics: bool,
+    /// Apply machine-applicable compiler suggestions when the check fails, then check again.
+    #[serde(default)]
+    pub auto_fix: bool,
+    /// Resource limits, network and write restrictions per stage (Linux).
+    #[serde(default)]
+    pub sandbox: SandboxConfig,
+    /// Line coverage after green steps, via cargo-llvm-cov or cargo-tarpaulin.
+    #[serde(default)]
+    pub coverage: CoverageConfig,
+}
+
+impl Default for CiConfig {
+    fn default() -> Self {
+        Self {
+            test_cmd: None,
+            check_cmd: None,
+            fmt_cmd: None,
+            timeouts: StageTimeouts::default(),
+            max_output_bytes: DEFAULT_MAX_OUTPUT_BYTES,
+            json_diagnostics: true,
+            auto_fix: false,
+            sandbox: SandboxConfig::default(),
+            coverage: CoverageConfig::default(),
+        }
+    }
+}
+
+fn default_json_diagnostics() -> bool {
+    true
+}
+
+fn default_max_output_bytes() -> usize {
+    DEFAULT_MAX_OUTPUT_BYTES
+}
+
+/// How streamed LLM responses are shown while they arrive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LlmOutput {
+    /// Print tokens as they arrive.
+    #[default]
+    Live,
+    /// Print a dot per chunk of output.
+    Progress,
+    Quiet,
+}
+
+/// What goes into `.tdd/logs` besides the step logs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// Detail of the per-call transcripts in `.tdd/logs/llm`: off, summary or full.
+    pub llm: TranscriptLevel,
+    /// Regular expressions for secrets to mask in transcripts, on top of API
+    /// keys, auth headers and common key formats.
+    pub redact: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,11 +289,38 @@ pub struct CommitConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub kata_description: String,
-    pub language: String,
+    /// Selects scaffolding, default commands, test-output parsing and prompts.
+    #[serde(default)]
+    pub language: Language,
     pub steps: u32,
     pub max_attempts_per_agent: u32,
+    /// Plan a test list from the kata before the first step.
+    #[serde(default = "default_plan_tests")]
+    pub plan_tests: bool,
     pub roles: HashMap<String, RoleConfig>,
-    pub llm: LlmConfig,
+    /// Endpoint for model strings without a `provider:` prefix.
+    #[serde(default)]
+    pub llm: Option<LlmConfig>,
+    /// Named endpoints, selected by the `provider:` prefix of a role's model.
+    /// `openai`, `anthropic`, `gemini` and `ollama` are predefined and may be
+    /// overridden.
+    #[serde(default)]
+    pub providers: HashMap<String, LlmConfig>,
+    #[serde(default)]
+    pub llm_output: LlmOutput,
+    /// USD per million tokens, keyed by bare model name, for cost estimates.
+    #[serde(default)]
+    pub pricing: HashMap<String, ModelPrice>,
+    /// Supported parameters and limits, keyed by bare model name, for models
+    /// missing from (or wrong in) the built-in table.
+    #[serde(default)]
+    pub models: HashMap<String, ModelCapabilities>,
+    /// Opt-in on-disk cache of LLM responses.
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
     pub ci: CiConfig,
     pub commit: CommitConfig,
 }
@@ -51,11 +335,76 @@ impl Config {
         Ok(config)
     }
 
-    pub fn default_yaml() -> &'static str {
-        r#"kata_description: "kata.md"
-language: "rust"
+    /// Split a role's model string into the provider to use and the bare model name.
+    pub fn resolve_model(&self, spec: &str) -> Result<(String, LlmConfig, ModelRef)> {
+        let model = ModelRef::parse(spec, |name| {
+            self.providers.contains_key(name) || LlmConfig::builtin(name).is_some()
+        });
+        match &model.provider {
+            Some(name) => {
+                // Before `providers` existed, `llm` configured OpenAI or an
+                // endpoint standing in for it (a proxy, Azure).
+                let legacy = self.llm.clone().filter(|_| name == "openai");
+                let provider = self
+                    .providers
+                    .get(name)
+                    .cloned()
+                    .or(legacy)
+                    .or_else(|| LlmConfig::builtin(name))
+                    .context(format!("Unknown provider: {}", name))?;
+                Ok((name.clone(), provider, model))
+            }
+            None => {
+                let provider = self.llm.clone().context(format!(
+                    "Model \"{}\" has no provider prefix and no `llm` section is configured",
+                    spec
+                ))?;
+                Ok(("llm".to_string(), provider, model))
+            }
+        }
+    }
+
+    pub fn fmt_cmd(&self) -> Vec<String> {
+        self.ci
+            .fmt_cmd
+            .clone()
+            .unwrap_or_else(|| self.language.profile().fmt_cmd())
+    }
+
+    pub fn check_cmd(&self) -> Vec<String> {
+        self.ci
+            .check_cmd
+            .clone()
+            .unwrap_or_else(|| self.language.profile().check_cmd())
+    }
+
+    pub fn test_cmd(&self) -> Vec<String> {
+        self.ci
+            .test_cmd
+            .clone()
+            .unwrap_or_else(|| self.language.profile().test_cmd())
+    }
+
+    /// The tdd.yaml `init` writes for a kata in `language`.
+    pub fn default_yaml(language: Language) -> String {
+        let profile = language.profile();
+        let list = |parts: &[&str]| {
+            let quoted: Vec<String> = parts.iter().map(|part| format!("\"{}\"", part)).collect();
+            format!("[{}]", quoted.join(", "))
+        };
+        DEFAULT_YAML
+            .replace("{language}", language.as_str())
+            .replace("{test_cmd}", &list(profile.test_cmd))
+            .replace("{check_cmd}", &list(profile.check_cmd))
+            .replace("{fmt_cmd}", &list(profile.fmt_cmd))
+    }
+}
+
+const DEFAULT_YAML: &str = r#"kata_description: "kata.md"
+language: "{language}"
 steps: 20
 max_attempts_per_agent: 5
+plan_tests: true
 roles:
   tester:
     model: "openai:gpt-4o"
@@ -66,16 +415,28 @@ roles:
   refactorer:
     model: "openai:gpt-4o"
     temperature: 0.3
-llm:
-  base_url: "https://api.openai.com/v1"
-  api_key_env: "OPENAI_API_KEY"
+providers:
+  openai:
+    base_url: "https://api.openai.com/v1"
+    api_key_env: "OPENAI_API_KEY"
+    api: "openai"
+llm_output: "live"
+logging:
+  llm: "summary"
+pricing:
+  gpt-4o:
+    input_per_mtok: 2.5
+    cached_input_per_mtok: 1.25
+    output_per_mtok: 10.0
 ci:
-  test_cmd: ["cargo", "test", "--all"]
-  check_cmd: ["cargo", "clippy", "--all", "--", "-D", "warnings"]
-  fmt_cmd: ["cargo", "fmt"]
+  test_cmd: {test_cmd}
+  check_cmd: {check_cmd}
+  fmt_cmd: {fmt_cmd}
+  timeouts:
+    fmt: 60
+    check: 600
+    test: 600
 commit:
   author_name: "TDD Machine"
   author_email: "tdd@local"
-"#
-    }
-}
+"#;
@@ -1,15 +1,23 @@
 mod config;
+mod output;
+mod providers;
 
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
 use config::Config;
 use dotenv::dotenv;
+use providers::LlmClients;
+use std::collections::HashMap;
 use std::env;
-
-use tdd_agents::LlmAgent;
-use tdd_core::{Orchestrator, Role, TddOrchestrator, Vcs};
-use tdd_exec::{GitVcs, ProcessRunner};
-use tdd_llm::LlmClient;
+use std::path::Path;
+use std::sync::Arc;
+
+use tdd_agents::{
+    LlmAgent, LlmPlanner, LlmReviewer, LlmUsageMeter, ModelChain, ModelChoice, ToolSettings,
+};
+use tdd_core::{Language, Orchestrator, Role, Runner, TddOrchestrator, Vcs};
+use tdd_exec::{GitVcs, KataConstraints, ProcessRunner, RustConstraintChecker};
+use tdd_llm::{ApiKind, OllamaProvider, ResponseCache, TranscriptLevel, TranscriptLog};
 use tokio::fs;
 
 #[derive(Parser)]
@@ -17,12 +25,22 @@ use tokio::fs;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Bypass the LLM response cache even if enabled in tdd.yaml
+    #[arg(long, global = true)]
+    no_cache: bool,
+    /// Ignore cached LLM responses but store the fresh ones
+    #[arg(long, global = true)]
+    refresh_cache: bool,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Initialize repo and scaffolding
-    Init,
+    Init {
+        /// Language of the kata: rust, python, typescript or go
+        #[arg(long)]
+        language: Option<Language>,
+    },
     /// Run N full TDD steps
     Run {
         #[arg(long, default_value_t = 20)]
@@ -42,9 +60,9 @@ async fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Init => init().await?,
-        Commands::Run { steps } => run(steps).await?,
-        Commands::Step => run(1).await?, // Step runs 1 step? Or just one agent turn? Orchestrator::next() is one turn.
+        Commands::Init { language } => init(language).await?,
+        Commands::Run { steps } => run(steps, &cli).await?,
+        Commands::Step => run(1, &cli).await?, // Step runs 1 step? Or just one agent turn? Orchestrator::next() is one turn.
         Commands::Status => status().await?,
         Commands::Doctor => doctor().await?,
     }
@@ -52,15 +70,27 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-async fn init() -> Result<()> {
+async fn init(language: Option<Language>) -> Result<()> {
     let cwd = env::current_dir()?;
     println!("Initializing TDD workspace in {:?}", cwd);
 
-    // Create tdd.yaml
-    if !cwd.join("tdd.yaml").exists() {
-        fs::write(cwd.join("tdd.yaml"), Config::default_yaml()).await?;
+    // Create tdd.yaml, or scaffold for the language it names
+    let language = if cwd.join("tdd.yaml").exists() {
+        let configured = Config::load(cwd.join("tdd.yaml")).await?.language;
+        if language.is_some_and(|language| language != configured) {
+            println!(
+                "tdd.yaml already selects language {}; using it",
+                configured.as_str()
+            );
+        }
+        configured
+    } else {
+        let language = language.unwrap_or_default();
+        fs::write(cwd.join("tdd.yaml"), Config::default_yaml(language)).await?;
         println!("Created tdd.yaml");
-    }
+        language
+    };
+    let profile = language.profile();
 
     // Create kata.md
     if !cwd.join("kata.md").exists() {
@@ -74,7 +104,9 @@ async fn init() -> Result<()> {
 
     // Create .gitignore
     if !cwd.join(".gitignore").exists() {
-        fs::write(cwd.join(".gitignore"), "/target\n/.tdd\n.env\n").await?;
+        let mut gitignore: Vec<&str> = profile.gitignore.to_vec();
+        gitignore.extend(["/.tdd", ".env"]);
+        fs::write(cwd.join(".gitignore"), gitignore.join("\n") + "\n").await?;
         println!("Created .gitignore");
     }
 
@@ -83,66 +115,94 @@ async fn init() -> Result<()> {
     vcs.init_if_needed()?;
     println!("Initialized git repo");
 
-    // Create Cargo.toml if not exists (scaffold rust project)
-    if !cwd.join("Cargo.toml").exists() {
-        let cargo_toml = r#"[package]
-name = "tdd-kata"
-version = "0.1.0"
-edition = "2021"
-
-[dependencies]
-
-[dev-dependencies]
-"#;
-        fs::write(cwd.join("Cargo.toml"), cargo_toml).await?;
-        println!("Created Cargo.toml");
-
-        fs::create_dir_all(cwd.join("src")).await?;
-        fs::write(cwd.join("src/lib.rs"), "").await?;
-        println!("Created src/lib.rs");
+    // Scaffold the project unless its manifest (the first file) exists
+    let manifest = profile.scaffold.first().map(|(path, _)| *path);
+    if manifest.is_some_and(|manifest| !cwd.join(manifest).exists()) {
+        for (path, content) in profile.scaffold {
+            let path = cwd.join(path);
+            if path.exists() {
+                continue;
+            }
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            fs::write(&path, content).await?;
+            println!("Created {}", path.strip_prefix(&cwd)?.display());
+        }
     }
 
     Ok(())
 }
 
-async fn run(steps: u32) -> Result<()> {
+async fn run(steps: u32, cli: &Cli) -> Result<()> {
     let cwd = env::current_dir()?;
     let config = Config::load(cwd.join("tdd.yaml")).await?;
 
-    let api_key =
-        env::var(&config.llm.api_key_env).context(format!("{} not set", config.llm.api_key_env))?;
+    let mut clients = LlmClients::new(&config);
+    if config.cache.enabled && !cli.no_cache {
+        let cache = ResponseCache::from_config(&config.cache, &cwd).refresh(cli.refresh_cache);
+        println!(
+            "LLM response cache enabled ({}){}",
+            config.cache.dir(&cwd).display(),
+            if cli.refresh_cache {
+                ", refreshing"
+            } else {
+                ""
+            }
+        );
+        clients = clients.with_cache(cache);
+    }
+    if config.logging.llm != TranscriptLevel::Off {
+        let log = TranscriptLog::new(
+            cwd.join(".tdd").join("logs").join("llm"),
+            config.logging.llm,
+        )
+        .redact_patterns(&config.logging.redact)
+        .context("Invalid pattern in logging.redact")?;
+        clients = clients.with_transcripts(log);
+    }
 
-    let llm_client = LlmClient::new(config.llm.base_url.clone(), api_key);
+    let mut coverage = config.ci.coverage.clone();
+    if coverage.enabled && coverage.command.is_none() && config.language != Language::Rust {
+        println!(
+            "Coverage of {} katas needs ci.coverage.command; not measuring it",
+            config.language.profile().name
+        );
+        coverage.enabled = false;
+    }
+    let runner: Arc<dyn Runner> = Arc::new(
+        ProcessRunner::new(config.fmt_cmd(), config.check_cmd(), config.test_cmd())
+            .with_work_dir(&cwd)
+            .with_test_format(config.language.profile().test_output)
+            .with_timeouts(config.ci.timeouts.clone())
+            .with_max_output_bytes(config.ci.max_output_bytes)
+            .with_json_diagnostics(config.ci.json_diagnostics)
+            .with_auto_fix(config.ci.auto_fix)
+            .with_sandbox(&config.ci.sandbox)?
+            .with_coverage(&coverage),
+    );
 
-    let tester = Box::new(LlmAgent::new(
+    let tester = Box::new(build_agent(
         Role::Tester,
-        llm_client.clone(),
-        config.roles.get("tester").unwrap().model.clone(),
-        config.roles.get("tester").unwrap().temperature,
-        cwd.clone(),
-    ));
-
-    let implementor = Box::new(LlmAgent::new(
+        &config,
+        &mut clients,
+        &cwd,
+        &runner,
+    )?);
+    let implementor = Box::new(build_agent(
         Role::Implementor,
-        llm_client.clone(),
-        config.roles.get("implementor").unwrap().model.clone(),
-        config.roles.get("implementor").unwrap().temperature,
-        cwd.clone(),
-    ));
-
-    let refactorer = Box::new(LlmAgent::new(
+        &config,
+        &mut clients,
+        &cwd,
+        &runner,
+    )?);
+    let refactorer = Box::new(build_agent(
         Role::Refactorer,
-        llm_client.clone(),
-        config.roles.get("refactorer").unwrap().model.clone(),
-        config.roles.get("refactorer").unwrap().temperature,
-        cwd.clone(),
-    ));
-
-    let runner = Box::new(ProcessRunner::new(
-        config.ci.fmt_cmd,
-        config.ci.check_cmd,
-        config.ci.test_cmd,
-    ));
+        &config,
+        &mut clients,
+        &cwd,
+        &runner,
+    )?);
 
     let vcs = Box::new(GitVcs::new(&cwd));
 
@@ -150,23 +210,142 @@ async fn run(steps: u32) -> Result<()> {
         .await
         .context("Failed to read kata description")?;
 
+    let constraints = KataConstraints::from_kata(&kata_description)?;
+
     let mut orchestrator = TddOrchestrator::new(
         tester,
         implementor,
         refactorer,
-        runner,
+        Box::new(runner),
         vcs,
         kata_description,
         config.max_attempts_per_agent,
-        cwd,
-    );
+        cwd.clone(),
+    )
+    .with_usage_meter(Box::new(LlmUsageMeter::new(clients.usage())))
+    .with_language(config.language)
+    .with_fail_on_uncovered(coverage.enabled && coverage.fail_on_uncovered);
+
+    if !constraints.is_empty() && config.language != Language::Rust {
+        println!(
+            "Kata constraints are only enforced for Rust katas; ignoring {:?}",
+            constraints
+        );
+    } else if !constraints.is_empty() {
+        println!("Enforcing kata constraints: {:?}", constraints);
+        orchestrator = orchestrator
+            .with_constraint_checker(Box::new(RustConstraintChecker::new(&cwd, constraints)));
+    }
+
+    if let Some(reviewer_config) = config.roles.get("reviewer") {
+        println!("Reviewer enabled ({})", reviewer_config.model);
+        let (llm_client, model) = clients.resolve(&reviewer_config.model)?;
+        let reviewer = LlmReviewer::new(llm_client, model, reviewer_config.params.clone())
+            .with_language(config.language);
+        let reviewer = match &reviewer_config.prompt {
+            Some(prompt) => reviewer.with_system_prompt(prompt.clone()),
+            None => reviewer,
+        };
+        orchestrator = orchestrator.with_reviewer(Box::new(reviewer));
+    }
 
+    orchestrator.resume().await?;
+    if !orchestrator.journal().is_empty() {
+        println!(
+            "Resuming the session at step {} ({})",
+            orchestrator.current_step(),
+            orchestrator.current_role().as_str()
+        );
+    }
+
+    if config.plan_tests {
+        // The planner uses its own role config when present, otherwise the tester's.
+        let planner_config = config
+            .roles
+            .get("planner")
+            .or_else(|| config.roles.get(Role::Tester.as_str()))
+            .context("No config for role planner or tester")?;
+        let (llm_client, model) = clients.resolve(&planner_config.model)?;
+        let planner = LlmPlanner::new(llm_client, model, planner_config.params.clone())
+            .with_language(config.language);
+        let test_list = orchestrator.plan_test_list(&planner).await?;
+        println!(
+            "Test list: {}/{} done (see .tdd/test-list.md)",
+            test_list.done_count(),
+            test_list.items.len()
+        );
+    }
+
+    let mut result = Ok(());
     for i in 0..steps {
+        if orchestrator.is_complete() {
+            println!("All test-list items are done.");
+            break;
+        }
         println!("Step {}/{}", i + 1, steps);
-        orchestrator.next().await?;
+        result = orchestrator.next().await;
+        if result.is_err() {
+            break;
+        }
     }
 
-    Ok(())
+    print!("{}", orchestrator.usage().render());
+    if !orchestrator.coverage_trend().is_empty() {
+        print!("{}", orchestrator.coverage_trend().render());
+    }
+    result
+}
+
+fn build_agent(
+    role: Role,
+    config: &Config,
+    clients: &mut LlmClients,
+    cwd: &Path,
+    runner: &Arc<dyn Runner>,
+) -> Result<LlmAgent> {
+    let role_config = config
+        .roles
+        .get(role.as_str())
+        .context(format!("No config for role {}", role.as_str()))?;
+
+    let (llm_client, model) = clients.resolve(&role_config.model)?;
+    let mut models = ModelChain::new(ModelChoice::new(
+        role_config.model.clone(),
+        llm_client.clone(),
+        model.clone(),
+    ));
+    for step in &role_config.escalate {
+        let (client, model) = clients.resolve(&step.model)?;
+        models = models.escalate(
+            step.from_attempt,
+            ModelChoice::new(step.model.clone(), client, model),
+        );
+    }
+    for spec in &role_config.fallback {
+        let (client, model) = clients.resolve(spec)?;
+        models = models.fallback(ModelChoice::new(spec.clone(), client, model));
+    }
+    let agent = LlmAgent::new(
+        role,
+        llm_client,
+        model,
+        role_config.params.clone(),
+        cwd.to_path_buf(),
+    )
+    .with_models(models)
+    .with_language(config.language);
+    let agent = match &role_config.prompt {
+        Some(prompt) => agent.with_system_prompt(prompt.clone()),
+        None => agent,
+    };
+
+    Ok(match &role_config.tools {
+        Some(tools) => agent.with_tools(ToolSettings {
+            max_turns: tools.max_turns,
+            runner: runner.clone(),
+        }),
+        None => agent,
+    })
 }
 
 async fn status() -> Result<()> {
@@ -201,5 +380,83 @@ async fn doctor() -> Result<()> {
         Err(_) => println!("Git: NOT FOUND"),
     }
 
+    if Path::new("tdd.yaml").exists() {
+        let config = Config::load("tdd.yaml").await?;
+        check_commands(&config).await;
+        check_local_models(&config).await?;
+    }
+
+    Ok(())
+}
+
+/// Report whether the programs of the verification commands are installed.
+async fn check_commands(config: &Config) {
+    let mut programs: Vec<String> = Vec::new();
+    for cmd in [config.fmt_cmd(), config.check_cmd(), config.test_cmd()] {
+        if let Some(program) = cmd.into_iter().next() {
+            if !programs.contains(&program) {
+                programs.push(program);
+            }
+        }
+    }
+    println!("Language: {}", config.language.profile().name);
+    for program in programs {
+        let version = tokio::process::Command::new(&program)
+            .arg("--version")
+            .output()
+            .await;
+        match version {
+            Ok(out) => {
+                let stdout = String::from_utf8_lossy(&out.stdout);
+                println!("{}: {}", program, stdout.lines().next().unwrap_or("found"))
+            }
+            Err(_) => println!("{}: NOT FOUND", program),
+        }
+    }
+}
+
+/// Report whether every model a role may use is pulled on the Ollama server serving it.
+async fn check_local_models(config: &Config) -> Result<()> {
+    let mut roles: Vec<_> = config.roles.iter().collect();
+    roles.sort_by_key(|(name, _)| name.as_str());
+    let mut available: HashMap<String, Option<Vec<String>>> = HashMap::new();
+
+    for (role, role_config) in roles {
+        for spec in role_config.models() {
+            let (name, provider, model) = config.resolve_model(spec)?;
+            if provider.api != ApiKind::Ollama {
+                continue;
+            }
+            if !available.contains_key(&name) {
+                let ollama = OllamaProvider::new(provider.provider_config()?);
+                let models = match ollama.list_models().await {
+                    Ok(models) => Some(models),
+                    Err(e) => {
+                        println!(
+                            "Ollama ({}): NOT REACHABLE at {}: {}",
+                            name, provider.base_url, e
+                        );
+                        None
+                    }
+                };
+                available.insert(name.clone(), models);
+            }
+            let Some(models) = &available[&name] else {
+                continue;
+            };
+            // `llama3` is shorthand for `llama3:latest`.
+            let present = models
+                .iter()
+                .any(|m| *m == model.model || *m == format!("{}:latest", model.model));
+            if present {
+                println!("Model {} ({}): OK", model, role);
+            } else {
+                println!(
+                    "Model {} ({}): NOT FOUND, run `ollama pull {}`",
+                    model, role, model.model
+                );
+            }
+        }
+    }
     Ok(())
 }
@@ -0,0 +1,69 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tdd_llm::{SilentObserver, StreamObserver};
+
+use crate::config::LlmOutput;
+
+/// Characters of output per progress dot.
+const CHARS_PER_DOT: usize = 200;
+
+pub fn observer(mode: LlmOutput) -> Arc<dyn StreamObserver> {
+    match mode {
+        LlmOutput::Live => Arc::new(LiveOutput::default()),
+        LlmOutput::Progress => Arc::new(ProgressOutput::default()),
+        LlmOutput::Quiet => Arc::new(SilentObserver),
+    }
+}
+
+/// Echoes the response to stdout as it streams in.
+#[derive(Default)]
+struct LiveOutput {
+    printed: AtomicUsize,
+}
+
+impl StreamObserver for LiveOutput {
+    fn on_text(&self, text: &str) {
+        self.printed.fetch_add(text.len(), Ordering::Relaxed);
+        print!("{}", text);
+        let _ = std::io::stdout().flush();
+    }
+
+    fn on_tool_call(&self, name: &str) {
+        self.printed.fetch_add(1, Ordering::Relaxed);
+        print!("\n[tool call: {}]", name);
+        let _ = std::io::stdout().flush();
+    }
+
+    fn on_finish(&self) {
+        if self.printed.swap(0, Ordering::Relaxed) > 0 {
+            println!();
+        }
+    }
+}
+
+/// Prints a dot every [`CHARS_PER_DOT`] characters, then the total.
+#[derive(Default)]
+struct ProgressOutput {
+    received: AtomicUsize,
+}
+
+impl StreamObserver for ProgressOutput {
+    fn on_text(&self, text: &str) {
+        let before = self.received.fetch_add(text.len(), Ordering::Relaxed);
+        if before == 0 {
+            print!("LLM ");
+        }
+        for _ in before / CHARS_PER_DOT..(before + text.len()) / CHARS_PER_DOT {
+            print!(".");
+        }
+        let _ = std::io::stdout().flush();
+    }
+
+    fn on_finish(&self) {
+        let received = self.received.swap(0, Ordering::Relaxed);
+        if received > 0 {
+            println!(" {} chars", received);
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tdd_llm::{
+    LlmClient, ModelCapabilities, RateLimiter, ResponseCache, TranscriptLog, UsageRecorder,
+};
+
+use crate::config::Config;
+use crate::output;
+
+/// Builds one [`LlmClient`] per provider and hands it to every role that uses it.
+pub struct LlmClients<'a> {
+    config: &'a Config,
+    clients: HashMap<String, LlmClient>,
+    usage: Arc<UsageRecorder>,
+    capabilities: Arc<HashMap<String, ModelCapabilities>>,
+    cache: Option<Arc<ResponseCache>>,
+    transcripts: Option<Arc<TranscriptLog>>,
+}
+
+impl<'a> LlmClients<'a> {
+    pub fn new(config: &'a Config) -> Self {
+        Self {
+            config,
+            clients: HashMap::new(),
+            usage: Arc::new(UsageRecorder::new(config.pricing.clone())),
+            capabilities: Arc::new(config.models.clone()),
+            cache: None,
+            transcripts: None,
+        }
+    }
+
+    /// Serve repeated requests from `cache`.
+    pub fn with_cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = Some(Arc::new(cache));
+        self
+    }
+
+    /// Write a transcript of every call to `log`.
+    pub fn with_transcripts(mut self, log: TranscriptLog) -> Self {
+        self.transcripts = Some(Arc::new(log));
+        self
+    }
+
+    /// Collects the usage of every client handed out.
+    pub fn usage(&self) -> Arc<UsageRecorder> {
+        self.usage.clone()
+    }
+
+    /// Client and bare model name for a `provider:model` string.
+    pub fn resolve(&mut self, spec: &str) -> Result<(LlmClient, String)> {
+        let (name, provider, model) = self.config.resolve_model(spec)?;
+        let client = match self.clients.get(&name) {
+            Some(client) => client.clone(),
+            None => {
+                let endpoint = provider.provider_config()?;
+                let mut client = LlmClient::with_provider(endpoint.clone().build())
+                    .with_retry(provider.retry.clone())
+                    .with_usage(self.usage.clone())
+                    .with_capabilities(self.capabilities.clone());
+                if !provider.limits.is_unlimited() {
+                    let limiter = RateLimiter::new(name.clone(), provider.limits.clone());
+                    client = client.with_limiter(Arc::new(limiter));
+                }
+                if let Some(log) = &self.transcripts {
+                    client = client.with_transcripts(log.clone(), name.clone(), &endpoint);
+                }
+                if let Some(cache) = &self.cache {
+                    client = client.with_cache(cache.clone(), name.clone());
+                }
+                if provider.stream {
+                    client = client.with_stream(output::observer(self.config.llm_output));
+                }
+                self.clients.insert(name, client.clone());
+                client
+            }
+        };
+        Ok((client, model.model))
+    }
+}
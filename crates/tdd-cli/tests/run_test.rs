@@ -0,0 +1,235 @@
+use anyhow::Result;
+use serde_json::json;
+use std::path::Path;
+use tdd_fixtures::{MockLlmServer, Reply};
+use tempfile::TempDir;
+use tokio::process::Command;
+
+const TEST_ONLY: &str = r#"#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_two_numbers() {
+        assert_eq!(add(1, 2), 3);
+    }
+}
+"#;
+
+const IMPLEMENTED: &str = r#"pub fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_two_numbers() {
+        assert_eq!(add(1, 2), 3);
+    }
+}
+"#;
+
+const REFACTORED: &str = r#"/// Sum of two numbers.
+pub fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_two_numbers() {
+        assert_eq!(add(1, 2), 3);
+    }
+}
+"#;
+
+fn config(base_url: &str) -> String {
+    format!(
+        r#"kata_description: "kata.md"
+language: "rust"
+steps: 3
+max_attempts_per_agent: 1
+roles:
+  tester:
+    model: "mock:mock-model"
+    temperature: 0.0
+  implementor:
+    model: "mock:mock-model"
+    temperature: 0.0
+  refactorer:
+    model: "mock:mock-model"
+    temperature: 0.0
+providers:
+  mock:
+    base_url: "{}"
+    retry:
+      max_retries: 0
+ci:
+  test_cmd: ["cargo", "test", "--quiet", "--offline"]
+  check_cmd: ["cargo", "check", "--quiet", "--offline"]
+  fmt_cmd: ["cargo", "fmt"]
+commit:
+  author_name: "TDD Machine"
+  author_email: "tdd@local"
+"#,
+        base_url
+    )
+}
+
+async fn cli(dir: &Path, args: &[&str]) -> Result<std::process::Output> {
+    Ok(Command::new(env!("CARGO_BIN_EXE_tdd-cli"))
+        .args(args)
+        .current_dir(dir)
+        // Keep the kata's build out of this workspace's target directory lock.
+        .env_remove("CARGO_TARGET_DIR")
+        .output()
+        .await?)
+}
+
+async fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .await?;
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[tokio::test]
+async fn test_run_completes_a_red_green_refactor_cycle() -> Result<()> {
+    let server = MockLlmServer::builder()
+        .role(
+            "planner",
+            Reply::json(json!({"tests": ["adds two numbers"]})),
+        )
+        .role(
+            "tester",
+            Reply::edit_plan(&[("src/lib.rs", TEST_ONLY)], "test: add two numbers"),
+        )
+        .role(
+            "implementor",
+            Reply::edit_plan(&[("src/lib.rs", IMPLEMENTED)], "feat: add two numbers"),
+        )
+        .role(
+            "refactorer",
+            Reply::edit_plan(&[("src/lib.rs", REFACTORED)], "refactor: document add"),
+        )
+        .start()
+        .await?;
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path();
+
+    let init = cli(dir, &["init"]).await?;
+    assert!(init.status.success(), "{:?}", init);
+    git(dir, &["config", "user.name", "Test"]).await?;
+    git(dir, &["config", "user.email", "test@local"]).await?;
+    std::fs::write(dir.join("tdd.yaml"), config(&server.base_url()))?;
+    std::fs::write(dir.join("kata.md"), "# Add\n\nAdd two numbers.\n")?;
+
+    let run = cli(dir, &["run", "--steps", "3"]).await?;
+    let stdout = String::from_utf8_lossy(&run.stdout);
+    assert!(
+        run.status.success(),
+        "{}\n{}",
+        stdout,
+        String::from_utf8_lossy(&run.stderr)
+    );
+
+    assert_eq!(
+        server.roles(),
+        vec!["planner", "tester", "implementor", "refactorer"]
+    );
+    assert_eq!(
+        git(dir, &["log", "--format=%s"])
+            .await?
+            .lines()
+            .collect::<Vec<_>>(),
+        vec![
+            "refactor: document add",
+            "feat: add two numbers",
+            "test: add two numbers"
+        ]
+    );
+    assert!(git(dir, &["log", "-1", "--format=%b"])
+        .await?
+        .contains("- Model: mock:mock-model"));
+    assert_eq!(std::fs::read_to_string(dir.join("src/lib.rs"))?, REFACTORED);
+    assert!(
+        std::fs::read_to_string(dir.join(".tdd/test-list.md"))?.contains("- [x] adds two numbers")
+    );
+    assert!(stdout.contains("LLM usage:"));
+    assert_eq!(
+        std::fs::read_dir(dir.join(".tdd/logs/llm"))?.count(),
+        server.requests().len()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_openai_models_use_the_legacy_llm_section() -> Result<()> {
+    let server = MockLlmServer::builder()
+        .role(
+            "planner",
+            Reply::json(json!({"tests": ["adds two numbers"]})),
+        )
+        .role(
+            "tester",
+            Reply::edit_plan(&[("src/lib.rs", TEST_ONLY)], "test: add two numbers"),
+        )
+        .start()
+        .await?;
+    let temp_dir = TempDir::new()?;
+    let dir = temp_dir.path();
+
+    let init = cli(dir, &["init"]).await?;
+    assert!(init.status.success(), "{:?}", init);
+    git(dir, &["config", "user.name", "Test"]).await?;
+    git(dir, &["config", "user.email", "test@local"]).await?;
+    // A config from before `providers`: `llm` points at a proxy.
+    let legacy = config(&server.base_url())
+        .replace("mock:mock-model", "openai:mock-model")
+        .replace("providers:\n  mock:", "llm:");
+    std::fs::write(dir.join("tdd.yaml"), legacy)?;
+    std::fs::write(dir.join("kata.md"), "# Add\n\nAdd two numbers.\n")?;
+
+    let run = cli(dir, &["run", "--steps", "1"]).await?;
+    assert!(
+        run.status.success(),
+        "{}\n{}",
+        String::from_utf8_lossy(&run.stdout),
+        String::from_utf8_lossy(&run.stderr)
+    );
+
+    assert_eq!(server.roles(), vec!["planner", "tester"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_init_scaffolds_the_selected_language() -> Result<()> {
+    let temp = TempDir::new()?;
+    let dir = temp.path();
+
+    let init = cli(dir, &["init", "--language", "python"]).await?;
+    assert!(
+        init.status.success(),
+        "{}",
+        String::from_utf8_lossy(&init.stderr)
+    );
+
+    let config = std::fs::read_to_string(dir.join("tdd.yaml"))?;
+    assert!(config.contains("language: \"python\""));
+    assert!(config.contains("llm: \"summary\""));
+    assert!(config.contains("test_cmd: [\"python3\", \"-m\", \"pytest\", \"-v\"]"));
+    assert!(
+        std::fs::read_to_string(dir.join("pyproject.toml"))?.contains("[tool.pytest.ini_options]")
+    );
+    assert!(dir.join("kata.py").exists());
+    assert!(dir.join("tests/__init__.py").exists());
+    assert!(!dir.join("Cargo.toml").exists());
+    assert!(std::fs::read_to_string(dir.join(".gitignore"))?.contains("__pycache__/"));
+    Ok(())
+}
@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::Role;
+
+/// Line coverage of one source file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FileCoverage {
+    /// Relative to the kata root.
+    pub path: String,
+    pub lines_covered: u32,
+    pub lines_total: u32,
+    /// Instrumented lines no test executed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub uncovered_lines: Vec<u32>,
+}
+
+/// Line coverage of the kata's sources after a test run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Coverage {
+    pub lines_covered: u32,
+    pub lines_total: u32,
+    pub files: Vec<FileCoverage>,
+}
+
+impl Coverage {
+    pub fn percent(&self) -> f64 {
+        if self.lines_total == 0 {
+            return 100.0;
+        }
+        f64::from(self.lines_covered) * 100.0 / f64::from(self.lines_total)
+    }
+
+    /// Lines `diff` adds that are instrumented but were not executed, by file.
+    pub fn uncovered_added_lines(&self, diff: &str) -> Vec<(String, Vec<u32>)> {
+        let added = added_lines(diff);
+        self.files
+            .iter()
+            .filter_map(|file| {
+                let added = added.get(&file.path)?;
+                let lines: Vec<u32> = file
+                    .uncovered_lines
+                    .iter()
+                    .copied()
+                    .filter(|line| added.contains(line))
+                    .collect();
+                (!lines.is_empty()).then(|| (file.path.clone(), lines))
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Display for Coverage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:.1}% ({}/{} lines)",
+            self.percent(),
+            self.lines_covered,
+            self.lines_total
+        )
+    }
+}
+
+/// Line numbers (in the new version) of the lines a unified diff adds, by file.
+pub fn added_lines(diff: &str) -> BTreeMap<String, Vec<u32>> {
+    let mut added: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+    let mut file: Option<String> = None;
+    let mut line = 0;
+    for text in diff.lines() {
+        if let Some(path) = text.strip_prefix("+++ ") {
+            file = path.strip_prefix("b/").map(str::to_string);
+        } else if let Some(hunk) = text.strip_prefix("@@ ") {
+            // `@@ -1,3 +4,5 @@`: the new version starts at line 4.
+            line = hunk
+                .split_whitespace()
+                .find_map(|range| range.strip_prefix('+'))
+                .and_then(|range| range.split(',').next()?.parse().ok())
+                .unwrap_or(0);
+        } else if text.starts_with("--- ") || text.starts_with('\\') {
+            // Old file name, or "\ No newline at end of file".
+        } else if let Some(path) = text.strip_prefix('+').and(file.as_ref()) {
+            added.entry(path.clone()).or_default().push(line);
+            line += 1;
+        } else if !text.starts_with('-') {
+            line += 1;
+        }
+    }
+    added
+}
+
+/// Coverage after each committed step that measured it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CoverageTrend {
+    pub points: Vec<CoveragePoint>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoveragePoint {
+    pub step: u32,
+    pub role: Role,
+    pub lines_covered: u32,
+    pub lines_total: u32,
+    pub percent: f64,
+}
+
+impl CoverageTrend {
+    pub fn record(&mut self, step: u32, role: Role, coverage: &Coverage) {
+        self.points.push(CoveragePoint {
+            step,
+            role,
+            lines_covered: coverage.lines_covered,
+            lines_total: coverage.lines_total,
+            percent: coverage.percent(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// One line per step with the change from the step before.
+    pub fn render(&self) -> String {
+        let mut out = String::from("Coverage:\n");
+        let mut previous: Option<f64> = None;
+        for point in &self.points {
+            out.push_str(&format!(
+                "  step {:<4} {:<12} {:>5.1}% ({}/{} lines)",
+                point.step,
+                point.role.as_str(),
+                point.percent,
+                point.lines_covered,
+                point.lines_total
+            ));
+            if let Some(previous) = previous {
+                out.push_str(&format!(" {:+.1}", point.percent - previous));
+            }
+            out.push('\n');
+            previous = Some(point.percent);
+        }
+        out
+    }
+}
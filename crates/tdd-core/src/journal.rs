@@ -0,0 +1,146 @@
+use crate::Role;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::Path;
+use tokio::fs;
+
+/// Number of most recent steps kept verbatim before being compressed.
+pub const DEFAULT_RECENT_ENTRIES: usize = 6;
+/// Compressed summaries are truncated to this many characters.
+const SUMMARY_WIDTH: usize = 100;
+/// Summaries kept before the oldest are dropped and only counted.
+const MAX_SUMMARIES: usize = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub step: u32,
+    pub role: Role,
+    /// The behaviour this step was about (the commit subject).
+    pub behaviour: String,
+    pub notes: String,
+    pub attempts: u32,
+    pub commit_id: String,
+}
+
+impl JournalEntry {
+    fn summary(&self) -> String {
+        let mut line = format!(
+            "step {} ({}): {}",
+            self.step,
+            self.role.as_str(),
+            self.behaviour
+        );
+        if self.attempts > 1 {
+            line.push_str(&format!(" [{} attempts]", self.attempts));
+        }
+        if line.chars().count() > SUMMARY_WIDTH {
+            line = line.chars().take(SUMMARY_WIDTH - 3).collect::<String>() + "...";
+        }
+        line
+    }
+}
+
+/// Rolling record of the session so far.
+///
+/// The last `max_recent` steps are kept in full and the ones before them as
+/// one-line summaries; beyond those only a count remains, so the context
+/// stays bounded over a long kata.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionJournal {
+    max_recent: usize,
+    /// Steps older than the summaries, no longer shown individually.
+    #[serde(default)]
+    omitted: usize,
+    summaries: VecDeque<String>,
+    recent: VecDeque<JournalEntry>,
+}
+
+impl Default for SessionJournal {
+    fn default() -> Self {
+        Self::new(DEFAULT_RECENT_ENTRIES)
+    }
+}
+
+impl SessionJournal {
+    pub fn new(max_recent: usize) -> Self {
+        Self {
+            max_recent: max_recent.max(1),
+            omitted: 0,
+            summaries: VecDeque::new(),
+            recent: VecDeque::new(),
+        }
+    }
+
+    /// Read `journal.json` from `dir`, if an earlier run left one.
+    pub async fn load(dir: &Path) -> Result<Option<Self>> {
+        let path = dir.join("journal.json");
+        if !path.exists() {
+            return Ok(None);
+        }
+        let json = fs::read_to_string(&path)
+            .await
+            .context(format!("Failed to read journal: {:?}", path))?;
+        Ok(Some(
+            serde_json::from_str(&json).context("Failed to parse journal JSON")?,
+        ))
+    }
+
+    pub fn record(&mut self, entry: JournalEntry) {
+        self.recent.push_back(entry);
+        while self.recent.len() > self.max_recent {
+            if let Some(oldest) = self.recent.pop_front() {
+                self.summaries.push_back(oldest.summary());
+            }
+        }
+        while self.summaries.len() > MAX_SUMMARIES {
+            self.summaries.pop_front();
+            self.omitted += 1;
+        }
+    }
+
+    /// The last step recorded, in full.
+    pub fn last(&self) -> Option<&JournalEntry> {
+        self.recent.back()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.summaries.is_empty() && self.recent.is_empty()
+    }
+
+    pub fn summaries(&self) -> impl Iterator<Item = &String> {
+        self.summaries.iter()
+    }
+
+    pub fn recent(&self) -> impl Iterator<Item = &JournalEntry> {
+        self.recent.iter()
+    }
+
+    /// Render the journal as prompt-ready text.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        if !self.summaries.is_empty() {
+            out.push_str("Earlier steps:\n");
+            if self.omitted > 0 {
+                out.push_str(&format!("- ({} older steps omitted)\n", self.omitted));
+            }
+            for summary in &self.summaries {
+                out.push_str(&format!("- {}\n", summary));
+            }
+        }
+        if !self.recent.is_empty() {
+            out.push_str("Recent steps:\n");
+            for entry in &self.recent {
+                out.push_str(&format!(
+                    "- step {} ({}), {} attempt(s): {}\n  notes: {}\n",
+                    entry.step,
+                    entry.role.as_str(),
+                    entry.attempts,
+                    entry.behaviour,
+                    entry.notes
+                ));
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,327 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::{Role, RunnerOutcome};
+
+/// Language of the kata, selected by `language:` in tdd.yaml.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    Rust,
+    #[serde(alias = "py")]
+    Python,
+    #[serde(alias = "ts")]
+    TypeScript,
+    #[serde(alias = "golang")]
+    Go,
+}
+
+impl Language {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Language::Rust => "rust",
+            Language::Python => "python",
+            Language::TypeScript => "typescript",
+            Language::Go => "go",
+        }
+    }
+
+    pub fn profile(self) -> &'static LanguageProfile {
+        match self {
+            Language::Rust => &RUST,
+            Language::Python => &PYTHON,
+            Language::TypeScript => &TYPESCRIPT,
+            Language::Go => &GO,
+        }
+    }
+}
+
+impl FromStr for Language {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> anyhow::Result<Self> {
+        Ok(match name.to_lowercase().as_str() {
+            "rust" => Language::Rust,
+            "python" | "py" => Language::Python,
+            "typescript" | "ts" => Language::TypeScript,
+            "go" | "golang" => Language::Go,
+            _ => anyhow::bail!(
+                "Unknown language: {} (expected rust, python, typescript or go)",
+                name
+            ),
+        })
+    }
+}
+
+/// How the test command reports individual test cases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestOutputFormat {
+    /// `cargo test` (human or JSON) and `cargo nextest run`.
+    Libtest,
+    /// `pytest -v`.
+    Pytest,
+    /// Verbose reporters of vitest and jest.
+    Jest,
+    /// `go test -v`.
+    GoTest,
+}
+
+/// Everything that differs between kata languages: the project `init`
+/// scaffolds, the default commands, how test output and files are read, and
+/// what the agents are told about the language.
+#[derive(Debug)]
+pub struct LanguageProfile {
+    pub language: Language,
+    /// Name used in prompts.
+    pub name: &'static str,
+    /// Files `init` creates unless they exist, as (path, content).
+    pub scaffold: &'static [(&'static str, &'static str)],
+    /// `.gitignore` entries besides tdd's own.
+    pub gitignore: &'static [&'static str],
+    pub fmt_cmd: &'static [&'static str],
+    pub check_cmd: &'static [&'static str],
+    pub test_cmd: &'static [&'static str],
+    pub test_output: TestOutputFormat,
+    /// Extensions of source files, test or production.
+    pub source_extensions: &'static [&'static str],
+    /// Directories whose sources are all test code.
+    pub test_dirs: &'static [&'static str],
+    /// File-name prefixes and suffixes of test files.
+    pub test_prefixes: &'static [&'static str],
+    pub test_suffixes: &'static [&'static str],
+    /// Tests may live next to production code in the same file.
+    pub inline_tests: bool,
+    /// Where a first test would go, shown in the agents' output examples.
+    pub example_test_path: &'static str,
+    /// Conventions appended to the agents' system prompts.
+    pub prompt: &'static str,
+}
+
+impl LanguageProfile {
+    pub fn is_source_file(&self, path: &str) -> bool {
+        self.source_extensions
+            .iter()
+            .any(|extension| path.ends_with(extension))
+    }
+
+    /// Whether `path` (relative to the kata root) is a test source file.
+    /// Production files of languages with inline tests may contain tests too.
+    pub fn is_test_file(&self, path: &str) -> bool {
+        if !self.is_source_file(path) {
+            return false;
+        }
+        let path = path.replace('\\', "/");
+        let (dirs, file) = path.rsplit_once('/').unwrap_or(("", &path));
+        dirs.split('/').any(|dir| self.test_dirs.contains(&dir))
+            || self
+                .test_prefixes
+                .iter()
+                .any(|prefix| file.starts_with(prefix))
+            || self
+                .test_suffixes
+                .iter()
+                .any(|suffix| file.ends_with(suffix))
+    }
+
+    /// Changed files `role` should have left alone: production code for the
+    /// Tester (unless tests are written inline) and test code for the
+    /// Implementor. Each comes with the reason.
+    pub fn misplaced_files(&self, role: Role, files: &[String]) -> Vec<(String, &'static str)> {
+        files
+            .iter()
+            .filter_map(|file| {
+                let reason = match role {
+                    Role::Tester if !self.inline_tests => (self.is_source_file(file)
+                        && !self.is_test_file(file))
+                    .then_some("the Tester may only change test files"),
+                    Role::Implementor => self
+                        .is_test_file(file)
+                        .then_some("the Implementor may not change test files"),
+                    _ => None,
+                }?;
+                Some((file.clone(), reason))
+            })
+            .collect()
+    }
+
+    /// Whether a failed check is the red test calling code that does not exist
+    /// yet: where tests live in files of their own, the Tester cannot add the
+    /// missing function, so `tsc` or `go vet` errors confined to test files
+    /// are part of the RED phase.
+    pub fn is_red_check(&self, check: &RunnerOutcome) -> bool {
+        if self.inline_tests || check.ok || check.timed_out() {
+            return false;
+        }
+        let files = self.files_with_errors(check);
+        !files.is_empty() && files.iter().all(|file| self.is_test_file(file))
+    }
+
+    /// Source files a check reports errors in: the diagnostics' spans, and
+    /// lines starting with a location such as `./kata_test.go:6:9: ...`
+    /// (go vet) or `src/kata.test.ts(3,10): error TS2305: ...` (tsc).
+    pub fn files_with_errors(&self, check: &RunnerOutcome) -> Vec<String> {
+        let mut files: Vec<String> = check
+            .diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.level == "error")
+            .filter_map(|diagnostic| diagnostic.file.clone())
+            .collect();
+        for line in check.stdout.lines().chain(check.stderr.lines()) {
+            let line = line.trim_start();
+            let line = line.strip_prefix("vet: ").unwrap_or(line);
+            let Some(end) = line.find([':', '(']) else {
+                continue;
+            };
+            let file = line[..end].trim_start_matches("./");
+            if !file.contains(char::is_whitespace)
+                && self.is_source_file(file)
+                && !files.iter().any(|known| known == file)
+            {
+                files.push(file.to_string());
+            }
+        }
+        files
+    }
+
+    pub fn fmt_cmd(&self) -> Vec<String> {
+        to_strings(self.fmt_cmd)
+    }
+
+    pub fn check_cmd(&self) -> Vec<String> {
+        to_strings(self.check_cmd)
+    }
+
+    pub fn test_cmd(&self) -> Vec<String> {
+        to_strings(self.test_cmd)
+    }
+}
+
+fn to_strings(parts: &[&str]) -> Vec<String> {
+    parts.iter().map(|part| part.to_string()).collect()
+}
+
+static RUST: LanguageProfile = LanguageProfile {
+    language: Language::Rust,
+    name: "Rust",
+    scaffold: &[
+        (
+            "Cargo.toml",
+            "[package]\nname = \"tdd-kata\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\n\n[dev-dependencies]\n",
+        ),
+        ("src/lib.rs", ""),
+    ],
+    gitignore: &["/target"],
+    fmt_cmd: &["cargo", "fmt"],
+    check_cmd: &["cargo", "clippy", "--all", "--", "-D", "warnings"],
+    test_cmd: &["cargo", "test", "--all"],
+    test_output: TestOutputFormat::Libtest,
+    source_extensions: &[".rs"],
+    test_dirs: &["tests", "benches"],
+    test_prefixes: &[],
+    test_suffixes: &[],
+    inline_tests: true,
+    example_test_path: "src/lib.rs",
+    prompt: r#"
+LANGUAGE: Rust
+- The kata is a library crate; production code lives in src/
+- Unit tests go in a #[cfg(test)] mod tests at the bottom of the file they test, with #[test] functions using assert_eq!
+- Test code is everything inside #[cfg(test)] modules, #[test] functions and the tests/ directory
+- Code must compile without warnings under clippy
+"#,
+};
+
+static PYTHON: LanguageProfile = LanguageProfile {
+    language: Language::Python,
+    name: "Python",
+    scaffold: &[
+        (
+            "pyproject.toml",
+            "[project]\nname = \"tdd-kata\"\nversion = \"0.1.0\"\nrequires-python = \">=3.9\"\n\n[tool.pytest.ini_options]\npythonpath = [\".\"]\ntestpaths = [\"tests\"]\n",
+        ),
+        ("kata.py", ""),
+        ("tests/__init__.py", ""),
+    ],
+    gitignore: &["__pycache__/", ".pytest_cache/", ".ruff_cache/", ".venv/"],
+    fmt_cmd: &["ruff", "format", "."],
+    check_cmd: &["ruff", "check", "."],
+    test_cmd: &["python3", "-m", "pytest", "-v"],
+    test_output: TestOutputFormat::Pytest,
+    source_extensions: &[".py"],
+    test_dirs: &["tests", "test"],
+    test_prefixes: &["test_", "conftest.py"],
+    test_suffixes: &["_test.py"],
+    inline_tests: false,
+    example_test_path: "tests/test_kata.py",
+    prompt: r#"
+LANGUAGE: Python
+- Production code lives in modules at the project root (start with kata.py)
+- Tests are pytest functions named test_* in tests/test_*.py, using plain assert statements
+- Import production code with `from kata import ...`
+- Use type hints; the code is checked with ruff
+"#,
+};
+
+static TYPESCRIPT: LanguageProfile = LanguageProfile {
+    language: Language::TypeScript,
+    name: "TypeScript",
+    scaffold: &[
+        (
+            "package.json",
+            "{\n  \"name\": \"tdd-kata\",\n  \"version\": \"0.1.0\",\n  \"private\": true,\n  \"type\": \"module\",\n  \"scripts\": {\n    \"test\": \"vitest run\"\n  },\n  \"devDependencies\": {\n    \"prettier\": \"^3.0.0\",\n    \"typescript\": \"^5.0.0\",\n    \"vitest\": \"^1.0.0\"\n  }\n}\n",
+        ),
+        (
+            "tsconfig.json",
+            "{\n  \"compilerOptions\": {\n    \"target\": \"ES2022\",\n    \"module\": \"ESNext\",\n    \"moduleResolution\": \"Bundler\",\n    \"strict\": true,\n    \"noEmit\": true,\n    \"skipLibCheck\": true\n  },\n  \"include\": [\"src\"]\n}\n",
+        ),
+        ("src/kata.ts", "export {};\n"),
+    ],
+    gitignore: &["node_modules/", "dist/", "coverage/"],
+    fmt_cmd: &["npx", "prettier", "--write", "src"],
+    check_cmd: &["npx", "tsc", "--noEmit"],
+    test_cmd: &["npx", "vitest", "run", "--reporter=verbose"],
+    test_output: TestOutputFormat::Jest,
+    source_extensions: &[".ts", ".tsx", ".js", ".jsx", ".mts", ".mjs"],
+    test_dirs: &["__tests__"],
+    test_prefixes: &[],
+    test_suffixes: &[
+        ".test.ts", ".spec.ts", ".test.tsx", ".spec.tsx", ".test.js", ".spec.js",
+    ],
+    inline_tests: false,
+    example_test_path: "src/kata.test.ts",
+    prompt: r#"
+LANGUAGE: TypeScript
+- Production code lives in src/ as ES modules (start with src/kata.ts)
+- Tests live next to the code they test in src/*.test.ts, using describe/it/expect imported from "vitest"
+- Import production code with relative paths, e.g. `import { add } from "./kata"`
+- The code must type-check under `tsc --strict`
+"#,
+};
+
+static GO: LanguageProfile = LanguageProfile {
+    language: Language::Go,
+    name: "Go",
+    scaffold: &[
+        ("go.mod", "module kata\n\ngo 1.21\n"),
+        ("kata.go", "package kata\n"),
+    ],
+    gitignore: &[],
+    fmt_cmd: &["gofmt", "-w", "."],
+    check_cmd: &["go", "vet", "./..."],
+    test_cmd: &["go", "test", "-v", "./..."],
+    test_output: TestOutputFormat::GoTest,
+    source_extensions: &[".go"],
+    test_dirs: &["testdata"],
+    test_prefixes: &[],
+    test_suffixes: &["_test.go"],
+    inline_tests: false,
+    example_test_path: "kata_test.go",
+    prompt: r#"
+LANGUAGE: Go
+- The kata is package kata at the module root; production code lives in *.go files
+- Tests are func TestXxx(t *testing.T) in *_test.go files of the same package, reporting with t.Errorf or t.Fatalf
+- Prefer table-driven tests with t.Run once cases multiply
+- Code must pass go vet
+"#,
+};
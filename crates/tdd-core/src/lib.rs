@@ -2,9 +2,18 @@ use anyhow::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
-
+pub mod coverage;
+pub mod journal;
+pub mod language;
 pub mod orchestrator;
+pub mod test_list;
+pub mod usage;
+pub use coverage::{Coverage, CoverageTrend, FileCoverage};
+pub use journal::{JournalEntry, SessionJournal};
+pub use language::{Language, LanguageProfile, TestOutputFormat};
 pub use orchestrator::TddOrchestrator;
+pub use test_list::{Planner, TestItem, TestList};
+pub use usage::{AttemptUsage, TokenUsage, UsageMeter, UsageReport};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -28,10 +37,23 @@ impl Role {
 pub struct StepContext {
     pub role: Role,
     pub step_index: u32,
+    /// Which attempt at this step this is, starting at 1.
+    #[serde(default = "first_attempt")]
+    pub attempt: u32,
     pub kata_description: String,
     pub git_last_commit_msg: String,
     pub git_last_diff: String,
     pub repo_snapshot_paths: Vec<String>,
+    /// The first unchecked item of the test list, when one was planned; Tester steps only.
+    pub next_test: Option<String>,
+    /// What happened in earlier steps of this session.
+    pub journal: SessionJournal,
+    /// Why the previous attempt of this step was rejected, if any.
+    pub feedback: Option<String>,
+}
+
+fn first_attempt() -> u32 {
+    1
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,6 +61,9 @@ pub struct StepResult {
     pub files_changed: Vec<String>,
     pub commit_message: String,
     pub notes: String,
+    /// The model that produced the change, when the agent knows it.
+    #[serde(default)]
+    pub model: Option<String>,
 }
 
 #[async_trait]
@@ -46,6 +71,40 @@ pub trait Agent: Send + Sync {
     fn role(&self) -> Role;
     async fn plan(&self, ctx: &StepContext) -> Result<String>;
     async fn edit(&self, ctx: &StepContext) -> Result<StepResult>;
+    /// The system prompt the agent works under, so the reviewer judges a step
+    /// against what the agent was actually told.
+    fn instructions(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Everything the reviewer sees about a verified but not yet committed step.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewRequest {
+    /// The role whose work is under review.
+    pub role: Role,
+    pub step_index: u32,
+    pub kata_description: String,
+    pub commit_message: String,
+    pub notes: String,
+    /// The acting agent's system prompt, when it reports one.
+    #[serde(default)]
+    pub instructions: Option<String>,
+    pub diff: String,
+    pub check: RunnerOutcome,
+    pub test: RunnerOutcome,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewVerdict {
+    pub approved: bool,
+    #[serde(default)]
+    pub reasons: Vec<String>,
+}
+
+#[async_trait]
+pub trait Reviewer: Send + Sync {
+    async fn review(&self, request: &ReviewRequest) -> Result<ReviewVerdict>;
 }
 
 #[async_trait]
@@ -61,11 +120,173 @@ pub struct RepoState {
     pub files: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// How a runner command ended.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    /// Ran to completion; `ok` tells whether it succeeded.
+    #[default]
+    Exited,
+    /// Killed, with everything it started, after exceeding the stage timeout.
+    TimedOut,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    Ignored,
+}
+
+/// One test case as reported by the test harness.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestCase {
+    pub name: String,
+    /// Path of the enclosing module, e.g. `tests` or `parser::tests`; empty at the crate root.
+    #[serde(default)]
+    pub module: String,
+    pub status: TestStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// Panic message of a failed test.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>,
+    /// Where it panicked, `file:line:column`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+}
+
+impl TestCase {
+    /// `module::name`, as the harness prints it.
+    pub fn path(&self) -> String {
+        if self.module.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}::{}", self.module, self.name)
+        }
+    }
+}
+
+/// A replacement the compiler proposes for a span of a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Suggestion {
+    pub file: String,
+    pub line: u32,
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub replacement: String,
+    /// Safe to apply without review (rustc's `MachineApplicable`).
+    pub machine_applicable: bool,
+    /// The help message it belongs to, counted within its diagnostic. The
+    /// spans of one message make up a single fix.
+    #[serde(default)]
+    pub group: u32,
+}
+
+/// One compiler or clippy diagnostic.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// `error` or `warning`.
+    pub level: String,
+    /// Error code or lint name, e.g. `E0425` or `clippy::needless_return`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    pub message: String,
+    /// Primary span: file, first and last line, and column.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default)]
+    pub line_start: u32,
+    #[serde(default)]
+    pub line_end: u32,
+    #[serde(default)]
+    pub column: u32,
+    /// The `help:` and `note:` lines.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<Suggestion>,
+}
+
+impl Diagnostic {
+    /// A few lines instead of rustc's source excerpts:
+    /// `error[E0425] src/lib.rs:2:36: cannot find value `valu` in this scope`.
+    pub fn render_compact(&self) -> String {
+        let mut text = self.level.clone();
+        if let Some(code) = &self.code {
+            text.push_str(&format!("[{}]", code));
+        }
+        if let Some(file) = &self.file {
+            text.push_str(&format!(" {}:{}:{}", file, self.line_start, self.column));
+        }
+        text.push_str(&format!(": {}\n", self.message));
+        for note in &self.notes {
+            text.push_str(&format!("  {}\n", note));
+        }
+        text
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RunnerOutcome {
     pub ok: bool,
+    #[serde(default)]
+    pub status: RunStatus,
     pub stdout: String,
     pub stderr: String,
+    /// Test cases parsed from the output of the test stage.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tests: Vec<TestCase>,
+    /// Compiler diagnostics of the check stage.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub diagnostics: Vec<Diagnostic>,
+    /// Files the check stage changed by applying compiler fixes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fixed_files: Vec<String>,
+}
+
+impl RunnerOutcome {
+    pub fn timed_out(&self) -> bool {
+        self.status == RunStatus::TimedOut
+    }
+
+    pub fn failed_tests(&self) -> impl Iterator<Item = &TestCase> {
+        self.tests
+            .iter()
+            .filter(|test| test.status == TestStatus::Failed)
+    }
+
+    /// `3 passed, 1 failed, 0 ignored`, when test cases were parsed.
+    pub fn test_summary(&self) -> Option<String> {
+        if self.tests.is_empty() {
+            return None;
+        }
+        let count = |status| self.tests.iter().filter(|t| t.status == status).count();
+        Some(format!(
+            "{} passed, {} failed, {} ignored",
+            count(TestStatus::Passed),
+            count(TestStatus::Failed),
+            count(TestStatus::Ignored)
+        ))
+    }
+
+    /// PASS, FAIL or TIMEOUT.
+    pub fn verdict(&self) -> &'static str {
+        match (self.status, self.ok) {
+            (RunStatus::TimedOut, _) => "TIMEOUT",
+            (RunStatus::Exited, true) => "PASS",
+            (RunStatus::Exited, false) => "FAIL",
+        }
+    }
+
+    /// The verdict with the test counts, e.g. `FAIL (3 passed, 1 failed, 0 ignored)`.
+    pub fn headline(&self) -> String {
+        match self.test_summary() {
+            Some(summary) => format!("{} ({})", self.verdict(), summary),
+            None => self.verdict().to_string(),
+        }
+    }
 }
 
 #[async_trait]
@@ -73,11 +294,61 @@ pub trait Runner: Send + Sync {
     async fn fmt(&self) -> Result<RunnerOutcome>;
     async fn check(&self) -> Result<RunnerOutcome>;
     async fn test(&self) -> Result<RunnerOutcome>;
+    /// Line coverage of the tests, when a coverage stage is configured and
+    /// its tool is available.
+    async fn coverage(&self) -> Result<Option<Coverage>> {
+        Ok(None)
+    }
+}
+
+/// A runner shared by the orchestrator and the agents' tools.
+#[async_trait]
+impl<R: Runner + ?Sized> Runner for std::sync::Arc<R> {
+    async fn fmt(&self) -> Result<RunnerOutcome> {
+        (**self).fmt().await
+    }
+
+    async fn check(&self) -> Result<RunnerOutcome> {
+        (**self).check().await
+    }
+
+    async fn test(&self) -> Result<RunnerOutcome> {
+        (**self).test().await
+    }
+
+    async fn coverage(&self) -> Result<Option<Coverage>> {
+        (**self).coverage().await
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstraintViolation {
+    pub rule: String,
+    pub file: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for ConstraintViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}: [{}] {}",
+            self.file, self.line, self.rule, self.message
+        )
+    }
+}
+
+/// Checks the working tree against the kata's stated constraints.
+pub trait ConstraintChecker: Send + Sync {
+    fn check(&self) -> Result<Vec<ConstraintViolation>>;
 }
 
 pub trait Vcs: Send + Sync {
     fn init_if_needed(&self) -> Result<()>;
     fn read_state(&self) -> Result<RepoState>;
+    /// Diff of the uncommitted changes (including new files) against HEAD.
+    fn working_diff(&self) -> Result<String>;
     fn stage_all(&self) -> Result<()>;
     fn commit(&self, message: &str) -> Result<String>;
     fn checkout_all(&self) -> Result<()>;
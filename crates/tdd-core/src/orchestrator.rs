@@ -1,4 +1,8 @@
-use crate::{Agent, Orchestrator, Role, Runner, StepContext, Vcs};
+use crate::{
+    Agent, AttemptUsage, ConstraintChecker, ConstraintViolation, CoverageTrend, JournalEntry,
+    Language, Orchestrator, Planner, ReviewRequest, Reviewer, Role, Runner, RunnerOutcome,
+    SessionJournal, StepContext, TestList, TokenUsage, UsageMeter, UsageReport, Vcs,
+};
 use anyhow::Result;
 use std::path::PathBuf;
 use tokio::fs;
@@ -9,13 +13,24 @@ pub struct TddOrchestrator {
     refactorer: Box<dyn Agent>,
     runner: Box<dyn Runner>,
     vcs: Box<dyn Vcs>,
+    constraint_checker: Option<Box<dyn ConstraintChecker>>,
+    reviewer: Option<Box<dyn Reviewer>>,
+    usage_meter: Option<Box<dyn UsageMeter>>,
     kata_description: String,
     max_attempts: u32,
     work_dir: PathBuf,
+    language: Language,
+    fail_on_uncovered: bool,
 
     // State
     current_step: u32,
     current_role: Role,
+    journal: SessionJournal,
+    test_list: Option<TestList>,
+    /// Test-list item the last Tester commit was written for, ticked once it goes green.
+    pending_test_item: Option<usize>,
+    usage: UsageReport,
+    coverage_trend: CoverageTrend,
 }
 
 impl TddOrchestrator {
@@ -36,14 +51,140 @@ impl TddOrchestrator {
             refactorer,
             runner,
             vcs,
+            constraint_checker: None,
+            reviewer: None,
+            usage_meter: None,
             kata_description,
             max_attempts,
             work_dir,
+            language: Language::default(),
+            fail_on_uncovered: false,
             current_step: 1,
             current_role: Role::Tester,
+            journal: SessionJournal::default(),
+            test_list: None,
+            pending_test_item: None,
+            usage: UsageReport::default(),
+            coverage_trend: CoverageTrend::default(),
         }
     }
 
+    /// Enforce kata constraints as an extra verification stage after `check`.
+    pub fn with_constraint_checker(mut self, checker: Box<dyn ConstraintChecker>) -> Self {
+        self.constraint_checker = Some(checker);
+        self
+    }
+
+    /// Hold each role to the test-file layout of `language` (Rust by default):
+    /// the Implementor may not touch test files, and where tests live in files
+    /// of their own the Tester may not touch anything else.
+    pub fn with_language(mut self, language: Language) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// Require reviewer approval before each step is committed.
+    pub fn with_reviewer(mut self, reviewer: Box<dyn Reviewer>) -> Self {
+        self.reviewer = Some(reviewer);
+        self
+    }
+
+    /// Attribute LLM token usage and cost to attempts, steps and roles.
+    pub fn with_usage_meter(mut self, meter: Box<dyn UsageMeter>) -> Self {
+        self.usage_meter = Some(meter);
+        self
+    }
+
+    /// Reject Implementor attempts that add lines no test executes. Needs a
+    /// runner that measures coverage.
+    pub fn with_fail_on_uncovered(mut self, fail_on_uncovered: bool) -> Self {
+        self.fail_on_uncovered = fail_on_uncovered;
+        self
+    }
+
+    /// Coverage after each committed step that measured it.
+    pub fn coverage_trend(&self) -> &CoverageTrend {
+        &self.coverage_trend
+    }
+
+    /// LLM usage so far in this run.
+    pub fn usage(&self) -> &UsageReport {
+        &self.usage
+    }
+
+    fn take_usage(&self) -> TokenUsage {
+        self.usage_meter
+            .as_ref()
+            .map(|meter| meter.take())
+            .unwrap_or_default()
+    }
+
+    /// Add a step's attempts to the run totals.
+    fn record_usage(&mut self, attempts: &[AttemptUsage]) -> TokenUsage {
+        let mut step = TokenUsage::default();
+        for attempt in attempts {
+            self.usage.add(self.current_role.as_str(), attempt.agent);
+            self.usage.add("reviewer", attempt.reviewer);
+            step += attempt.agent;
+            step += attempt.reviewer;
+        }
+        step
+    }
+
+    pub fn current_step(&self) -> u32 {
+        self.current_step
+    }
+
+    pub fn journal(&self) -> &SessionJournal {
+        &self.journal
+    }
+
+    /// Continue the session an earlier run recorded in `.tdd/journal.json`:
+    /// keep its journal and carry on with the step after its last commit.
+    pub async fn resume(&mut self) -> Result<()> {
+        let Some(journal) = SessionJournal::load(&self.work_dir.join(".tdd")).await? else {
+            return Ok(());
+        };
+        if let Some(last) = journal.last() {
+            self.current_step = last.step;
+            self.current_role = last.role;
+            self.rotate_role();
+        }
+        self.journal = journal;
+        Ok(())
+    }
+
+    /// Load `.tdd/test-list.json`, or ask the planner for one and save it.
+    pub async fn plan_test_list(&mut self, planner: &dyn Planner) -> Result<&TestList> {
+        let tdd_dir = self.work_dir.join(".tdd");
+        let test_list = match TestList::load(&tdd_dir).await? {
+            Some(test_list) => test_list,
+            None => {
+                println!("Planning test list...");
+                let test_list = TestList::new(planner.plan_tests(&self.kata_description).await?);
+                test_list.save(&tdd_dir).await?;
+                let usage = self.take_usage();
+                self.usage.add("planner", usage);
+                test_list
+            }
+        };
+        // Resumed after a Tester commit: its test is for the next open item.
+        if self.current_role == Role::Implementor {
+            self.pending_test_item = test_list.next_index();
+        }
+        Ok(self.test_list.insert(test_list))
+    }
+
+    pub fn test_list(&self) -> Option<&TestList> {
+        self.test_list.as_ref()
+    }
+
+    /// True once every test-list item is done and the current cycle has finished.
+    pub fn is_complete(&self) -> bool {
+        self.current_role == Role::Tester
+            && self.test_list.as_ref().is_some_and(TestList::is_complete)
+    }
+
     fn get_agent(&self, role: Role) -> &dyn Agent {
         match role {
             Role::Tester => self.tester.as_ref(),
@@ -61,6 +202,71 @@ impl TddOrchestrator {
         self.current_step += 1;
     }
 
+    fn failure_feedback(
+        &self,
+        check_res: &RunnerOutcome,
+        test_res: &RunnerOutcome,
+        violations: &[ConstraintViolation],
+        misplaced: &[(String, &str)],
+        uncovered: &[(String, Vec<u32>)],
+    ) -> String {
+        let mut feedback = String::new();
+        let red_check =
+            self.current_role == Role::Tester && self.language.profile().is_red_check(check_res);
+        if red_check {
+            // Errors in the test files alone are expected of a red test.
+        } else if check_res.timed_out() {
+            feedback.push_str(&format!("Check timed out:\n{}\n", check_res.stderr));
+        } else if !check_res.ok && !check_res.diagnostics.is_empty() {
+            feedback.push_str("Check failed:\n");
+            for diagnostic in &check_res.diagnostics {
+                feedback.push_str(&diagnostic.render_compact());
+            }
+        } else if !check_res.ok {
+            // tsc reports on stdout, most other checkers on stderr.
+            feedback.push_str(&format!(
+                "Check failed:\n{}{}\n",
+                check_res.stdout, check_res.stderr
+            ));
+        }
+        if !violations.is_empty() {
+            feedback.push_str("Kata constraints violated:\n");
+            for violation in violations {
+                feedback.push_str(&format!("- {}\n", violation));
+            }
+        }
+        if !misplaced.is_empty() {
+            feedback.push_str("Files outside your role changed:\n");
+            for (file, reason) in misplaced {
+                feedback.push_str(&format!("- {}: {}\n", file, reason));
+            }
+        }
+        if !uncovered.is_empty() {
+            feedback.push_str(
+                "Lines you added that no test executes; remove them or implement only what the tests need:\n",
+            );
+            for (file, lines) in uncovered {
+                let lines: Vec<String> = lines.iter().map(u32::to_string).collect();
+                feedback.push_str(&format!("- {}: {}\n", file, lines.join(", ")));
+            }
+        }
+        match self.current_role {
+            _ if test_res.timed_out() => feedback.push_str(&format!(
+                "Tests timed out and were killed; look for an infinite loop, a deadlock or a test waiting on input:\n{}\n{}\n",
+                test_res.stdout, test_res.stderr
+            )),
+            Role::Tester if test_res.ok => {
+                feedback.push_str("Tests passed, but the new test must fail (RED phase).\n")
+            }
+            Role::Implementor | Role::Refactorer if !test_res.ok => {
+                feedback.push_str(&failing_tests(test_res));
+                feedback.push_str(&format!("Tests failed:\n{}\n", test_res.stdout))
+            }
+            _ => {}
+        }
+        feedback
+    }
+
     async fn save_plan(&self, plan: &str) -> Result<()> {
         let plan_dir = self.work_dir.join(".tdd").join("plan");
         fs::create_dir_all(&plan_dir).await?;
@@ -73,6 +279,39 @@ impl TddOrchestrator {
         Ok(())
     }
 
+    async fn advance_test_list(&mut self) -> Result<()> {
+        let Some(test_list) = self.test_list.as_mut() else {
+            return Ok(());
+        };
+        match self.current_role {
+            Role::Tester => self.pending_test_item = test_list.next_index(),
+            Role::Implementor => {
+                if let Some(index) = self.pending_test_item.take() {
+                    test_list.tick(index, self.current_step);
+                    test_list.save(&self.work_dir.join(".tdd")).await?;
+                    println!(
+                        "Test list: {}/{} done",
+                        test_list.done_count(),
+                        test_list.items.len()
+                    );
+                }
+            }
+            Role::Refactorer => {}
+        }
+        Ok(())
+    }
+
+    async fn save_journal(&self) -> Result<()> {
+        let tdd_dir = self.work_dir.join(".tdd");
+        fs::create_dir_all(&tdd_dir).await?;
+        fs::write(
+            tdd_dir.join("journal.json"),
+            serde_json::to_string_pretty(&self.journal)?,
+        )
+        .await?;
+        Ok(())
+    }
+
     async fn save_log(&self, log: &serde_json::Value) -> Result<()> {
         let log_dir = self.work_dir.join(".tdd").join("logs");
         fs::create_dir_all(&log_dir).await?;
@@ -95,34 +334,47 @@ impl Orchestrator for TddOrchestrator {
     async fn next(&mut self) -> Result<()> {
         // 1. Build StepContext
         let repo_state = self.vcs.read_state()?;
-        let ctx = StepContext {
+        let mut ctx = StepContext {
             role: self.current_role,
             step_index: self.current_step,
+            attempt: 1,
             kata_description: self.kata_description.clone(),
             git_last_commit_msg: repo_state.last_commit_message,
             git_last_diff: repo_state.last_diff,
             repo_snapshot_paths: repo_state.files,
+            // Only the Tester picks the next item; the others work on the red test.
+            next_test: self
+                .test_list
+                .as_ref()
+                .filter(|_| self.current_role == Role::Tester)
+                .and_then(TestList::next_item)
+                .map(|item| item.description.clone()),
+            journal: self.journal.clone(),
+            feedback: None,
         };
 
         let agent = self.get_agent(self.current_role);
 
-        // 2. Plan
-        println!(
-            "Planning step {} as {}...",
-            self.current_step,
-            self.current_role.as_str()
-        );
-        let plan_content = agent.plan(&ctx).await?;
-        self.save_plan(&plan_content).await?;
-
         // Loop for attempts
         let mut attempts = 0;
+        let mut attempt_usage = Vec::new();
         loop {
             attempts += 1;
+            ctx.attempt = attempts;
             println!("Attempt {}/{}...", attempts, self.max_attempts);
 
+            // 2. Plan (re-planned on every attempt so feedback can be taken into account)
+            println!(
+                "Planning step {} as {}...",
+                self.current_step,
+                self.current_role.as_str()
+            );
+            let plan_content = agent.plan(&ctx).await?;
+            self.save_plan(&plan_content).await?;
+
             // 3. Edit
             let step_result = agent.edit(&ctx).await?;
+            let agent_usage = self.take_usage();
 
             // 4. Verify
             println!("Verifying...");
@@ -134,34 +386,117 @@ impl Orchestrator for TddOrchestrator {
             }
 
             let check_res = self.runner.check().await?;
+            if !check_res.fixed_files.is_empty() {
+                println!("Applied compiler fixes to {:?}", check_res.fixed_files);
+            }
+            // Compiler fixes count as the role's changes too.
+            let mut changed = step_result.files_changed.clone();
+            for file in &check_res.fixed_files {
+                if !changed.contains(file) {
+                    changed.push(file.clone());
+                }
+            }
+            let misplaced = self
+                .language
+                .profile()
+                .misplaced_files(self.current_role, &changed);
+            let violations = match &self.constraint_checker {
+                Some(checker) if check_res.ok => checker.check()?,
+                _ => Vec::new(),
+            };
             let test_res = self.runner.test().await?;
 
             let success = match self.current_role {
                 Role::Tester => {
                     // Tester MUST fail tests (Red)
-                    // But code must compile (check passes)
-                    check_res.ok && !test_res.ok
+                    // But code must compile (check passes), and a hanging test is not a failing one
+                    (check_res.ok || self.language.profile().is_red_check(&check_res))
+                        && !test_res.ok
+                        && !test_res.timed_out()
+                        && (test_res.tests.is_empty() || test_res.failed_tests().next().is_some())
                 }
                 Role::Implementor | Role::Refactorer => {
                     // Must pass tests (Green)
                     check_res.ok && test_res.ok
                 }
+            } && violations.is_empty()
+                && misplaced.is_empty();
+
+            // Coverage of green steps
+            let coverage = match self.current_role {
+                Role::Implementor | Role::Refactorer if success => {
+                    println!("Measuring coverage...");
+                    self.runner.coverage().await?
+                }
+                _ => None,
+            };
+            let uncovered = match &coverage {
+                Some(coverage)
+                    if self.fail_on_uncovered && self.current_role == Role::Implementor =>
+                {
+                    coverage.uncovered_added_lines(&self.vcs.working_diff()?)
+                }
+                _ => Vec::new(),
+            };
+            let success = success && uncovered.is_empty();
+
+            // 5. Review
+            let mut review_error = None;
+            let verdict = match &self.reviewer {
+                Some(reviewer) if success => {
+                    println!("Reviewing...");
+                    let request = ReviewRequest {
+                        role: self.current_role,
+                        step_index: self.current_step,
+                        kata_description: self.kata_description.clone(),
+                        commit_message: step_result.commit_message.clone(),
+                        notes: step_result.notes.clone(),
+                        instructions: agent.instructions(),
+                        diff: self.vcs.working_diff()?,
+                        check: check_res.clone(),
+                        test: test_res.clone(),
+                    };
+                    // A reviewer that cannot answer does not approve: the
+                    // attempt fails rather than ending the run.
+                    match reviewer.review(&request).await {
+                        Ok(verdict) => Some(verdict),
+                        Err(e) => {
+                            review_error = Some(format!("{:#}", e));
+                            None
+                        }
+                    }
+                }
+                _ => None,
             };
+            let success =
+                success && review_error.is_none() && verdict.as_ref().is_none_or(|v| v.approved);
+            attempt_usage.push(AttemptUsage {
+                attempt: attempts,
+                agent: agent_usage,
+                reviewer: self.take_usage(),
+            });
 
             if success {
                 println!("Success!");
                 // 6. Commit
                 self.vcs.stage_all()?;
+                let model_line = step_result
+                    .model
+                    .as_ref()
+                    .map(|model| format!("- Model: {}\n", model))
+                    .unwrap_or_default();
                 let commit_msg = format!(
-                    "{}\n\nContext:\n- Role: {:?}\n- Step: {}\n- Kata goal: ...\n\nRationale:\n{}\n\nDiff summary:\n{:?}\n\nVerification:\nTests: {}",
+                    "{}\n\nContext:\n- Role: {:?}\n- Step: {}\n{}- Kata goal: ...\n\nRationale:\n{}\n\nDiff summary:\n{:?}\n\nVerification:\nTests: {}",
                     step_result.commit_message,
                     self.current_role,
                     self.current_step,
+                    model_line,
                     step_result.notes,
                     step_result.files_changed,
-                    if test_res.ok { "PASS" } else { "FAIL" }
+                    test_res.headline()
                 );
                 let commit_id = self.vcs.commit(&commit_msg)?;
+                let step_usage = self.record_usage(&attempt_usage);
 
                 // Log
                 let log = serde_json::json!({
@@ -169,23 +504,116 @@ impl Orchestrator for TddOrchestrator {
                     "role": self.current_role,
                     "plan": plan_content,
                     "attempts": attempts,
+                    "model": step_result.model,
                     "commit_id": commit_id,
                     "fmt_output": fmt_res,
                     "check_output": check_res,
+                    "constraint_violations": violations,
                     "test_output": test_res,
+                    "coverage": coverage,
+                    "review": verdict,
+                    "test_item": ctx.next_test,
+                    "usage": {
+                        "attempts": attempt_usage,
+                        "step": step_usage,
+                        "run": self.usage,
+                    },
                 });
                 self.save_log(&log).await?;
+                if let Some(coverage) = &coverage {
+                    println!("Coverage: {}", coverage);
+                    self.coverage_trend
+                        .record(self.current_step, self.current_role, coverage);
+                }
+
+                self.journal.record(JournalEntry {
+                    step: self.current_step,
+                    role: self.current_role,
+                    behaviour: step_result
+                        .commit_message
+                        .lines()
+                        .next()
+                        .unwrap_or_default()
+                        .to_string(),
+                    notes: step_result.notes.clone(),
+                    attempts,
+                    commit_id,
+                });
+                self.save_journal().await?;
+                self.advance_test_list().await?;
 
                 self.rotate_role();
                 return Ok(());
             } else {
                 println!("Verification failed.");
+                for violation in &violations {
+                    println!("  {}", violation);
+                }
+                for (file, reason) in &misplaced {
+                    println!("  {}: {}", file, reason);
+                }
+                for (file, lines) in &uncovered {
+                    println!(
+                        "  {}: {} added lines not covered by tests",
+                        file,
+                        lines.len()
+                    );
+                }
+                if let Some(error) = &review_error {
+                    println!("Review failed: {}", error);
+                }
+                if let Some(verdict) = &verdict {
+                    println!("Rejected by reviewer:");
+                    for reason in &verdict.reasons {
+                        println!("  {}", reason);
+                    }
+                }
                 if attempts >= self.max_attempts {
+                    self.record_usage(&attempt_usage);
                     anyhow::bail!("Max attempts reached for step {}", self.current_step);
                 }
+                let mut feedback = self.failure_feedback(
+                    &check_res,
+                    &test_res,
+                    &violations,
+                    &misplaced,
+                    &uncovered,
+                );
+                if let Some(error) = &review_error {
+                    feedback.push_str(&format!(
+                        "The reviewer could not review your change ({}); it was not committed unreviewed.\n",
+                        error
+                    ));
+                }
+                if let Some(verdict) = &verdict {
+                    feedback.push_str("Rejected by the reviewer:\n");
+                    for reason in &verdict.reasons {
+                        feedback.push_str(&format!("- {}\n", reason));
+                    }
+                }
+                ctx.feedback = Some(feedback);
                 // Undo changes
                 self.vcs.checkout_all()?;
             }
         }
     }
 }
+
+/// One line per failed test case: where it panicked and why.
+fn failing_tests(test_res: &RunnerOutcome) -> String {
+    let mut text = String::new();
+    for test in test_res.failed_tests() {
+        text.push_str(&format!("- {}", test.path()));
+        if let Some(location) = &test.location {
+            text.push_str(&format!(" ({})", location));
+        }
+        if let Some(failure) = &test.failure {
+            text.push_str(&format!(": {}", failure.replace('\n', "\n  ")));
+        }
+        text.push('\n');
+    }
+    if text.is_empty() {
+        return text;
+    }
+    format!("Failing tests:\n{}", text)
+}
@@ -0,0 +1,106 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tokio::fs;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestItem {
+    pub description: String,
+    pub done: bool,
+    /// Step whose test went green for this item.
+    #[serde(default)]
+    pub completed_at_step: Option<u32>,
+}
+
+/// Kent Beck style list of behaviours still to be driven out by tests.
+///
+/// Persisted as `.tdd/test-list.json`, with a `.tdd/test-list.md` checklist
+/// rendered alongside for humans.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TestList {
+    pub items: Vec<TestItem>,
+}
+
+impl TestList {
+    pub fn new(descriptions: Vec<String>) -> Self {
+        Self {
+            items: descriptions
+                .into_iter()
+                .map(|description| TestItem {
+                    description,
+                    done: false,
+                    completed_at_step: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Index of the first unchecked item.
+    pub fn next_index(&self) -> Option<usize> {
+        self.items.iter().position(|item| !item.done)
+    }
+
+    pub fn next_item(&self) -> Option<&TestItem> {
+        self.next_index().map(|i| &self.items[i])
+    }
+
+    pub fn tick(&mut self, index: usize, step: u32) {
+        if let Some(item) = self.items.get_mut(index) {
+            item.done = true;
+            item.completed_at_step = Some(step);
+        }
+    }
+
+    pub fn done_count(&self) -> usize {
+        self.items.iter().filter(|item| item.done).count()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.items.iter().all(|item| item.done)
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from("# Test List\n\n");
+        for item in &self.items {
+            out.push_str(&format!(
+                "- [{}] {}\n",
+                if item.done { "x" } else { " " },
+                item.description
+            ));
+        }
+        out
+    }
+
+    /// Load `test-list.json` from `dir`, if it exists.
+    pub async fn load(dir: &Path) -> Result<Option<Self>> {
+        let path = dir.join("test-list.json");
+        if !path.exists() {
+            return Ok(None);
+        }
+        let json = fs::read_to_string(&path)
+            .await
+            .context(format!("Failed to read test list: {:?}", path))?;
+        Ok(Some(
+            serde_json::from_str(&json).context("Failed to parse test list JSON")?,
+        ))
+    }
+
+    /// Write `test-list.json` and `test-list.md` into `dir`.
+    pub async fn save(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir).await?;
+        fs::write(
+            dir.join("test-list.json"),
+            serde_json::to_string_pretty(self)?,
+        )
+        .await?;
+        fs::write(dir.join("test-list.md"), self.to_markdown()).await?;
+        Ok(())
+    }
+}
+
+/// Turns a kata description into an ordered list of behaviours to test.
+#[async_trait]
+pub trait Planner: Send + Sync {
+    async fn plan_tests(&self, kata_description: &str) -> Result<Vec<String>>;
+}
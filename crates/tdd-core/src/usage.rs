@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::AddAssign;
+
+/// Tokens and money spent on LLM calls.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub calls: u64,
+    /// Input tokens, including `cached_tokens`.
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Input tokens served from the provider's prompt cache.
+    pub cached_tokens: u64,
+    /// Estimated from the pricing table; calls to unpriced models count as zero.
+    pub cost_usd: f64,
+    /// Requests answered from the response cache instead of the provider.
+    #[serde(default)]
+    pub cache_hits: u64,
+}
+
+impl TokenUsage {
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+impl AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.calls += other.calls;
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.cached_tokens += other.cached_tokens;
+        self.cost_usd += other.cost_usd;
+        self.cache_hits += other.cache_hits;
+    }
+}
+
+impl std::fmt::Display for TokenUsage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} calls, {} prompt ({} cached) + {} completion tokens, ${:.4}",
+            self.calls,
+            self.prompt_tokens,
+            self.cached_tokens,
+            self.completion_tokens,
+            self.cost_usd
+        )?;
+        if self.cache_hits > 0 {
+            write!(f, ", {} cached responses", self.cache_hits)?;
+        }
+        Ok(())
+    }
+}
+
+/// Where LLM usage comes from. The orchestrator drains it after each stage
+/// so every call is attributed to the role that made it.
+pub trait UsageMeter: Send + Sync {
+    /// Usage since the previous call.
+    fn take(&self) -> TokenUsage;
+}
+
+/// Usage of one attempt at a step.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct AttemptUsage {
+    pub attempt: u32,
+    /// The acting role's planning and editing calls.
+    pub agent: TokenUsage,
+    pub reviewer: TokenUsage,
+}
+
+/// Usage totals per role and for the whole run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageReport {
+    pub by_role: BTreeMap<String, TokenUsage>,
+    pub total: TokenUsage,
+}
+
+impl UsageReport {
+    pub fn add(&mut self, role: &str, usage: TokenUsage) {
+        if usage.calls == 0 && usage.cache_hits == 0 {
+            return;
+        }
+        *self.by_role.entry(role.to_string()).or_default() += usage;
+        self.total += usage;
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::from("LLM usage:\n");
+        for (role, usage) in &self.by_role {
+            out.push_str(&format!("  {:<12} {}\n", role, usage));
+        }
+        out.push_str(&format!("  {:<12} {}\n", "total", self.total));
+        out
+    }
+}
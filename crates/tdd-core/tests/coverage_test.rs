@@ -0,0 +1,64 @@
+use tdd_core::coverage::added_lines;
+use tdd_core::{Coverage, CoverageTrend, FileCoverage, Role};
+
+const DIFF: &str = "diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,4 +1,5 @@
+ pub fn add(a: i32, b: i32) -> i32 {
+-    0
++    if a == 0 {
++        return b;
++    }
+     a + b
+ }
+diff --git a/tests/add.rs b/tests/add.rs
+deleted file mode 100644
+--- a/tests/add.rs
++++ /dev/null
+@@ -1 +0,0 @@
+-#[test] fn t() {}
+";
+
+#[test]
+fn test_added_lines_are_numbered_in_the_new_file() {
+    let added = added_lines(DIFF);
+    assert_eq!(added.len(), 1);
+    assert_eq!(added["src/lib.rs"], vec![2, 3, 4]);
+}
+
+#[test]
+fn test_only_added_lines_count_as_newly_uncovered() {
+    let coverage = Coverage {
+        lines_covered: 3,
+        lines_total: 5,
+        files: vec![FileCoverage {
+            path: "src/lib.rs".to_string(),
+            lines_covered: 3,
+            lines_total: 5,
+            uncovered_lines: vec![3, 7],
+        }],
+    };
+    assert_eq!(
+        coverage.uncovered_added_lines(DIFF),
+        vec![("src/lib.rs".to_string(), vec![3])]
+    );
+    assert_eq!(coverage.to_string(), "60.0% (3/5 lines)");
+}
+
+#[test]
+fn test_trend_shows_change_per_step() {
+    let mut trend = CoverageTrend::default();
+    let coverage = |lines_covered, lines_total| Coverage {
+        lines_covered,
+        lines_total,
+        files: Vec::new(),
+    };
+    trend.record(2, Role::Implementor, &coverage(3, 4));
+    trend.record(5, Role::Implementor, &coverage(9, 10));
+    assert_eq!(
+        trend.render(),
+        "Coverage:\n  step 2    implementor   75.0% (3/4 lines)\n  step 5    implementor   90.0% (9/10 lines) +15.0\n"
+    );
+}
@@ -0,0 +1,46 @@
+use tdd_core::{JournalEntry, Role, SessionJournal};
+
+fn entry(step: u32, role: Role, behaviour: &str) -> JournalEntry {
+    JournalEntry {
+        step,
+        role,
+        behaviour: behaviour.to_string(),
+        notes: format!("notes for step {}", step),
+        attempts: 1,
+        commit_id: format!("c{}", step),
+    }
+}
+
+#[test]
+fn test_journal_compresses_older_entries() {
+    let mut journal = SessionJournal::new(2);
+
+    journal.record(entry(1, Role::Tester, "test: empty string returns 0"));
+    journal.record(entry(2, Role::Implementor, "feat: return 0 for empty"));
+    journal.record(entry(3, Role::Refactorer, "refactor: extract parse"));
+
+    assert_eq!(
+        journal.summaries().collect::<Vec<_>>(),
+        vec!["step 1 (tester): test: empty string returns 0"]
+    );
+    let recent: Vec<u32> = journal.recent().map(|e| e.step).collect();
+    assert_eq!(recent, vec![2, 3]);
+
+    let rendered = journal.render();
+    assert!(rendered.contains("Earlier steps:\n- step 1 (tester)"));
+    assert!(rendered.contains("notes for step 3"));
+    assert!(!rendered.contains("notes for step 1"));
+}
+
+#[test]
+fn test_old_summaries_are_only_counted() {
+    let mut journal = SessionJournal::new(1);
+    for step in 1..=100 {
+        journal.record(entry(step, Role::Tester, "test: something"));
+    }
+
+    assert_eq!(journal.summaries().count(), 30);
+    let rendered = journal.render();
+    assert!(rendered.contains("- (69 older steps omitted)\n- step 70 (tester)"));
+    assert!(!rendered.contains("step 69 "));
+}
@@ -0,0 +1,91 @@
+use tdd_core::{Language, Role, RunnerOutcome};
+
+#[test]
+fn test_classifies_test_files_per_language() {
+    let cases = [
+        (Language::Rust, "tests/adds.rs", true),
+        (Language::Rust, "src/lib.rs", false),
+        (Language::Python, "tests/test_kata.py", true),
+        (Language::Python, "kata_test.py", true),
+        (Language::Python, "kata.py", false),
+        (Language::TypeScript, "src/kata.test.ts", true),
+        (Language::TypeScript, "src/__tests__/kata.ts", true),
+        (Language::TypeScript, "src/kata.ts", false),
+        (Language::Go, "kata_test.go", true),
+        (Language::Go, "kata.go", false),
+        (Language::Go, "README.md", false),
+    ];
+    for (language, path, is_test) in cases {
+        assert_eq!(
+            language.profile().is_test_file(path),
+            is_test,
+            "{:?} {}",
+            language,
+            path
+        );
+    }
+}
+
+#[test]
+fn test_roles_are_held_to_the_test_file_layout() {
+    let files = |paths: &[&str]| paths.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+    let go = Language::Go.profile();
+
+    let misplaced =
+        go.misplaced_files(Role::Tester, &files(&["kata_test.go", "kata.go", "go.mod"]));
+    assert_eq!(
+        misplaced,
+        vec![(
+            "kata.go".to_string(),
+            "the Tester may only change test files"
+        )]
+    );
+    let misplaced = go.misplaced_files(Role::Implementor, &files(&["kata_test.go", "kata.go"]));
+    assert_eq!(misplaced[0].0, "kata_test.go");
+    assert!(go
+        .misplaced_files(Role::Refactorer, &files(&["kata_test.go", "kata.go"]))
+        .is_empty());
+
+    // Rust tests live inline, so the Tester may edit src/.
+    let rust = Language::Rust.profile();
+    assert!(rust
+        .misplaced_files(Role::Tester, &files(&["src/lib.rs"]))
+        .is_empty());
+    assert_eq!(
+        rust.misplaced_files(Role::Implementor, &files(&["tests/adds.rs"]))
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn test_check_errors_confined_to_test_files_are_red() {
+    let check = |stderr: &str| RunnerOutcome {
+        ok: false,
+        stderr: stderr.to_string(),
+        ..RunnerOutcome::default()
+    };
+    let go = Language::Go.profile();
+
+    let red = check("# kata\n# [kata]\nvet: ./kata_test.go:6:9: undefined: Add\n");
+    assert_eq!(go.files_with_errors(&red), vec!["kata_test.go".to_string()]);
+    assert!(go.is_red_check(&red));
+    assert!(!go.is_red_check(&check("./kata.go:3:1: syntax error: unexpected }\n")));
+    assert!(!go.is_red_check(&check("go: cannot find main module\n")));
+    // Rust tests live inline; the red test must compile.
+    assert!(!Language::Rust
+        .profile()
+        .is_red_check(&check("tests/adds.rs:1:5: error\n")));
+}
+
+#[test]
+fn test_language_names_parse() {
+    assert_eq!(
+        "TypeScript".parse::<Language>().unwrap(),
+        Language::TypeScript
+    );
+    assert_eq!("golang".parse::<Language>().unwrap(), Language::Go);
+    assert!("cobol".parse::<Language>().is_err());
+    let language: Language = serde_json::from_str("\"py\"").unwrap();
+    assert_eq!(language, Language::Python);
+}
@@ -1,9 +1,11 @@
 use anyhow::Result;
 use async_trait::async_trait;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
 use tdd_core::{
-    Agent, Orchestrator, RepoState, Role, Runner, RunnerOutcome, StepContext, StepResult,
-    TddOrchestrator, Vcs,
+    Agent, Coverage, FileCoverage, Language, Orchestrator, Planner, RepoState, ReviewRequest,
+    ReviewVerdict, Reviewer, Role, RunStatus, Runner, RunnerOutcome, StepContext, StepResult,
+    TddOrchestrator, TokenUsage, UsageMeter, Vcs,
 };
 use tempfile::TempDir;
 
@@ -21,11 +23,16 @@ impl Agent for MockAgent {
         Ok(format!("Plan for {:?}", self.role))
     }
 
+    fn instructions(&self) -> Option<String> {
+        Some(format!("Instructions for {:?}", self.role))
+    }
+
     async fn edit(&self, _ctx: &StepContext) -> Result<StepResult> {
         Ok(StepResult {
             files_changed: vec!["test.rs".to_string()],
             commit_message: format!("test: add test for {:?}", self.role),
             notes: "notes".to_string(),
+            model: None,
         })
     }
 }
@@ -40,24 +47,46 @@ impl Runner for MockRunner {
     async fn fmt(&self) -> Result<RunnerOutcome> {
         Ok(RunnerOutcome {
             ok: true,
-            stdout: "".to_string(),
-            stderr: "".to_string(),
+            ..RunnerOutcome::default()
         })
     }
 
     async fn check(&self) -> Result<RunnerOutcome> {
         Ok(RunnerOutcome {
             ok: self.check_ok,
-            stdout: "".to_string(),
-            stderr: "".to_string(),
+            ..RunnerOutcome::default()
         })
     }
 
     async fn test(&self) -> Result<RunnerOutcome> {
         Ok(RunnerOutcome {
             ok: self.test_ok,
-            stdout: "".to_string(),
-            stderr: "".to_string(),
+            ..RunnerOutcome::default()
+        })
+    }
+}
+
+/// Compiles, but the tests hang until the runner kills them.
+struct HangingTestRunner;
+
+#[async_trait]
+impl Runner for HangingTestRunner {
+    async fn fmt(&self) -> Result<RunnerOutcome> {
+        Ok(RunnerOutcome {
+            ok: true,
+            ..RunnerOutcome::default()
+        })
+    }
+
+    async fn check(&self) -> Result<RunnerOutcome> {
+        self.fmt().await
+    }
+
+    async fn test(&self) -> Result<RunnerOutcome> {
+        Ok(RunnerOutcome {
+            ok: false,
+            status: RunStatus::TimedOut,
+            ..RunnerOutcome::default()
         })
     }
 }
@@ -79,6 +108,10 @@ impl Vcs for MockVcs {
         })
     }
 
+    fn working_diff(&self) -> Result<String> {
+        Ok("+new line".to_string())
+    }
+
     fn stage_all(&self) -> Result<()> {
         Ok(())
     }
@@ -136,5 +169,627 @@ async fn test_orchestrator_tester_step() -> Result<()> {
     assert_eq!(commits.lock().unwrap().len(), 1);
     assert!(commits.lock().unwrap()[0].contains("test: add test"));
 
+    let journal: Vec<_> = orchestrator.journal().recent().collect();
+    assert_eq!(journal.len(), 1);
+    assert_eq!(journal[0].role, Role::Tester);
+    assert_eq!(journal[0].behaviour, "test: add test for Tester");
+
+    Ok(())
+}
+
+/// Rejects the first review, approves the rest.
+struct MockReviewer {
+    requests: Arc<Mutex<Vec<ReviewRequest>>>,
+}
+
+#[async_trait]
+impl Reviewer for MockReviewer {
+    async fn review(&self, request: &ReviewRequest) -> Result<ReviewVerdict> {
+        let mut requests = self.requests.lock().unwrap();
+        requests.push(request.clone());
+        Ok(ReviewVerdict {
+            approved: requests.len() > 1,
+            reasons: vec!["test name is unclear".to_string()],
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_reviewer_rejection_counts_as_failed_attempt() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let commits = Arc::new(Mutex::new(Vec::new()));
+    let requests = Arc::new(Mutex::new(Vec::new()));
+
+    let mut orchestrator = TddOrchestrator::new(
+        Box::new(MockAgent { role: Role::Tester }),
+        Box::new(MockAgent {
+            role: Role::Implementor,
+        }),
+        Box::new(MockAgent {
+            role: Role::Refactorer,
+        }),
+        Box::new(MockRunner {
+            check_ok: true,
+            test_ok: false,
+        }),
+        Box::new(MockVcs {
+            commits: commits.clone(),
+        }),
+        "kata".to_string(),
+        3,
+        temp_dir.path().to_path_buf(),
+    )
+    .with_reviewer(Box::new(MockReviewer {
+        requests: requests.clone(),
+    }));
+
+    orchestrator.next().await?;
+
+    assert_eq!(commits.lock().unwrap().len(), 1);
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].role, Role::Tester);
+    assert_eq!(requests[0].diff, "+new line");
+    assert_eq!(
+        requests[0].instructions.as_deref(),
+        Some("Instructions for Tester")
+    );
+    assert_eq!(orchestrator.journal().recent().next().unwrap().attempts, 2);
+
+    Ok(())
+}
+
+struct FailingReviewer;
+
+#[async_trait]
+impl Reviewer for FailingReviewer {
+    async fn review(&self, _request: &ReviewRequest) -> Result<ReviewVerdict> {
+        anyhow::bail!("Failed to parse review JSON")
+    }
+}
+
+#[tokio::test]
+async fn test_reviewer_failure_counts_as_failed_attempt() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let commits = Arc::new(Mutex::new(Vec::new()));
+    let feedback = Arc::new(Mutex::new(Vec::new()));
+
+    let mut orchestrator = TddOrchestrator::new(
+        Box::new(FileAgent {
+            role: Role::Tester,
+            file: "tests/kata.rs",
+            feedback: feedback.clone(),
+        }),
+        Box::new(MockAgent {
+            role: Role::Implementor,
+        }),
+        Box::new(MockAgent {
+            role: Role::Refactorer,
+        }),
+        Box::new(MockRunner {
+            check_ok: true,
+            test_ok: false,
+        }),
+        Box::new(MockVcs {
+            commits: commits.clone(),
+        }),
+        "kata".to_string(),
+        2,
+        temp_dir.path().to_path_buf(),
+    )
+    .with_reviewer(Box::new(FailingReviewer));
+
+    assert!(orchestrator.next().await.is_err());
+    assert!(commits.lock().unwrap().is_empty());
+    let feedback = feedback.lock().unwrap()[1].clone().unwrap();
+    assert!(feedback.contains("could not review your change (Failed to parse review JSON)"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_timed_out_tests_are_not_a_red_step() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let commits = Arc::new(Mutex::new(Vec::new()));
+
+    let mut orchestrator = TddOrchestrator::new(
+        Box::new(MockAgent { role: Role::Tester }),
+        Box::new(MockAgent {
+            role: Role::Implementor,
+        }),
+        Box::new(MockAgent {
+            role: Role::Refactorer,
+        }),
+        Box::new(HangingTestRunner),
+        Box::new(MockVcs {
+            commits: commits.clone(),
+        }),
+        "kata".to_string(),
+        2,
+        temp_dir.path().to_path_buf(),
+    );
+
+    assert!(orchestrator.next().await.is_err());
+    assert!(commits.lock().unwrap().is_empty());
+    assert_eq!(orchestrator.current_role(), Role::Tester);
+    Ok(())
+}
+
+/// Changes one file and remembers the feedback each attempt got.
+struct FileAgent {
+    role: Role,
+    file: &'static str,
+    feedback: Arc<Mutex<Vec<Option<String>>>>,
+}
+
+#[async_trait]
+impl Agent for FileAgent {
+    fn role(&self) -> Role {
+        self.role
+    }
+
+    async fn plan(&self, ctx: &StepContext) -> Result<String> {
+        self.feedback.lock().unwrap().push(ctx.feedback.clone());
+        Ok(String::new())
+    }
+
+    async fn edit(&self, _ctx: &StepContext) -> Result<StepResult> {
+        Ok(StepResult {
+            files_changed: vec![self.file.to_string()],
+            commit_message: "test: something".to_string(),
+            notes: String::new(),
+            model: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_tester_may_only_change_test_files_of_the_language() -> Result<()> {
+    for (file, committed) in [("kata.py", false), ("tests/test_kata.py", true)] {
+        let temp_dir = TempDir::new()?;
+        let commits = Arc::new(Mutex::new(Vec::new()));
+        let feedback = Arc::new(Mutex::new(Vec::new()));
+        let mut orchestrator = TddOrchestrator::new(
+            Box::new(FileAgent {
+                role: Role::Tester,
+                file,
+                feedback: feedback.clone(),
+            }),
+            Box::new(MockAgent {
+                role: Role::Implementor,
+            }),
+            Box::new(MockAgent {
+                role: Role::Refactorer,
+            }),
+            Box::new(MockRunner {
+                check_ok: true,
+                test_ok: false,
+            }),
+            Box::new(MockVcs {
+                commits: commits.clone(),
+            }),
+            "kata".to_string(),
+            2,
+            temp_dir.path().to_path_buf(),
+        )
+        .with_language(Language::Python);
+
+        assert_eq!(orchestrator.next().await.is_ok(), committed);
+        assert_eq!(commits.lock().unwrap().len(), usize::from(committed));
+        if !committed {
+            let feedback = feedback.lock().unwrap()[1].clone().unwrap();
+            assert!(feedback.contains("- kata.py: the Tester may only change test files"));
+        }
+    }
+    Ok(())
+}
+
+/// `tsc --noEmit` failing on the file that was changed, tests failing.
+struct TypeCheckRunner {
+    error_in: &'static str,
+}
+
+#[async_trait]
+impl Runner for TypeCheckRunner {
+    async fn fmt(&self) -> Result<RunnerOutcome> {
+        Ok(RunnerOutcome {
+            ok: true,
+            ..RunnerOutcome::default()
+        })
+    }
+
+    async fn check(&self) -> Result<RunnerOutcome> {
+        Ok(RunnerOutcome {
+            ok: false,
+            stdout: format!(
+                "{}(1,10): error TS2305: Module './kata' has no exported member 'add'.\n",
+                self.error_in
+            ),
+            ..RunnerOutcome::default()
+        })
+    }
+
+    async fn test(&self) -> Result<RunnerOutcome> {
+        Ok(RunnerOutcome {
+            ok: false,
+            ..RunnerOutcome::default()
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_check_failing_only_in_test_files_is_a_red_step() -> Result<()> {
+    for (file, committed) in [("src/kata.test.ts", true), ("src/kata.ts", false)] {
+        let temp_dir = TempDir::new()?;
+        let commits = Arc::new(Mutex::new(Vec::new()));
+        let feedback = Arc::new(Mutex::new(Vec::new()));
+        let mut orchestrator = TddOrchestrator::new(
+            Box::new(FileAgent {
+                role: Role::Tester,
+                file: "src/kata.test.ts",
+                feedback: feedback.clone(),
+            }),
+            Box::new(MockAgent {
+                role: Role::Implementor,
+            }),
+            Box::new(MockAgent {
+                role: Role::Refactorer,
+            }),
+            Box::new(TypeCheckRunner { error_in: file }),
+            Box::new(MockVcs {
+                commits: commits.clone(),
+            }),
+            "kata".to_string(),
+            2,
+            temp_dir.path().to_path_buf(),
+        )
+        .with_language(Language::TypeScript);
+
+        assert_eq!(orchestrator.next().await.is_ok(), committed, "{}", file);
+        assert_eq!(commits.lock().unwrap().len(), usize::from(committed));
+        if !committed {
+            let feedback = feedback.lock().unwrap()[1].clone().unwrap();
+            assert!(feedback.contains("Check failed:\nsrc/kata.ts(1,10)"));
+        }
+    }
+    Ok(())
+}
+
+struct MockPlanner;
+
+#[async_trait]
+impl Planner for MockPlanner {
+    async fn plan_tests(&self, _kata_description: &str) -> Result<Vec<String>> {
+        Ok(vec![
+            "empty string returns 0".to_string(),
+            "single number returns its value".to_string(),
+        ])
+    }
+}
+
+#[tokio::test]
+async fn test_planned_test_list_feeds_the_tester() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let work_dir = temp_dir.path().to_path_buf();
+
+    let mut orchestrator = TddOrchestrator::new(
+        Box::new(MockAgent { role: Role::Tester }),
+        Box::new(MockAgent {
+            role: Role::Implementor,
+        }),
+        Box::new(MockAgent {
+            role: Role::Refactorer,
+        }),
+        Box::new(CoverageRunner {
+            test_runs: AtomicU32::new(0),
+        }),
+        Box::new(MockVcs {
+            commits: Arc::new(Mutex::new(Vec::new())),
+        }),
+        "kata".to_string(),
+        3,
+        work_dir.clone(),
+    );
+
+    let test_list = orchestrator.plan_test_list(&MockPlanner).await?;
+    assert_eq!(test_list.items.len(), 2);
+    assert_eq!(
+        std::fs::read_to_string(work_dir.join(".tdd/test-list.md"))?,
+        "# Test List\n\n- [ ] empty string returns 0\n- [ ] single number returns its value\n"
+    );
+    assert!(!orchestrator.is_complete());
+
+    orchestrator.next().await?;
+
+    let log: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(
+        work_dir.join(".tdd/logs/step-1-tester.json"),
+    )?)?;
+    assert_eq!(log["test_item"], "empty string returns 0");
+
+    orchestrator.next().await?;
+
+    let log: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(
+        work_dir.join(".tdd/logs/step-2-implementor.json"),
+    )?)?;
+    assert!(log["test_item"].is_null());
+
+    Ok(())
+}
+
+/// Reports one call of 100 prompt and 10 completion tokens per stage.
+struct MockMeter;
+
+impl UsageMeter for MockMeter {
+    fn take(&self) -> TokenUsage {
+        TokenUsage {
+            calls: 1,
+            prompt_tokens: 100,
+            completion_tokens: 10,
+            cached_tokens: 0,
+            cost_usd: 0.5,
+            cache_hits: 0,
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_usage_is_attributed_to_attempts_and_roles() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let work_dir = temp_dir.path().to_path_buf();
+
+    let mut orchestrator = TddOrchestrator::new(
+        Box::new(MockAgent { role: Role::Tester }),
+        Box::new(MockAgent {
+            role: Role::Implementor,
+        }),
+        Box::new(MockAgent {
+            role: Role::Refactorer,
+        }),
+        Box::new(MockRunner {
+            check_ok: true,
+            test_ok: false,
+        }),
+        Box::new(MockVcs {
+            commits: Arc::new(Mutex::new(Vec::new())),
+        }),
+        "kata".to_string(),
+        3,
+        work_dir.clone(),
+    )
+    .with_reviewer(Box::new(MockReviewer {
+        requests: Arc::new(Mutex::new(Vec::new())),
+    }))
+    .with_usage_meter(Box::new(MockMeter));
+
+    orchestrator.next().await?;
+
+    // Two attempts, each with an agent and a reviewer stage.
+    let usage = orchestrator.usage();
+    assert_eq!(usage.by_role["tester"].calls, 2);
+    assert_eq!(usage.by_role["reviewer"].calls, 2);
+    assert_eq!(usage.total.prompt_tokens, 400);
+    assert_eq!(usage.total.cost_usd, 2.0);
+
+    let log: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(
+        work_dir.join(".tdd/logs/step-1-tester.json"),
+    )?)?;
+    assert_eq!(log["usage"]["attempts"].as_array().unwrap().len(), 2);
+    assert_eq!(
+        log["usage"]["attempts"][1]["agent"]["completion_tokens"],
+        10
+    );
+    assert_eq!(log["usage"]["step"]["calls"], 4);
+    assert_eq!(log["usage"]["run"]["total"]["calls"], 4);
+    Ok(())
+}
+
+/// Red on the first test run, green afterwards; line 3 of src/lib.rs is never executed.
+struct CoverageRunner {
+    test_runs: AtomicU32,
+}
+
+#[async_trait]
+impl Runner for CoverageRunner {
+    async fn fmt(&self) -> Result<RunnerOutcome> {
+        Ok(RunnerOutcome {
+            ok: true,
+            ..RunnerOutcome::default()
+        })
+    }
+
+    async fn check(&self) -> Result<RunnerOutcome> {
+        self.fmt().await
+    }
+
+    async fn test(&self) -> Result<RunnerOutcome> {
+        Ok(RunnerOutcome {
+            ok: self.test_runs.fetch_add(1, Ordering::SeqCst) > 0,
+            ..RunnerOutcome::default()
+        })
+    }
+
+    async fn coverage(&self) -> Result<Option<Coverage>> {
+        Ok(Some(Coverage {
+            lines_covered: 2,
+            lines_total: 3,
+            files: vec![FileCoverage {
+                path: "src/lib.rs".to_string(),
+                lines_covered: 2,
+                lines_total: 3,
+                uncovered_lines: vec![3],
+            }],
+        }))
+    }
+}
+
+/// Every step adds three lines to src/lib.rs.
+struct DiffVcs {
+    commits: Arc<Mutex<Vec<String>>>,
+}
+
+impl Vcs for DiffVcs {
+    fn init_if_needed(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn read_state(&self) -> Result<RepoState> {
+        MockVcs {
+            commits: self.commits.clone(),
+        }
+        .read_state()
+    }
+
+    fn working_diff(&self) -> Result<String> {
+        Ok("diff --git a/src/lib.rs b/src/lib.rs\n--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -0,0 +1,3 @@\n+pub fn add(a: i32, b: i32) -> i32 {\n+    a + b\n+}\n".to_string())
+    }
+
+    fn stage_all(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn commit(&self, message: &str) -> Result<String> {
+        self.commits.lock().unwrap().push(message.to_string());
+        Ok("commit-hash".to_string())
+    }
+
+    fn checkout_all(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_uncovered_added_lines_fail_the_implementor_when_configured() -> Result<()> {
+    for fail_on_uncovered in [false, true] {
+        let temp_dir = TempDir::new()?;
+        let commits = Arc::new(Mutex::new(Vec::new()));
+        let feedback = Arc::new(Mutex::new(Vec::new()));
+        let mut orchestrator = TddOrchestrator::new(
+            Box::new(MockAgent { role: Role::Tester }),
+            Box::new(FileAgent {
+                role: Role::Implementor,
+                file: "src/lib.rs",
+                feedback: feedback.clone(),
+            }),
+            Box::new(MockAgent {
+                role: Role::Refactorer,
+            }),
+            Box::new(CoverageRunner {
+                test_runs: AtomicU32::new(0),
+            }),
+            Box::new(DiffVcs {
+                commits: commits.clone(),
+            }),
+            "kata".to_string(),
+            2,
+            temp_dir.path().to_path_buf(),
+        )
+        .with_fail_on_uncovered(fail_on_uncovered);
+
+        orchestrator.next().await?;
+        assert!(orchestrator.coverage_trend().is_empty());
+        assert_eq!(orchestrator.next().await.is_ok(), !fail_on_uncovered);
+        if fail_on_uncovered {
+            let feedback = feedback.lock().unwrap()[1].clone().unwrap();
+            assert!(feedback.contains("- src/lib.rs: 3\n"));
+            assert!(orchestrator.coverage_trend().is_empty());
+        } else {
+            let trend = orchestrator.coverage_trend();
+            assert_eq!(trend.points.len(), 1);
+            assert_eq!(trend.points[0].role, Role::Implementor);
+            assert_eq!(trend.points[0].lines_covered, 2);
+        }
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_resume_continues_after_the_last_journal_entry() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let orchestrator = |commits: Arc<Mutex<Vec<String>>>| {
+        TddOrchestrator::new(
+            Box::new(MockAgent { role: Role::Tester }),
+            Box::new(MockAgent {
+                role: Role::Implementor,
+            }),
+            Box::new(MockAgent {
+                role: Role::Refactorer,
+            }),
+            Box::new(MockRunner {
+                check_ok: true,
+                test_ok: false,
+            }),
+            Box::new(MockVcs { commits }),
+            "kata".to_string(),
+            1,
+            temp_dir.path().to_path_buf(),
+        )
+    };
+    let mut first = orchestrator(Arc::new(Mutex::new(Vec::new())));
+    first.next().await?;
+
+    let mut second = orchestrator(Arc::new(Mutex::new(Vec::new())));
+    second.resume().await?;
+
+    assert_eq!(second.current_step(), 2);
+    assert_eq!(second.current_role(), Role::Implementor);
+    assert_eq!(second.journal().last().unwrap().role, Role::Tester);
+    Ok(())
+}
+
+/// Compiles after applying a compiler fix to production code; tests fail.
+struct FixingRunner;
+
+#[async_trait]
+impl Runner for FixingRunner {
+    async fn fmt(&self) -> Result<RunnerOutcome> {
+        Ok(RunnerOutcome {
+            ok: true,
+            ..RunnerOutcome::default()
+        })
+    }
+
+    async fn check(&self) -> Result<RunnerOutcome> {
+        Ok(RunnerOutcome {
+            ok: true,
+            fixed_files: vec!["kata.py".to_string()],
+            ..RunnerOutcome::default()
+        })
+    }
+
+    async fn test(&self) -> Result<RunnerOutcome> {
+        Ok(RunnerOutcome::default())
+    }
+}
+
+#[tokio::test]
+async fn test_files_changed_by_compiler_fixes_are_checked_against_the_role() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let commits = Arc::new(Mutex::new(Vec::new()));
+    let feedback = Arc::new(Mutex::new(Vec::new()));
+    let mut orchestrator = TddOrchestrator::new(
+        Box::new(FileAgent {
+            role: Role::Tester,
+            file: "tests/test_kata.py",
+            feedback: feedback.clone(),
+        }),
+        Box::new(MockAgent {
+            role: Role::Implementor,
+        }),
+        Box::new(MockAgent {
+            role: Role::Refactorer,
+        }),
+        Box::new(FixingRunner),
+        Box::new(MockVcs {
+            commits: commits.clone(),
+        }),
+        "kata".to_string(),
+        2,
+        temp_dir.path().to_path_buf(),
+    )
+    .with_language(Language::Python);
+
+    assert!(orchestrator.next().await.is_err());
+    assert!(commits.lock().unwrap().is_empty());
+    let feedback = feedback.lock().unwrap()[1].clone().unwrap();
+    assert!(feedback.contains("- kata.py: the Tester may only change test files"));
     Ok(())
 }
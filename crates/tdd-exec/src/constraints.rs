@@ -0,0 +1,387 @@
+use anyhow::{Context, Result};
+use ignore::WalkBuilder;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use syn::meta::ParseNestedMeta;
+use syn::visit::{self, Visit};
+use syn::{Attribute, Block, Meta};
+use tdd_core::{ConstraintChecker, ConstraintViolation, Language};
+
+const NUMBER: &str = r"(\d+|one|two|three|four|five|six|seven|eight|nine|ten)";
+
+static FUNCTION_LINES: LazyLock<[Regex; 2]> = LazyLock::new(|| {
+    [
+        format!(
+            r"(?:function|method)s?\b.*?(?:<=|at most|max(?:imum)?|no more than|up to)\s*{NUMBER}\s*(?:lines|loc)"
+        ),
+        format!(
+            r"(?:max(?:imum)?|at most|no more than|up to)\s*{NUMBER}\s*(?:lines|loc)\s*(?:per|for each|in an?|in each)\s*(?:function|method)"
+        ),
+    ]
+    .map(|pattern| Regex::new(&pattern).expect("function length pattern is valid"))
+});
+
+static INSTANCE_VARIABLES: LazyLock<[Regex; 2]> = LazyLock::new(|| {
+    [
+        format!(
+            r"(?:<=|at most|max(?:imum)?|no more than|up to)\s*{NUMBER}\s*(?:instance variables|fields)"
+        ),
+        format!(r"more than\s*{NUMBER}\s*(?:instance variables|fields)"),
+    ]
+    .map(|pattern| Regex::new(&pattern).expect("instance variable pattern is valid"))
+});
+
+static NO_LOOPS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(?:no|without|don't use|do not use)\s+(?:`?(?:for|while)`?\s+)?loops?\b")
+        .expect("loop pattern is valid")
+});
+
+static NO_ELSE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(?:no|without|don't use|do not use)\s+(?:the\s+)?`?else`?\b")
+        .expect("else pattern is valid")
+});
+
+/// Object-calisthenics style rules a kata may impose on production code.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KataConstraints {
+    pub max_function_lines: Option<usize>,
+    pub no_loops: bool,
+    pub no_else: bool,
+    pub max_instance_variables: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct Frontmatter {
+    #[serde(default)]
+    constraints: Option<KataConstraints>,
+}
+
+impl KataConstraints {
+    /// Read constraints from the kata markdown.
+    ///
+    /// A `constraints:` map in YAML frontmatter wins; otherwise the bullets of any
+    /// section whose heading mentions "constraint" are matched against known phrasings
+    /// such as "functions <= 5 lines", "no loops", "no else" or
+    /// "max two instance variables".
+    pub fn from_kata(markdown: &str) -> Result<Self> {
+        if let Some(frontmatter) = frontmatter(markdown) {
+            let parsed: Frontmatter =
+                serde_yaml::from_str(frontmatter).context("Failed to parse kata frontmatter")?;
+            if let Some(constraints) = parsed.constraints {
+                return Ok(constraints);
+            }
+        }
+
+        let mut constraints = KataConstraints::default();
+        for line in constraint_section_lines(markdown) {
+            constraints.apply_phrase(&line.to_lowercase().replace('≤', "<="));
+        }
+        Ok(constraints)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == KataConstraints::default()
+    }
+
+    fn apply_phrase(&mut self, line: &str) {
+        for pattern in FUNCTION_LINES.iter() {
+            if let Some(n) = capture_number(pattern, line) {
+                self.max_function_lines = Some(n);
+            }
+        }
+        for pattern in INSTANCE_VARIABLES.iter() {
+            if let Some(n) = capture_number(pattern, line) {
+                self.max_instance_variables = Some(n);
+            }
+        }
+        if NO_LOOPS.is_match(line) {
+            self.no_loops = true;
+        }
+        if NO_ELSE.is_match(line) {
+            self.no_else = true;
+        }
+    }
+}
+
+fn capture_number(pattern: &Regex, line: &str) -> Option<usize> {
+    let captures = pattern.captures(line)?;
+    let word = captures.get(1)?.as_str();
+    let words = [
+        "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+    ];
+    word.parse()
+        .ok()
+        .or_else(|| words.iter().position(|w| *w == word))
+}
+
+fn frontmatter(markdown: &str) -> Option<&str> {
+    let rest = markdown.trim_start().strip_prefix("---")?;
+    let end = rest.find("\n---")?;
+    Some(&rest[..end])
+}
+
+fn constraint_section_lines(markdown: &str) -> Vec<&str> {
+    let mut lines = Vec::new();
+    let mut section_level = None;
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        let level = trimmed.chars().take_while(|c| *c == '#').count();
+        if level > 0 {
+            match section_level {
+                Some(current) if level > current => {}
+                _ => {
+                    section_level = trimmed
+                        .to_lowercase()
+                        .contains("constraint")
+                        .then_some(level);
+                    continue;
+                }
+            }
+        }
+        if section_level.is_some() {
+            lines.push(line);
+        }
+    }
+    lines
+}
+
+/// Checks Rust sources under the work dir against [`KataConstraints`].
+///
+/// Test code (`#[cfg(test)]` items, `#[test]` functions and the files the Rust
+/// profile counts as tests, such as `tests/` and `benches/` directories) is
+/// exempt: constraints describe the production design.
+pub struct RustConstraintChecker {
+    root: PathBuf,
+    constraints: KataConstraints,
+}
+
+impl RustConstraintChecker {
+    pub fn new(root: impl Into<PathBuf>, constraints: KataConstraints) -> Self {
+        Self {
+            root: root.into(),
+            constraints,
+        }
+    }
+
+    fn check_file(&self, path: &Path, relative: &str) -> Result<Vec<ConstraintViolation>> {
+        let source =
+            std::fs::read_to_string(path).context(format!("Failed to read file: {}", relative))?;
+        // Unparseable files are reported by the check stage, not here.
+        let Ok(file) = syn::parse_file(&source) else {
+            return Ok(Vec::new());
+        };
+        let mut visitor = ConstraintVisitor {
+            constraints: &self.constraints,
+            file: relative,
+            violations: Vec::new(),
+        };
+        visitor.visit_file(&file);
+        Ok(visitor.violations)
+    }
+}
+
+impl ConstraintChecker for RustConstraintChecker {
+    fn check(&self) -> Result<Vec<ConstraintViolation>> {
+        let mut violations = Vec::new();
+        for result in WalkBuilder::new(&self.root)
+            .hidden(false)
+            .git_ignore(true)
+            .build()
+        {
+            let entry = result?;
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "rs") {
+                continue;
+            }
+            let Ok(relative) = path.strip_prefix(&self.root) else {
+                continue;
+            };
+            let relative = relative.to_string_lossy();
+            if ["target", ".git", ".tdd"]
+                .iter()
+                .any(|dir| Path::new(relative.as_ref()).starts_with(dir))
+                || Language::Rust.profile().is_test_file(&relative)
+            {
+                continue;
+            }
+            violations.extend(self.check_file(path, &relative)?);
+        }
+        violations.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+        Ok(violations)
+    }
+}
+
+struct ConstraintVisitor<'a> {
+    constraints: &'a KataConstraints,
+    file: &'a str,
+    violations: Vec<ConstraintViolation>,
+}
+
+impl ConstraintVisitor<'_> {
+    fn report(&mut self, rule: &str, line: usize, message: String) {
+        self.violations.push(ConstraintViolation {
+            rule: rule.to_string(),
+            file: self.file.to_string(),
+            line,
+            message,
+        });
+    }
+
+    fn check_function(&mut self, name: &syn::Ident, block: &Block) {
+        let Some(max) = self.constraints.max_function_lines else {
+            return;
+        };
+        let open = block.brace_token.span.open().start().line;
+        let close = block.brace_token.span.close().start().line;
+        let lines = close.saturating_sub(open + 1);
+        if lines > max {
+            self.report(
+                "max_function_lines",
+                name.span().start().line,
+                format!("function `{}` has {} lines (max {})", name, lines, max),
+            );
+        }
+    }
+}
+
+fn is_test_code(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| match &attr.meta {
+        Meta::Path(path) => path.is_ident("test"),
+        Meta::List(list) if list.path.is_ident("cfg") => {
+            let mut test = false;
+            let parsed = attr.parse_nested_meta(|meta| {
+                test = cfg_requires_test(&meta)?;
+                Ok(())
+            });
+            parsed.is_ok() && test
+        }
+        Meta::List(_) | Meta::NameValue(_) => false,
+    })
+}
+
+/// Whether a cfg predicate only holds under `cfg(test)`: `test` itself or an
+/// `all(..)` that includes it. `not(test)`, `any(..)` and options such as
+/// `feature = "attest"` do not.
+fn cfg_requires_test(meta: &ParseNestedMeta) -> syn::Result<bool> {
+    if meta.path.is_ident("test") {
+        return Ok(true);
+    }
+    if meta.path.is_ident("all") {
+        let mut test = false;
+        meta.parse_nested_meta(|inner| {
+            test |= cfg_requires_test(&inner)?;
+            Ok(())
+        })?;
+        return Ok(test);
+    }
+    // Skip the rest of the predicate.
+    if meta.input.peek(syn::Token![=]) {
+        meta.value()?.parse::<syn::Lit>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        let content;
+        syn::parenthesized!(content in meta.input);
+        content.parse::<proc_macro2::TokenStream>()?;
+    }
+    Ok(false)
+}
+
+impl<'ast> Visit<'ast> for ConstraintVisitor<'_> {
+    fn visit_item_mod(&mut self, node: &'ast syn::ItemMod) {
+        if !is_test_code(&node.attrs) {
+            visit::visit_item_mod(self, node);
+        }
+    }
+
+    fn visit_item_trait(&mut self, node: &'ast syn::ItemTrait) {
+        if !is_test_code(&node.attrs) {
+            visit::visit_item_trait(self, node);
+        }
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast syn::ItemImpl) {
+        if !is_test_code(&node.attrs) {
+            visit::visit_item_impl(self, node);
+        }
+    }
+
+    fn visit_item_fn(&mut self, node: &'ast syn::ItemFn) {
+        if !is_test_code(&node.attrs) {
+            self.check_function(&node.sig.ident, &node.block);
+            visit::visit_item_fn(self, node);
+        }
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast syn::ImplItemFn) {
+        if !is_test_code(&node.attrs) {
+            self.check_function(&node.sig.ident, &node.block);
+            visit::visit_impl_item_fn(self, node);
+        }
+    }
+
+    fn visit_trait_item_fn(&mut self, node: &'ast syn::TraitItemFn) {
+        if is_test_code(&node.attrs) {
+            return;
+        }
+        if let Some(block) = &node.default {
+            self.check_function(&node.sig.ident, block);
+        }
+        visit::visit_trait_item_fn(self, node);
+    }
+
+    fn visit_item_struct(&mut self, node: &'ast syn::ItemStruct) {
+        if is_test_code(&node.attrs) {
+            return;
+        }
+        if let Some(max) = self.constraints.max_instance_variables {
+            let fields = node.fields.len();
+            if fields > max {
+                self.report(
+                    "max_instance_variables",
+                    node.ident.span().start().line,
+                    format!(
+                        "struct `{}` has {} fields (max {})",
+                        node.ident, fields, max
+                    ),
+                );
+            }
+        }
+        visit::visit_item_struct(self, node);
+    }
+
+    fn visit_expr_for_loop(&mut self, node: &'ast syn::ExprForLoop) {
+        if self.constraints.no_loops {
+            let line = node.for_token.span.start().line;
+            self.report("no_loops", line, "`for` loop is not allowed".to_string());
+        }
+        visit::visit_expr_for_loop(self, node);
+    }
+
+    fn visit_expr_while(&mut self, node: &'ast syn::ExprWhile) {
+        if self.constraints.no_loops {
+            let line = node.while_token.span.start().line;
+            self.report("no_loops", line, "`while` loop is not allowed".to_string());
+        }
+        visit::visit_expr_while(self, node);
+    }
+
+    fn visit_expr_loop(&mut self, node: &'ast syn::ExprLoop) {
+        if self.constraints.no_loops {
+            let line = node.loop_token.span.start().line;
+            self.report("no_loops", line, "`loop` is not allowed".to_string());
+        }
+        visit::visit_expr_loop(self, node);
+    }
+
+    fn visit_expr_if(&mut self, node: &'ast syn::ExprIf) {
+        if self.constraints.no_else {
+            if let Some((else_token, _)) = &node.else_branch {
+                let line = else_token.span.start().line;
+                self.report("no_else", line, "`else` branch is not allowed".to_string());
+            }
+        }
+        visit::visit_expr_if(self, node);
+    }
+}
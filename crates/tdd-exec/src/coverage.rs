@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tdd_core::{Coverage, FileCoverage};
+
+use crate::sandbox::warn_once;
+
+/// Which tool measures coverage.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CoverageTool {
+    /// `cargo llvm-cov` if installed, else `cargo tarpaulin`.
+    #[default]
+    Auto,
+    LlvmCov,
+    Tarpaulin,
+}
+
+/// Optional stage that measures line coverage after green steps. The tool
+/// runs the tests again, instrumented, and writes an lcov report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CoverageConfig {
+    pub enabled: bool,
+    pub tool: CoverageTool,
+    /// Custom command instead of the tool's; it must write lcov to `report`.
+    pub command: Option<Vec<String>>,
+    pub report: PathBuf,
+    /// Reject Implementor attempts that add lines no test executes.
+    pub fail_on_uncovered: bool,
+    /// Seconds the command may run; 0 lets it run indefinitely.
+    pub timeout: u64,
+}
+
+impl Default for CoverageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tool: CoverageTool::Auto,
+            command: None,
+            report: PathBuf::from("target/tdd-coverage/lcov.info"),
+            fail_on_uncovered: false,
+            timeout: 900,
+        }
+    }
+}
+
+/// The resolved command of an enabled coverage stage.
+pub(crate) struct CoverageStage {
+    pub(crate) cmd: Vec<String>,
+    pub(crate) report: PathBuf,
+    pub(crate) timeout: u64,
+}
+
+impl CoverageStage {
+    /// `None` when coverage is off or no tool is installed; the latter is
+    /// reported once.
+    pub(crate) fn new(config: &CoverageConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        let report = &config.report;
+        let (cmd, report) = match (&config.command, config.tool) {
+            (Some(cmd), _) => (cmd.clone(), report.clone()),
+            (None, CoverageTool::LlvmCov) if installed("llvm-cov") => llvm_cov(report),
+            (None, CoverageTool::Tarpaulin) if installed("tarpaulin") => tarpaulin(report),
+            (None, CoverageTool::Auto) if installed("llvm-cov") => llvm_cov(report),
+            (None, CoverageTool::Auto) if installed("tarpaulin") => tarpaulin(report),
+            (None, tool) => {
+                let wanted = match tool {
+                    CoverageTool::LlvmCov => "cargo-llvm-cov",
+                    CoverageTool::Tarpaulin => "cargo-tarpaulin",
+                    CoverageTool::Auto => "cargo-llvm-cov or cargo-tarpaulin",
+                };
+                warn_once(&format!(
+                    "Warning: coverage needs {}, which is not installed; coverage is not measured",
+                    wanted
+                ));
+                return None;
+            }
+        };
+        Some(Self {
+            cmd,
+            report,
+            timeout: config.timeout,
+        })
+    }
+}
+
+fn installed(subcommand: &str) -> bool {
+    Command::new("cargo")
+        .args([subcommand, "--version"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+fn llvm_cov(report: &Path) -> (Vec<String>, PathBuf) {
+    let mut cmd: Vec<String> = ["cargo", "llvm-cov", "--lcov", "--output-path"]
+        .map(String::from)
+        .to_vec();
+    cmd.push(report.display().to_string());
+    (cmd, report.to_path_buf())
+}
+
+/// tarpaulin names the report itself: `lcov.info` in the output directory.
+fn tarpaulin(report: &Path) -> (Vec<String>, PathBuf) {
+    let dir = report.parent().unwrap_or(Path::new("."));
+    let mut cmd: Vec<String> = ["cargo", "tarpaulin", "--out", "Lcov", "--output-dir"]
+        .map(String::from)
+        .to_vec();
+    cmd.push(dir.display().to_string());
+    (cmd, dir.join("lcov.info"))
+}
+
+/// Parse an lcov report (`SF:`, `DA:` and `end_of_record` lines). Paths are
+/// made relative to `root`; files outside it, such as dependencies, are left
+/// out.
+pub fn parse_lcov(text: &str, root: &Path) -> Coverage {
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let mut files: BTreeMap<String, BTreeMap<u32, u64>> = BTreeMap::new();
+    let mut current: Option<String> = None;
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(path) = line.strip_prefix("SF:") {
+            current = relative_path(Path::new(path), &root);
+            if let Some(path) = &current {
+                files.entry(path.clone()).or_default();
+            }
+        } else if let Some(data) = line.strip_prefix("DA:") {
+            let Some(path) = &current else {
+                continue;
+            };
+            let mut fields = data.split(',');
+            let (Some(Ok(number)), Some(Ok(hits))) = (
+                fields.next().map(str::parse::<u32>),
+                fields.next().map(str::parse::<u64>),
+            ) else {
+                continue;
+            };
+            // A line may be reported once per function instantiation.
+            let lines = files.entry(path.clone()).or_default();
+            let count = lines.entry(number).or_default();
+            *count = (*count).max(hits);
+        } else if line == "end_of_record" {
+            current = None;
+        }
+    }
+
+    let files: Vec<FileCoverage> = files
+        .into_iter()
+        .map(|(path, lines)| FileCoverage {
+            path,
+            lines_covered: lines.values().filter(|hits| **hits > 0).count() as u32,
+            lines_total: lines.len() as u32,
+            uncovered_lines: lines
+                .iter()
+                .filter(|(_, hits)| **hits == 0)
+                .map(|(line, _)| *line)
+                .collect(),
+        })
+        .collect();
+    Coverage {
+        lines_covered: files.iter().map(|file| file.lines_covered).sum(),
+        lines_total: files.iter().map(|file| file.lines_total).sum(),
+        files,
+    }
+}
+
+fn relative_path(path: &Path, root: &Path) -> Option<String> {
+    let relative = if path.is_absolute() {
+        path.strip_prefix(root).ok()?
+    } else {
+        path
+    };
+    Some(relative.to_string_lossy().replace('\\', "/"))
+}
@@ -0,0 +1,215 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Component, Path};
+use tdd_core::{Diagnostic, Suggestion};
+
+const MESSAGE_FORMAT: &str = "--message-format=json";
+
+/// `cmd` with `--message-format=json` added when it is a cargo build-type
+/// command that does not choose a message format itself; `None` otherwise.
+pub fn json_command(cmd: &[String]) -> Option<Vec<String>> {
+    let (program, args) = cmd.split_first()?;
+    let is_cargo = Path::new(program)
+        .file_stem()
+        .is_some_and(|stem| stem == "cargo");
+    // Flags after `--` belong to rustc or clippy-driver.
+    let cargo_args = args.split(|arg| arg == "--").next().unwrap_or_default();
+    let subcommand = cargo_args
+        .iter()
+        .position(|arg| ["check", "clippy", "build", "rustc"].contains(&arg.as_str()))?;
+    if !is_cargo
+        || cargo_args
+            .iter()
+            .any(|arg| arg.starts_with("--message-format"))
+    {
+        return None;
+    }
+    let mut json = cmd.to_vec();
+    json.insert(subcommand + 2, MESSAGE_FORMAT.to_string());
+    Some(json)
+}
+
+/// Diagnostics from cargo's JSON messages, rustc's own rendering of them, and
+/// the output lines that were not JSON. Duplicates (one file compiled for
+/// several targets) are dropped.
+pub fn parse_cargo_messages(stdout: &str) -> (Vec<Diagnostic>, String, String) {
+    let mut diagnostics = Vec::new();
+    let mut rendered = String::new();
+    let mut other = String::new();
+    let mut seen = HashSet::new();
+    for line in stdout.lines() {
+        let Some(json) = line
+            .starts_with('{')
+            .then(|| serde_json::from_str::<Value>(line).ok())
+            .flatten()
+        else {
+            other.push_str(line);
+            other.push('\n');
+            continue;
+        };
+        if json["reason"] != "compiler-message" {
+            continue;
+        }
+        let message = &json["message"];
+        let text = message["rendered"].as_str().unwrap_or_default();
+        if !seen.insert(text.to_string()) {
+            continue;
+        }
+        rendered.push_str(text);
+        if let Some(diagnostic) = diagnostic(message) {
+            diagnostics.push(diagnostic);
+        }
+    }
+    (diagnostics, rendered, other)
+}
+
+fn diagnostic(message: &Value) -> Option<Diagnostic> {
+    let level = message["level"].as_str()?;
+    let text = message["message"].as_str()?;
+    let spans = message["spans"].as_array().cloned().unwrap_or_default();
+    // Summaries such as "aborting due to 2 previous errors" or "1 warning emitted".
+    if !matches!(level, "error" | "warning")
+        || (spans.is_empty() && (text.starts_with("aborting due to") || text.ends_with("emitted")))
+    {
+        return None;
+    }
+
+    let primary = spans
+        .iter()
+        .find(|span| span["is_primary"] == true)
+        .or(spans.first());
+    let number = |span: Option<&Value>, field: &str| {
+        span.and_then(|span| span[field].as_u64()).unwrap_or(0) as u32
+    };
+
+    let mut notes = Vec::new();
+    let mut suggestions = Vec::new();
+    for (group, child) in message["children"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .enumerate()
+    {
+        let level = child["level"].as_str().unwrap_or("note");
+        let mut note = format!(
+            "{}: {}",
+            level,
+            child["message"].as_str().unwrap_or_default()
+        );
+        let mut replacements = Vec::new();
+        for span in child["spans"].as_array().into_iter().flatten() {
+            let Some(replacement) = span["suggested_replacement"].as_str() else {
+                continue;
+            };
+            replacements.push(format!("`{}`", replacement));
+            suggestions.push(Suggestion {
+                file: span["file_name"].as_str().unwrap_or_default().to_string(),
+                line: number(Some(span), "line_start"),
+                byte_start: span["byte_start"].as_u64().unwrap_or(0) as usize,
+                byte_end: span["byte_end"].as_u64().unwrap_or(0) as usize,
+                replacement: replacement.to_string(),
+                machine_applicable: span["suggestion_applicability"] == "MachineApplicable",
+                group: group as u32,
+            });
+        }
+        if !replacements.is_empty() {
+            note.push_str(&format!(": {}", replacements.join(", ")));
+        }
+        notes.push(note);
+    }
+
+    Some(Diagnostic {
+        level: level.to_string(),
+        code: message["code"]["code"].as_str().map(str::to_string),
+        message: text.to_string(),
+        file: primary
+            .and_then(|span| span["file_name"].as_str())
+            .map(str::to_string),
+        line_start: number(primary, "line_start"),
+        line_end: number(primary, "line_end"),
+        column: number(primary, "column_start"),
+        notes,
+        suggestions,
+    })
+}
+
+/// Apply the machine-applicable suggestions to files under `root`. The spans
+/// of a suggestion are applied together, or not at all when one of them
+/// overlaps a suggestion already applied. Returns the files it changed.
+pub fn apply_suggestions(root: &Path, diagnostics: &[Diagnostic]) -> Result<Vec<String>> {
+    let mut fixes: Vec<Vec<&Suggestion>> = Vec::new();
+    for diagnostic in diagnostics {
+        let mut groups: BTreeMap<u32, Vec<&Suggestion>> = BTreeMap::new();
+        for suggestion in &diagnostic.suggestions {
+            groups.entry(suggestion.group).or_default().push(suggestion);
+        }
+        fixes.extend(groups.into_values().filter(|spans| {
+            spans
+                .iter()
+                .all(|span| span.machine_applicable && is_ours(&span.file))
+        }));
+    }
+
+    let mut sources: BTreeMap<&str, String> = BTreeMap::new();
+    let mut applied: BTreeMap<&str, Vec<&Suggestion>> = BTreeMap::new();
+    for spans in fixes {
+        for span in &spans {
+            if !sources.contains_key(span.file.as_str()) {
+                let source = std::fs::read_to_string(root.join(&span.file))
+                    .context(format!("Failed to read file: {}", span.file))?;
+                sources.insert(&span.file, source);
+            }
+        }
+        let fits = spans.iter().enumerate().all(|(i, span)| {
+            let source = &sources[span.file.as_str()];
+            span.byte_start <= span.byte_end
+                && span.byte_end <= source.len()
+                && source.is_char_boundary(span.byte_start)
+                && source.is_char_boundary(span.byte_end)
+                && applied
+                    .get(span.file.as_str())
+                    .into_iter()
+                    .flatten()
+                    .chain(&spans[..i])
+                    .filter(|other| other.file == span.file)
+                    .all(|other| disjoint(other, span))
+        });
+        if fits {
+            for span in spans {
+                applied.entry(&span.file).or_default().push(span);
+            }
+        }
+    }
+
+    let mut changed = Vec::new();
+    for (file, mut spans) in applied {
+        let mut source = sources.remove(file).unwrap_or_default();
+        // Back to front, so earlier offsets stay valid.
+        spans.sort_by_key(|s| std::cmp::Reverse((s.byte_start, s.byte_end)));
+        for span in spans {
+            source.replace_range(span.byte_start..span.byte_end, &span.replacement);
+        }
+        std::fs::write(root.join(file), source)
+            .context(format!("Failed to write file: {}", file))?;
+        changed.push(file.to_string());
+    }
+    Ok(changed)
+}
+
+/// Dependencies and the standard library are not ours to edit.
+fn is_ours(file: &str) -> bool {
+    let relative = Path::new(file);
+    !relative.is_absolute()
+        && !relative
+            .components()
+            .any(|component| component == Component::ParentDir)
+}
+
+/// One span ends before the other starts; two insertions at the same offset
+/// conflict, since their order is unknown.
+fn disjoint(a: &Suggestion, b: &Suggestion) -> bool {
+    let before =
+        |a: &Suggestion, b: &Suggestion| a.byte_end <= b.byte_start && a.byte_start < b.byte_start;
+    before(a, b) || before(b, a)
+}
@@ -18,6 +18,24 @@ impl GitVcs {
     }
 }
 
+fn format_patch(diff: &git2::Diff) -> Result<String> {
+    let mut diff_buf = Vec::new();
+    diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
+        let origin = line.origin();
+        match origin {
+            '+' | '-' | ' ' => {
+                diff_buf.push(origin as u8);
+                diff_buf.extend_from_slice(line.content());
+            }
+            _ => {
+                diff_buf.extend_from_slice(line.content());
+            }
+        }
+        true
+    })?;
+    Ok(String::from_utf8_lossy(&diff_buf).to_string())
+}
+
 impl Vcs for GitVcs {
     fn init_if_needed(&self) -> Result<()> {
         if !self.root.join(".git").exists() {
@@ -40,42 +58,12 @@ impl Vcs for GitVcs {
                     let tree = commit.tree()?;
                     let parent_tree = parent.tree()?;
                     let diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&tree), None)?;
-
-                    // Format diff
-                    let mut diff_buf = Vec::new();
-                    diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
-                        let origin = line.origin();
-                        match origin {
-                            '+' | '-' | ' ' => {
-                                diff_buf.push(origin as u8);
-                                diff_buf.extend_from_slice(line.content());
-                            }
-                            _ => {
-                                diff_buf.extend_from_slice(line.content());
-                            }
-                        }
-                        true
-                    })?;
-                    String::from_utf8_lossy(&diff_buf).to_string()
+                    format_patch(&diff)?
                 } else {
                     // Initial commit, diff against empty tree
                     let tree = commit.tree()?;
                     let diff = repo.diff_tree_to_tree(None, Some(&tree), None)?;
-                    let mut diff_buf = Vec::new();
-                    diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
-                        let origin = line.origin();
-                        match origin {
-                            '+' | '-' | ' ' => {
-                                diff_buf.push(origin as u8);
-                                diff_buf.extend_from_slice(line.content());
-                            }
-                            _ => {
-                                diff_buf.extend_from_slice(line.content());
-                            }
-                        }
-                        true
-                    })?;
-                    String::from_utf8_lossy(&diff_buf).to_string()
+                    format_patch(&diff)?
                 };
 
                 (msg, diff_str)
@@ -111,6 +99,20 @@ impl Vcs for GitVcs {
         })
     }
 
+    fn working_diff(&self) -> Result<String> {
+        let repo = self.repo()?;
+        let head_tree = match repo.head() {
+            Ok(head) => Some(head.peel_to_tree()?),
+            Err(_) => None,
+        };
+        let mut opts = git2::DiffOptions::new();
+        opts.include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .show_untracked_content(true);
+        let diff = repo.diff_tree_to_workdir_with_index(head_tree.as_ref(), Some(&mut opts))?;
+        format_patch(&diff)
+    }
+
     fn stage_all(&self) -> Result<()> {
         let repo = self.repo()?;
         let mut index = repo.index()?;
@@ -159,6 +161,23 @@ impl Vcs for GitVcs {
         // git checkout .
         // Force checkout head to discard changes in working directory
         repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))?;
+
+        // git clean -fd: a rejected attempt's new files must not reach the next
+        // attempt's diff. Ignored files (target/, .tdd/) stay.
+        let mut opts = git2::StatusOptions::new();
+        opts.include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(false);
+        for entry in repo.statuses(Some(&mut opts))?.iter() {
+            if !entry.status().contains(git2::Status::WT_NEW) {
+                continue;
+            }
+            if let Some(path) = entry.path() {
+                let path = self.root.join(path);
+                std::fs::remove_file(&path)
+                    .context(format!("Failed to remove file: {}", path.display()))?;
+            }
+        }
         Ok(())
     }
 }
@@ -1,5 +1,14 @@
+pub mod constraints;
+pub mod coverage;
+pub mod diagnostics;
 pub mod git;
 pub mod runner;
+pub mod sandbox;
+pub mod test_results;
 
+pub use constraints::{KataConstraints, RustConstraintChecker};
+pub use coverage::{parse_lcov, CoverageConfig, CoverageTool};
 pub use git::GitVcs;
-pub use runner::ProcessRunner;
+pub use runner::{ProcessRunner, StageTimeouts, DEFAULT_MAX_OUTPUT_BYTES};
+pub use sandbox::{SandboxConfig, SandboxPolicy};
+pub use test_results::{parse_test_output, parse_test_output_as};
@@ -1,64 +1,387 @@
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
 use std::process::Stdio;
-use tdd_core::{Runner, RunnerOutcome};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tdd_core::{Coverage, RunStatus, Runner, RunnerOutcome, TestOutputFormat};
+use tokio::io::{AsyncRead, AsyncReadExt};
 use tokio::process::Command;
+use tokio::task::JoinHandle;
+
+use crate::coverage::{parse_lcov, CoverageConfig, CoverageStage};
+use crate::diagnostics::{apply_suggestions, json_command, parse_cargo_messages};
+use crate::sandbox::{Sandbox, SandboxConfig};
+use crate::test_results::parse_test_output_as;
+
+/// Bytes of output kept per stream unless configured otherwise.
+pub const DEFAULT_MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+
+/// How long output is still collected once the command has ended or been
+/// killed; a process that escaped the group may hold the pipes open.
+const DRAIN_GRACE: Duration = Duration::from_secs(2);
+
+/// Time limit of each stage in seconds; 0 lets a stage run indefinitely.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StageTimeouts {
+    pub fmt: u64,
+    pub check: u64,
+    pub test: u64,
+}
+
+impl Default for StageTimeouts {
+    fn default() -> Self {
+        Self {
+            fmt: 60,
+            check: 600,
+            test: 600,
+        }
+    }
+}
 
 pub struct ProcessRunner {
+    work_dir: PathBuf,
     fmt_cmd: Vec<String>,
     check_cmd: Vec<String>,
     test_cmd: Vec<String>,
+    timeouts: StageTimeouts,
+    max_output_bytes: usize,
+    json_diagnostics: bool,
+    auto_fix: bool,
+    test_format: TestOutputFormat,
+    fmt_sandbox: Option<Sandbox>,
+    check_sandbox: Option<Sandbox>,
+    test_sandbox: Option<Sandbox>,
+    coverage: Option<CoverageStage>,
 }
 
 impl ProcessRunner {
     pub fn new(fmt_cmd: Vec<String>, check_cmd: Vec<String>, test_cmd: Vec<String>) -> Self {
         Self {
+            work_dir: PathBuf::from("."),
             fmt_cmd,
             check_cmd,
             test_cmd,
+            timeouts: StageTimeouts::default(),
+            max_output_bytes: DEFAULT_MAX_OUTPUT_BYTES,
+            json_diagnostics: true,
+            auto_fix: false,
+            test_format: TestOutputFormat::Libtest,
+            fmt_sandbox: None,
+            check_sandbox: None,
+            test_sandbox: None,
+            coverage: None,
         }
     }
 
-    async fn run_command(&self, cmd_parts: &[String]) -> Result<RunnerOutcome> {
+    /// Run the commands in `work_dir` rather than the current directory.
+    pub fn with_work_dir(mut self, work_dir: impl Into<PathBuf>) -> Self {
+        self.work_dir = work_dir.into();
+        self
+    }
+
+    pub fn with_timeouts(mut self, timeouts: StageTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Keep at most this many bytes of stdout and of stderr: the start and
+    /// the end, with the middle replaced by a marker.
+    pub fn with_max_output_bytes(mut self, max_output_bytes: usize) -> Self {
+        self.max_output_bytes = max_output_bytes;
+        self
+    }
+
+    /// Run a cargo check command with `--message-format=json` and parse its
+    /// diagnostics (on by default). rustc's rendering of them stays in stderr.
+    pub fn with_json_diagnostics(mut self, json_diagnostics: bool) -> Self {
+        self.json_diagnostics = json_diagnostics;
+        self
+    }
+
+    /// When the check fails, apply the compiler's machine-applicable
+    /// suggestions and check again.
+    pub fn with_auto_fix(mut self, auto_fix: bool) -> Self {
+        self.auto_fix = auto_fix;
+        self
+    }
+
+    /// How to read individual test cases from the test command's output;
+    /// libtest (`cargo test`) unless set.
+    pub fn with_test_format(mut self, test_format: TestOutputFormat) -> Self {
+        self.test_format = test_format;
+        self
+    }
+
+    /// Confine the stages whose policy is enabled; see [`SandboxConfig`].
+    /// Set the work directory first: the stages may write to it.
+    pub fn with_sandbox(mut self, config: &SandboxConfig) -> Result<Self> {
+        self.fmt_sandbox = Sandbox::new(&config.fmt, config, &self.work_dir)?;
+        self.check_sandbox = Sandbox::new(&config.check, config, &self.work_dir)?;
+        self.test_sandbox = Sandbox::new(&config.test, config, &self.work_dir)?;
+        Ok(self)
+    }
+
+    /// Measure coverage after green steps when `config` enables it and its
+    /// tool is installed. The command runs in the test stage's sandbox.
+    pub fn with_coverage(mut self, config: &CoverageConfig) -> Self {
+        self.coverage = CoverageStage::new(config);
+        self
+    }
+
+    async fn run_check(&self) -> Result<RunnerOutcome> {
+        let json = self
+            .json_diagnostics
+            .then(|| json_command(&self.check_cmd))
+            .flatten();
+        let Some(cmd) = json else {
+            return self
+                .run_command(&self.check_cmd, self.timeouts.check, &self.check_sandbox)
+                .await;
+        };
+        let mut outcome = self
+            .run_command(&cmd, self.timeouts.check, &self.check_sandbox)
+            .await?;
+        let (diagnostics, rendered, other) = parse_cargo_messages(&outcome.stdout);
+        outcome.diagnostics = diagnostics;
+        outcome.stdout = other;
+        outcome.stderr = format!("{}{}", rendered, outcome.stderr);
+        Ok(outcome)
+    }
+
+    async fn run_command(
+        &self,
+        cmd_parts: &[String],
+        timeout_secs: u64,
+        sandbox: &Option<Sandbox>,
+    ) -> Result<RunnerOutcome> {
         if cmd_parts.is_empty() {
             return Ok(RunnerOutcome {
                 ok: true,
-                stdout: String::new(),
-                stderr: String::new(),
+                ..RunnerOutcome::default()
             });
         }
 
         let program = &cmd_parts[0];
         let args = &cmd_parts[1..];
 
-        let output = Command::new(program)
+        let mut command = Command::new(program);
+        command
             .args(args)
+            .current_dir(&self.work_dir)
+            .stdin(Stdio::null())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        // A group of its own, so a timeout takes down cargo together with the
+        // test binaries it started.
+        #[cfg(unix)]
+        command.process_group(0);
+        if let Some(sandbox) = sandbox {
+            sandbox.apply(&mut command);
+        }
+        let mut child = command
             .spawn()
-            .context(format!("Failed to spawn command: {}", program))?
-            .wait_with_output()
-            .await
-            .context(format!("Failed to wait for command: {}", program))?;
+            .context(format!("Failed to spawn command: {}", program))?;
+        let pid = child.id();
+        let stdout = Capture::spawn(child.stdout.take(), self.max_output_bytes);
+        let stderr = Capture::spawn(child.stderr.take(), self.max_output_bytes);
 
-        Ok(RunnerOutcome {
-            ok: output.status.success(),
-            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
-            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        let wait = child.wait();
+        let status = match timeout_secs {
+            0 => Some(wait.await),
+            secs => tokio::time::timeout(Duration::from_secs(secs), wait)
+                .await
+                .ok(),
+        }
+        .transpose()
+        .context(format!("Failed to wait for command: {}", program))?;
+
+        if status.is_none() {
+            // The leader is not reaped yet, so its pid still names the group.
+            kill_group(pid);
+            child.start_kill().ok();
+            child.wait().await.ok();
+        }
+        let (stdout, mut stderr) = Capture::finish(stdout, stderr).await;
+
+        Ok(match status {
+            Some(status) => RunnerOutcome {
+                ok: status.success(),
+                status: RunStatus::Exited,
+                stdout,
+                stderr,
+                ..RunnerOutcome::default()
+            },
+            None => {
+                stderr.push_str(&format!(
+                    "\n[{} timed out after {}s and was killed]\n",
+                    program, timeout_secs
+                ));
+                RunnerOutcome {
+                    ok: false,
+                    status: RunStatus::TimedOut,
+                    stdout,
+                    stderr,
+                    ..RunnerOutcome::default()
+                }
+            }
+        })
+    }
+}
+
+#[cfg(unix)]
+fn kill_group(pid: Option<u32>) {
+    if let Some(pid) = pid {
+        // SAFETY: kill(2) has no memory-safety preconditions; a negative pid
+        // addresses the process group the child leads.
+        unsafe {
+            libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_group(_pid: Option<u32>) {}
+
+/// Output of one stream, cut in the middle once it exceeds the limit.
+struct Capture {
+    limit: usize,
+    head: Vec<u8>,
+    tail: VecDeque<u8>,
+    omitted: usize,
+}
+
+impl Capture {
+    fn spawn(
+        reader: Option<impl AsyncRead + Unpin + Send + 'static>,
+        limit: usize,
+    ) -> (Arc<Mutex<Capture>>, JoinHandle<()>) {
+        let capture = Arc::new(Mutex::new(Capture {
+            limit,
+            head: Vec::new(),
+            tail: VecDeque::new(),
+            omitted: 0,
+        }));
+        let shared = capture.clone();
+        let task = tokio::spawn(async move {
+            let Some(mut reader) = reader else {
+                return;
+            };
+            let mut buf = [0u8; 8192];
+            while let Ok(n) = reader.read(&mut buf).await {
+                if n == 0 {
+                    break;
+                }
+                shared.lock().unwrap().push(&buf[..n]);
+            }
+        });
+        (capture, task)
+    }
+
+    /// Wait briefly for both streams to close and return what they produced.
+    async fn finish(
+        (stdout, mut stdout_task): (Arc<Mutex<Capture>>, JoinHandle<()>),
+        (stderr, mut stderr_task): (Arc<Mutex<Capture>>, JoinHandle<()>),
+    ) -> (String, String) {
+        let _ = tokio::time::timeout(DRAIN_GRACE, async {
+            let _ = (&mut stdout_task).await;
+            let _ = (&mut stderr_task).await;
         })
+        .await;
+        stdout_task.abort();
+        stderr_task.abort();
+        let text = |capture: Arc<Mutex<Capture>>| capture.lock().unwrap().text();
+        (text(stdout), text(stderr))
+    }
+
+    fn push(&mut self, mut bytes: &[u8]) {
+        let head_limit = self.limit / 2;
+        if self.head.len() < head_limit {
+            let take = (head_limit - self.head.len()).min(bytes.len());
+            self.head.extend_from_slice(&bytes[..take]);
+            bytes = &bytes[take..];
+        }
+        self.tail.extend(bytes);
+        let excess = self.tail.len().saturating_sub(self.limit - head_limit);
+        if excess > 0 {
+            self.tail.drain(..excess);
+            self.omitted += excess;
+        }
+    }
+
+    fn text(&mut self) -> String {
+        let mut text = String::from_utf8_lossy(&self.head).into_owned();
+        if self.omitted > 0 {
+            text.push_str(&format!(
+                "\n[... {} bytes of output omitted ...]\n",
+                self.omitted
+            ));
+        }
+        text.push_str(&String::from_utf8_lossy(self.tail.make_contiguous()));
+        text
     }
 }
 
 #[async_trait::async_trait]
 impl Runner for ProcessRunner {
     async fn fmt(&self) -> Result<RunnerOutcome> {
-        self.run_command(&self.fmt_cmd).await
+        self.run_command(&self.fmt_cmd, self.timeouts.fmt, &self.fmt_sandbox)
+            .await
     }
 
     async fn check(&self) -> Result<RunnerOutcome> {
-        self.run_command(&self.check_cmd).await
+        let outcome = self.run_check().await?;
+        if !self.auto_fix || outcome.ok {
+            return Ok(outcome);
+        }
+        let fixed_files = apply_suggestions(&self.work_dir, &outcome.diagnostics)?;
+        if fixed_files.is_empty() {
+            return Ok(outcome);
+        }
+        let mut outcome = self.run_check().await?;
+        outcome.fixed_files = fixed_files;
+        Ok(outcome)
     }
 
     async fn test(&self) -> Result<RunnerOutcome> {
-        self.run_command(&self.test_cmd).await
+        let mut outcome = self
+            .run_command(&self.test_cmd, self.timeouts.test, &self.test_sandbox)
+            .await?;
+        outcome.tests = parse_test_output_as(self.test_format, &outcome.stdout, &outcome.stderr);
+        Ok(outcome)
+    }
+
+    async fn coverage(&self) -> Result<Option<Coverage>> {
+        let Some(stage) = &self.coverage else {
+            return Ok(None);
+        };
+        // A stale report must not pass for this step's.
+        let report_path = self.work_dir.join(&stage.report);
+        let _ = std::fs::remove_file(&report_path);
+        let outcome = self
+            .run_command(&stage.cmd, stage.timeout, &self.test_sandbox)
+            .await?;
+        if !outcome.ok {
+            println!(
+                "Coverage not measured, {} failed:\n{}",
+                stage.cmd.join(" "),
+                outcome.stderr
+            );
+            return Ok(None);
+        }
+        match std::fs::read_to_string(&report_path) {
+            Ok(report) => Ok(Some(parse_lcov(&report, &self.work_dir))),
+            Err(e) => {
+                println!(
+                    "Coverage not measured, cannot read {}: {}",
+                    stage.report.display(),
+                    e
+                );
+                Ok(None)
+            }
+        }
     }
 }
@@ -0,0 +1,540 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// Environment variables passed into a sandbox besides the configured ones.
+const DEFAULT_ENV: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "LANG",
+    "LC_ALL",
+    "LC_CTYPE",
+    "LC_MESSAGES",
+    "TERM",
+    "TZ",
+    "TMPDIR",
+    "CC",
+    "CXX",
+    "AR",
+    "LD_LIBRARY_PATH",
+    "PKG_CONFIG_PATH",
+];
+
+/// Toolchain variables are passed in too, unless their name suggests a credential.
+const DEFAULT_ENV_PREFIXES: &[&str] = &["CARGO", "RUST"];
+const SECRET_MARKERS: &[&str] = &["KEY", "TOKEN", "SECRET", "PASSWORD", "CREDENTIAL"];
+
+/// Limits of one stage's command; a limit of 0 leaves that resource alone.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SandboxPolicy {
+    pub enabled: bool,
+    /// CPU seconds per process.
+    pub cpu_secs: u64,
+    /// Address space per process.
+    pub memory_mb: u64,
+    /// Largest file a process may write.
+    pub file_size_mb: u64,
+    /// Processes and threads of the user, counted across the whole system.
+    pub max_processes: u64,
+    /// Keep network access.
+    pub network: bool,
+}
+
+impl Default for SandboxPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cpu_secs: 900,
+            memory_mb: 8192,
+            file_size_mb: 2048,
+            max_processes: 4096,
+            network: false,
+        }
+    }
+}
+
+/// Sandbox of each stage. Commands may write to the runner's work directory,
+/// the target directory, cargo's home, the temp directory, `/dev` and
+/// `writable`, and see only toolchain variables of the environment plus `env`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SandboxConfig {
+    pub fmt: SandboxPolicy,
+    pub check: SandboxPolicy,
+    pub test: SandboxPolicy,
+    pub writable: Vec<PathBuf>,
+    pub env: Vec<String>,
+}
+
+/// How a sandboxed command is cut off from the network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkIsolation {
+    /// A network namespace of its own with only loopback (needs root).
+    Namespace,
+    /// A network namespace inside a user namespace, for unprivileged users.
+    UserNamespace,
+    /// Landlock denies TCP bind and connect, when namespaces are unavailable.
+    Landlock,
+}
+
+/// What the kernel offers the sandbox, probed once per process.
+#[derive(Debug, Clone)]
+pub struct SandboxSupport {
+    /// How the network is cut off, or why it cannot be.
+    pub network: std::result::Result<NetworkIsolation, String>,
+    /// Landlock ABI version, needed to restrict writes.
+    pub landlock_abi: Option<u32>,
+}
+
+/// The confinement of one stage's command.
+pub struct Sandbox {
+    env: Vec<(OsString, OsString)>,
+    #[cfg(target_os = "linux")]
+    confinement: std::sync::Arc<linux::Confinement>,
+}
+
+impl Sandbox {
+    /// The sandbox described by `policy` for commands run in `work_dir`, or
+    /// `None` when it is disabled. Missing kernel support is reported once
+    /// and leaves the command less confined rather than failing the run.
+    pub fn new(
+        policy: &SandboxPolicy,
+        config: &SandboxConfig,
+        work_dir: &Path,
+    ) -> Result<Option<Self>> {
+        if !policy.enabled {
+            return Ok(None);
+        }
+        let env = std::env::vars_os()
+            .filter(|(name, _)| {
+                let name = name.to_string_lossy();
+                config.env.iter().any(|keep| *keep == name) || is_default_env(&name)
+            })
+            .collect();
+
+        #[cfg(target_os = "linux")]
+        {
+            let confinement = linux::Confinement::new(policy, config, work_dir)?;
+            Ok(Some(Self {
+                env,
+                confinement: std::sync::Arc::new(confinement),
+            }))
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = work_dir;
+            warn_once("Warning: sandboxing needs Linux; commands only get a scrubbed environment");
+            Ok(Some(Self { env }))
+        }
+    }
+
+    pub(crate) fn apply(&self, command: &mut Command) {
+        command.env_clear().envs(self.env.iter().cloned());
+        #[cfg(target_os = "linux")]
+        {
+            let confinement = self.confinement.clone();
+            // SAFETY: `confine` only makes async-signal-safe system calls on
+            // data prepared before the fork.
+            unsafe {
+                command.pre_exec(move || confinement.confine());
+            }
+        }
+    }
+}
+
+/// Kernel support for sandboxing on this machine.
+pub fn support() -> &'static SandboxSupport {
+    static SUPPORT: std::sync::OnceLock<SandboxSupport> = std::sync::OnceLock::new();
+    SUPPORT.get_or_init(|| {
+        #[cfg(target_os = "linux")]
+        {
+            linux::probe()
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            SandboxSupport {
+                network: Err("not supported on this platform".to_string()),
+                landlock_abi: None,
+            }
+        }
+    })
+}
+
+fn is_default_env(name: &str) -> bool {
+    DEFAULT_ENV.contains(&name)
+        || (DEFAULT_ENV_PREFIXES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+            && !SECRET_MARKERS.iter().any(|marker| name.contains(marker)))
+}
+
+pub(crate) fn warn_once(warning: &str) {
+    static WARNED: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+    let mut warned = WARNED.lock().unwrap();
+    if !warned.iter().any(|w| w == warning) {
+        println!("{}", warning);
+        warned.push(warning.to_string());
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::{
+        support, warn_once, NetworkIsolation, SandboxConfig, SandboxPolicy, SandboxSupport,
+    };
+    use anyhow::{Context, Result};
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+
+    #[cfg(target_env = "gnu")]
+    type Resource = libc::__rlimit_resource_t;
+    #[cfg(not(target_env = "gnu"))]
+    type Resource = libc::c_int;
+
+    const CREATE_RULESET_VERSION: u32 = 1;
+    const RULE_PATH_BENEATH: libc::c_int = 1;
+
+    const ACCESS_FS_WRITE_FILE: u64 = 1 << 1;
+    const ACCESS_FS_REMOVE_DIR: u64 = 1 << 4;
+    const ACCESS_FS_REMOVE_FILE: u64 = 1 << 5;
+    const ACCESS_FS_MAKE_CHAR: u64 = 1 << 6;
+    const ACCESS_FS_MAKE_DIR: u64 = 1 << 7;
+    const ACCESS_FS_MAKE_REG: u64 = 1 << 8;
+    const ACCESS_FS_MAKE_SOCK: u64 = 1 << 9;
+    const ACCESS_FS_MAKE_FIFO: u64 = 1 << 10;
+    const ACCESS_FS_MAKE_BLOCK: u64 = 1 << 11;
+    const ACCESS_FS_MAKE_SYM: u64 = 1 << 12;
+    /// ABI 2: moving files between directories, denied unless handled.
+    const ACCESS_FS_REFER: u64 = 1 << 13;
+    /// ABI 3.
+    const ACCESS_FS_TRUNCATE: u64 = 1 << 14;
+    /// ABI 4.
+    const ACCESS_NET_BIND_TCP: u64 = 1 << 0;
+    const ACCESS_NET_CONNECT_TCP: u64 = 1 << 1;
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+        handled_access_net: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: i32,
+    }
+
+    /// Everything the child applies between fork and exec.
+    pub(super) struct Confinement {
+        limits: [(Resource, u64); 4],
+        network: Option<NetworkIsolation>,
+        uid_map: Vec<u8>,
+        gid_map: Vec<u8>,
+        ruleset: Option<OwnedFd>,
+    }
+
+    impl Confinement {
+        pub(super) fn new(
+            policy: &SandboxPolicy,
+            config: &SandboxConfig,
+            work_dir: &Path,
+        ) -> Result<Self> {
+            let support = support();
+            let network = match (&support.network, policy.network) {
+                (_, true) => None,
+                (Ok(isolation), false) => Some(*isolation),
+                (Err(reason), false) => {
+                    warn_once(&format!(
+                        "Warning: sandbox cannot cut off the network ({}); commands keep network access",
+                        reason
+                    ));
+                    None
+                }
+            };
+            let ruleset = match support.landlock_abi {
+                Some(abi) => Some(
+                    ruleset(
+                        abi,
+                        &writable(config, work_dir),
+                        network == Some(NetworkIsolation::Landlock),
+                    )
+                    .context("Failed to set up the sandbox's writable directories")?,
+                ),
+                None => {
+                    warn_once(
+                        "Warning: sandbox cannot restrict writes (Landlock unavailable); commands can write anywhere the user can",
+                    );
+                    None
+                }
+            };
+            // SAFETY: getuid and getgid cannot fail.
+            let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+            Ok(Self {
+                limits: [
+                    (libc::RLIMIT_CPU, policy.cpu_secs),
+                    (libc::RLIMIT_AS, policy.memory_mb * 1024 * 1024),
+                    (libc::RLIMIT_FSIZE, policy.file_size_mb * 1024 * 1024),
+                    (libc::RLIMIT_NPROC, policy.max_processes),
+                ],
+                network,
+                uid_map: format!("{} {} 1", uid, uid).into_bytes(),
+                gid_map: format!("{} {} 1", gid, gid).into_bytes(),
+                ruleset,
+            })
+        }
+
+        /// Runs in the forked child: no allocation, only system calls.
+        pub(super) fn confine(&self) -> io::Result<()> {
+            for (resource, limit) in self.limits {
+                if limit == 0 {
+                    continue;
+                }
+                let rlimit = libc::rlimit {
+                    rlim_cur: limit,
+                    rlim_max: limit,
+                };
+                // SAFETY: `rlimit` outlives the call.
+                check(unsafe { libc::setrlimit(resource, &rlimit) })?;
+            }
+            match self.network {
+                Some(NetworkIsolation::Namespace) => {
+                    // SAFETY: unshare has no memory-safety preconditions.
+                    check(unsafe { libc::unshare(libc::CLONE_NEWNET) })?;
+                    loopback_up();
+                }
+                Some(NetworkIsolation::UserNamespace) => {
+                    // SAFETY: as above.
+                    check(unsafe { libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) })?;
+                    // Keep our own ids rather than appear as `nobody`.
+                    write_proc(c"/proc/self/setgroups", b"deny").ok();
+                    write_proc(c"/proc/self/uid_map", &self.uid_map)?;
+                    write_proc(c"/proc/self/gid_map", &self.gid_map)?;
+                    loopback_up();
+                }
+                Some(NetworkIsolation::Landlock) | None => {}
+            }
+            if let Some(ruleset) = &self.ruleset {
+                // SAFETY: prctl and landlock_restrict_self take plain integers.
+                unsafe {
+                    check(libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0))?;
+                    check(
+                        libc::syscall(libc::SYS_landlock_restrict_self, ruleset.as_raw_fd(), 0)
+                            as libc::c_int,
+                    )?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    pub(super) fn probe() -> SandboxSupport {
+        // SAFETY: with a null attribute and the version flag the call only
+        // returns the ABI version.
+        let abi = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                std::ptr::null::<RulesetAttr>(),
+                0usize,
+                CREATE_RULESET_VERSION,
+            )
+        };
+        let landlock_abi = (abi > 0).then_some(abi as u32);
+        let network = probe_network().or_else(|reason| match landlock_abi {
+            Some(abi) if abi >= 4 => Ok(NetworkIsolation::Landlock),
+            _ => Err(reason),
+        });
+        SandboxSupport {
+            network,
+            landlock_abi,
+        }
+    }
+
+    /// Try both kinds of network namespace in a throwaway child.
+    fn probe_network() -> std::result::Result<NetworkIsolation, String> {
+        // SAFETY: the child only calls unshare and _exit, which are
+        // async-signal-safe, before exiting.
+        unsafe {
+            let pid = libc::fork();
+            if pid == 0 {
+                let code = if libc::unshare(libc::CLONE_NEWNET) == 0 {
+                    0
+                } else if libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) == 0 {
+                    1
+                } else {
+                    64 + *libc::__errno_location()
+                };
+                libc::_exit(code);
+            }
+            if pid < 0 {
+                return Err(io::Error::last_os_error().to_string());
+            }
+            let mut status = 0;
+            if libc::waitpid(pid, &mut status, 0) < 0 || !libc::WIFEXITED(status) {
+                return Err("namespace probe did not finish".to_string());
+            }
+            match libc::WEXITSTATUS(status) {
+                0 => Ok(NetworkIsolation::Namespace),
+                1 => Ok(NetworkIsolation::UserNamespace),
+                code => Err(format!(
+                    "network namespaces unavailable: {}",
+                    io::Error::from_raw_os_error(code - 64)
+                )),
+            }
+        }
+    }
+
+    fn writable(config: &SandboxConfig, work_dir: &Path) -> Vec<PathBuf> {
+        let mut paths = vec![
+            work_dir.to_path_buf(),
+            work_dir.join("target"),
+            std::env::temp_dir(),
+            PathBuf::from("/dev"),
+        ];
+        if let Some(target) = std::env::var_os("CARGO_TARGET_DIR") {
+            paths.push(PathBuf::from(target));
+        }
+        // cargo locks its registry and package cache even when offline.
+        let cargo_home = std::env::var_os("CARGO_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cargo")));
+        paths.extend(cargo_home);
+        paths.extend(config.writable.iter().cloned());
+        paths
+    }
+
+    /// A Landlock ruleset that denies writes outside `writable`, and TCP
+    /// entirely when `deny_tcp` is set.
+    fn ruleset(abi: u32, writable: &[PathBuf], deny_tcp: bool) -> Result<OwnedFd> {
+        let mut file_access = ACCESS_FS_WRITE_FILE;
+        if abi >= 3 {
+            file_access |= ACCESS_FS_TRUNCATE;
+        }
+        let mut dir_access = file_access
+            | ACCESS_FS_REMOVE_DIR
+            | ACCESS_FS_REMOVE_FILE
+            | ACCESS_FS_MAKE_CHAR
+            | ACCESS_FS_MAKE_DIR
+            | ACCESS_FS_MAKE_REG
+            | ACCESS_FS_MAKE_SOCK
+            | ACCESS_FS_MAKE_FIFO
+            | ACCESS_FS_MAKE_BLOCK
+            | ACCESS_FS_MAKE_SYM;
+        if abi >= 2 {
+            dir_access |= ACCESS_FS_REFER;
+        }
+        let attr = RulesetAttr {
+            handled_access_fs: dir_access,
+            handled_access_net: if deny_tcp {
+                ACCESS_NET_BIND_TCP | ACCESS_NET_CONNECT_TCP
+            } else {
+                0
+            },
+        };
+        // Kernels before ABI 4 only know the first field.
+        let size = if abi >= 4 {
+            std::mem::size_of::<RulesetAttr>()
+        } else {
+            std::mem::size_of::<u64>()
+        };
+        // SAFETY: `attr` is a valid ruleset attribute of at least `size` bytes.
+        let fd = unsafe { libc::syscall(libc::SYS_landlock_create_ruleset, &attr, size, 0) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error()).context("Failed to create Landlock ruleset");
+        }
+        // SAFETY: the kernel just handed us this descriptor.
+        let ruleset = unsafe { OwnedFd::from_raw_fd(fd as i32) };
+
+        for path in writable {
+            let Ok(metadata) = std::fs::metadata(path) else {
+                continue;
+            };
+            let parent = open_path(path)?;
+            let rule = PathBeneathAttr {
+                allowed_access: if metadata.is_dir() {
+                    dir_access
+                } else {
+                    file_access
+                },
+                parent_fd: parent.as_raw_fd(),
+            };
+            // SAFETY: `rule` is a valid path-beneath attribute.
+            let result = unsafe {
+                libc::syscall(
+                    libc::SYS_landlock_add_rule,
+                    ruleset.as_raw_fd(),
+                    RULE_PATH_BENEATH,
+                    &rule,
+                    0,
+                )
+            };
+            if result < 0 {
+                return Err(io::Error::last_os_error())
+                    .context(format!("Failed to allow writes to {}", path.display()));
+            }
+        }
+        Ok(ruleset)
+    }
+
+    fn open_path(path: &Path) -> Result<OwnedFd> {
+        let mut bytes = path.as_os_str().as_bytes().to_vec();
+        bytes.push(0);
+        // SAFETY: `bytes` is NUL-terminated.
+        let fd = unsafe { libc::open(bytes.as_ptr().cast(), libc::O_PATH | libc::O_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error())
+                .context(format!("Failed to open {}", path.display()));
+        }
+        // SAFETY: as above.
+        Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+    }
+
+    fn check(result: libc::c_int) -> io::Result<()> {
+        if result < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn write_proc(path: &std::ffi::CStr, contents: &[u8]) -> io::Result<()> {
+        // SAFETY: `path` is NUL-terminated and `contents` outlives the write.
+        unsafe {
+            let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+            check(fd)?;
+            let written = libc::write(fd, contents.as_ptr().cast(), contents.len());
+            libc::close(fd);
+            if written < 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    /// A new network namespace starts with loopback down; tests talking to
+    /// a local server need it up. Failure leaves it down.
+    fn loopback_up() {
+        // SAFETY: `request` is a zeroed ifreq naming "lo", valid for both ioctls.
+        unsafe {
+            let socket = libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0);
+            if socket < 0 {
+                return;
+            }
+            let mut request: libc::ifreq = std::mem::zeroed();
+            for (slot, byte) in request.ifr_name.iter_mut().zip(b"lo") {
+                *slot = *byte as libc::c_char;
+            }
+            if libc::ioctl(socket, libc::SIOCGIFFLAGS, &mut request) == 0 {
+                request.ifr_ifru.ifru_flags |= libc::IFF_UP as libc::c_short;
+                libc::ioctl(socket, libc::SIOCSIFFLAGS, &request);
+            }
+            libc::close(socket);
+        }
+    }
+}
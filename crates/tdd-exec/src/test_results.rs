@@ -0,0 +1,402 @@
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use tdd_core::{TestCase, TestOutputFormat, TestStatus};
+
+/// Test cases reported by a test command whose output has the given format.
+pub fn parse_test_output_as(format: TestOutputFormat, stdout: &str, stderr: &str) -> Vec<TestCase> {
+    let output = format!("{}\n{}", stdout, stderr);
+    match format {
+        TestOutputFormat::Libtest => parse_test_output(stdout, stderr),
+        TestOutputFormat::Pytest => parse_pytest_output(&output),
+        TestOutputFormat::Jest => parse_jest_output(&output),
+        TestOutputFormat::GoTest => parse_go_test_output(&output),
+    }
+}
+
+/// Test cases reported in the output of `cargo test`, in libtest's human or
+/// JSON (`--format json`) format, or of `cargo nextest run`.
+///
+/// Failed tests get the message and location of their panic, which every
+/// format prints under the test's thread name.
+pub fn parse_test_output(stdout: &str, stderr: &str) -> Vec<TestCase> {
+    let output = format!("{}\n{}", stdout, stderr);
+    let mut tests: Vec<TestCase> = Vec::new();
+    let mut panics = panics(&output);
+    for line in output.lines() {
+        let parsed = parse_libtest_line(line)
+            .or_else(|| parse_json_line(line, &mut panics))
+            .or_else(|| parse_nextest_line(line));
+        let Some(test) = parsed else {
+            continue;
+        };
+        // nextest repeats failures in its summary; keep the first report.
+        if tests.iter().all(|t| t.path() != test.path()) {
+            tests.push(test);
+        }
+    }
+    for test in &mut tests {
+        if test.status == TestStatus::Failed {
+            if let Some((failure, location)) = panics.remove(&test.path()) {
+                test.failure = Some(failure);
+                test.location = location;
+            }
+        }
+    }
+    tests
+}
+
+fn test_case(path: &str, status: TestStatus, duration_secs: Option<f64>) -> TestCase {
+    let path = path.trim().trim_end_matches(" - should panic");
+    let (module, name) = path.rsplit_once("::").unwrap_or(("", path));
+    TestCase {
+        name: name.to_string(),
+        module: module.to_string(),
+        status,
+        duration_ms: duration_secs.map(|secs| (secs * 1000.0).round() as u64),
+        failure: None,
+        location: None,
+    }
+}
+
+/// `test tests::adds ... ok`, with `<0.002s>` under `--report-time`.
+fn parse_libtest_line(line: &str) -> Option<TestCase> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| {
+        Regex::new(r"^test (.+?) \.\.\. (ok|FAILED|ignored)\b.*?(?: <([\d.]+)s>)?$").unwrap()
+    });
+    let captures = pattern.captures(line.trim_end())?;
+    let status = match &captures[2] {
+        "ok" => TestStatus::Passed,
+        "FAILED" => TestStatus::Failed,
+        _ => TestStatus::Ignored,
+    };
+    let duration = captures.get(3).and_then(|d| d.as_str().parse().ok());
+    Some(test_case(&captures[1], status, duration))
+}
+
+/// `{"type": "test", "event": "failed", "name": ..., "stdout": ...}`; the
+/// panic of a failed test is in its `stdout`.
+fn parse_json_line(
+    line: &str,
+    panics: &mut HashMap<String, (String, Option<String>)>,
+) -> Option<TestCase> {
+    if !line.starts_with('{') {
+        return None;
+    }
+    let json: Value = serde_json::from_str(line).ok()?;
+    if json["type"] != "test" {
+        return None;
+    }
+    let status = match json["event"].as_str()? {
+        "ok" => TestStatus::Passed,
+        "failed" | "timeout" => TestStatus::Failed,
+        "ignored" => TestStatus::Ignored,
+        _ => return None,
+    };
+    if let Some(output) = json["stdout"].as_str() {
+        panics.extend(self::panics(output));
+    }
+    Some(test_case(
+        json["name"].as_str()?,
+        status,
+        json["exec_time"].as_f64(),
+    ))
+}
+
+/// `        PASS [   0.004s] my-crate tests::adds`
+fn parse_nextest_line(line: &str) -> Option<TestCase> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| {
+        Regex::new(r"^\s*([A-Z]+)\s+\[\s*([\d.]*)s?\s*\]\s+(?:\(\d+/\d+\)\s+)?\S+\s+(\S+)\s*$")
+            .unwrap()
+    });
+    let captures = pattern.captures(line)?;
+    let status = match &captures[1] {
+        "PASS" | "LEAK" => TestStatus::Passed,
+        "SKIP" => TestStatus::Ignored,
+        "FAIL" | "TIMEOUT" | "ABORT" | "SIGSEGV" | "SIGABRT" | "SIGBUS" | "SIGILL" => {
+            TestStatus::Failed
+        }
+        _ => return None,
+    };
+    let duration = captures[2].parse().ok();
+    Some(test_case(&captures[3], status, duration))
+}
+
+/// Panic message and location by thread (test) name, from either panic
+/// format: `panicked at src/lib.rs:3:5:` followed by the message, or
+/// (before Rust 1.73) `panicked at 'message', src/lib.rs:3:5`.
+fn panics(output: &str) -> HashMap<String, (String, Option<String>)> {
+    static HEADER: OnceLock<Regex> = OnceLock::new();
+    static INLINE: OnceLock<Regex> = OnceLock::new();
+    let header = HEADER
+        .get_or_init(|| Regex::new(r"^thread '([^']+)'(?: \(\d+\))? panicked at (.*)$").unwrap());
+    let inline = INLINE.get_or_init(|| Regex::new(r"^'(.*)', (\S+:\d+:\d+)$").unwrap());
+
+    let mut panics = HashMap::new();
+    let lines: Vec<&str> = output.lines().collect();
+    for (index, line) in lines.iter().enumerate() {
+        let Some(captures) = header.captures(line) else {
+            continue;
+        };
+        let thread = captures[1].to_string();
+        let rest = &captures[2];
+        let panic = match inline.captures(rest) {
+            Some(old) => (old[1].to_string(), Some(old[2].to_string())),
+            None => {
+                let message: Vec<&str> = lines[index + 1..]
+                    .iter()
+                    .take_while(|line| {
+                        !line.is_empty()
+                            && !line.starts_with("note:")
+                            && !line.starts_with("stack backtrace:")
+                            && !header.is_match(line)
+                    })
+                    .copied()
+                    .collect();
+                let location = rest.strip_suffix(':').unwrap_or(rest);
+                (message.join("\n"), Some(location.to_string()))
+            }
+        };
+        panics.entry(thread).or_insert(panic);
+    }
+    panics
+}
+
+/// `tests/test_kata.py::TestAdd::test_sums PASSED [ 50%]` lines of
+/// `pytest -v`. Failure messages come from the short test summary, locations
+/// from the tracebacks.
+fn parse_pytest_output(output: &str) -> Vec<TestCase> {
+    static RESULT: OnceLock<Regex> = OnceLock::new();
+    static SUMMARY: OnceLock<Regex> = OnceLock::new();
+    static SECTION: OnceLock<Regex> = OnceLock::new();
+    static LOCATION: OnceLock<Regex> = OnceLock::new();
+    let result = RESULT.get_or_init(|| {
+        Regex::new(r"^(\S+?\.py::.+?) (PASSED|FAILED|ERROR|SKIPPED|XFAIL|XPASS)(?:\s|$)").unwrap()
+    });
+    let summary =
+        SUMMARY.get_or_init(|| Regex::new(r"^(?:FAILED|ERROR) (\S+?\.py::.+?) - (.*)$").unwrap());
+    let section = SECTION.get_or_init(|| Regex::new(r"^_{3,} (.+?) _{3,}$").unwrap());
+    let location = LOCATION.get_or_init(|| Regex::new(r"^(\S+\.py:\d+): \w+$").unwrap());
+
+    let mut tests: Vec<TestCase> = Vec::new();
+    let mut messages = HashMap::new();
+    let mut locations = HashMap::new();
+    let mut current_section = None;
+    for line in output.lines() {
+        if let Some(captures) = result.captures(line) {
+            let status = match &captures[2] {
+                "PASSED" | "XPASS" => TestStatus::Passed,
+                "FAILED" | "ERROR" => TestStatus::Failed,
+                _ => TestStatus::Ignored,
+            };
+            let (module, name) = captures[1].rsplit_once("::").unwrap_or(("", &captures[1]));
+            if tests.iter().all(|t| t.module != module || t.name != name) {
+                tests.push(TestCase {
+                    name: name.to_string(),
+                    module: module.to_string(),
+                    status,
+                    duration_ms: None,
+                    failure: None,
+                    location: None,
+                });
+            }
+        } else if let Some(captures) = summary.captures(line) {
+            messages.insert(captures[1].to_string(), captures[2].to_string());
+        } else if let Some(captures) = section.captures(line) {
+            // `test_adds` or `TestAdd.test_sums`
+            current_section = Some(captures[1].replace('.', "::"));
+        } else if let (Some(name), Some(captures)) = (&current_section, location.captures(line)) {
+            locations.insert(name.clone(), captures[1].to_string());
+        }
+    }
+    for test in &mut tests {
+        if test.status != TestStatus::Failed {
+            continue;
+        }
+        let path = test.path();
+        test.failure = messages.remove(&path);
+        test.location = locations
+            .iter()
+            .find(|(name, _)| path.ends_with(&format!("::{}", name)))
+            .map(|(_, location)| location.clone());
+    }
+    tests
+}
+
+/// The verbose reporters of vitest (`✓ src/kata.test.ts > add > sums 1ms`)
+/// and jest (`✓ sums (1 ms)` under `PASS src/kata.test.ts` and its describe
+/// blocks). Failure details follow a `FAIL  file > ...` or `● ... › ...` header.
+fn parse_jest_output(output: &str) -> Vec<TestCase> {
+    static RESULT: OnceLock<Regex> = OnceLock::new();
+    static FILE: OnceLock<Regex> = OnceLock::new();
+    static FAILURE: OnceLock<Regex> = OnceLock::new();
+    static LOCATION: OnceLock<Regex> = OnceLock::new();
+    static CODE_FRAME: OnceLock<Regex> = OnceLock::new();
+    let result = RESULT.get_or_init(|| {
+        Regex::new(r"^(\s*)(✓|✔|√|×|✕|✗|✘|↓|○|✎)\s+(.+?)(?:\s+\(?(\d+(?:\.\d+)?)\s?ms\)?)?(?:\s+\[skipped\])?$")
+            .unwrap()
+    });
+    let file = FILE.get_or_init(|| Regex::new(r"^\s*(PASS|FAIL)\s+(\S+)\s*(?:\(.*\))?$").unwrap());
+    let failure =
+        FAILURE.get_or_init(|| Regex::new(r"^\s*(?:FAIL\s+(\S+) > (.+)|● (.+))$").unwrap());
+    let location = LOCATION
+        .get_or_init(|| Regex::new(r"^\s*(?:at .*?\(?|❯ )([^\s()]+:\d+:\d+)\)?(?:\s|$)").unwrap());
+    let code_frame = CODE_FRAME.get_or_init(|| Regex::new(r"^\s*>?\s*\d*\s*\|").unwrap());
+
+    let mut tests: Vec<TestCase> = Vec::new();
+    // Current jest file and its open describe blocks, by indentation.
+    let mut jest_file: Option<String> = None;
+    let mut describes: Vec<(usize, String)> = Vec::new();
+    let mut in_tree = false;
+    let lines: Vec<&str> = output.lines().collect();
+    for (index, line) in lines.iter().enumerate() {
+        if let Some(captures) = failure.captures(line) {
+            let (module, name) = match (captures.get(1), captures.get(2), captures.get(3)) {
+                (Some(file), Some(name), _) => {
+                    (file.as_str().to_string(), name.as_str().to_string())
+                }
+                (_, _, Some(name)) => (
+                    jest_file.clone().unwrap_or_default(),
+                    name.as_str().replace(" › ", " > "),
+                ),
+                _ => continue,
+            };
+            let mut message = Vec::new();
+            let mut found = None;
+            let mut in_message = true;
+            for next in lines[index + 1..].iter().take(40) {
+                if failure.is_match(next) || file.is_match(next) {
+                    break;
+                }
+                if let Some(captures) = location.captures(next) {
+                    if !captures[1].contains("node_modules") {
+                        found = Some(captures[1].to_string());
+                        break;
+                    }
+                } else if code_frame.is_match(next) {
+                    in_message = false;
+                } else if in_message && !next.trim().is_empty() {
+                    message.push(next.trim());
+                }
+            }
+            if let Some(test) = tests
+                .iter_mut()
+                .find(|t| t.module == module && t.name == name && t.status == TestStatus::Failed)
+            {
+                test.failure = Some(message.join("\n"));
+                test.location = found;
+            }
+            continue;
+        }
+        if let Some(captures) = file.captures(line) {
+            jest_file = Some(captures[2].to_string());
+            in_tree = true;
+            describes.clear();
+            continue;
+        }
+        if line.trim().is_empty() {
+            // The test tree of a jest file ends at the first blank line.
+            in_tree = false;
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        let Some(captures) = result.captures(line) else {
+            if in_tree {
+                describes.retain(|(level, _)| *level < indent);
+                describes.push((indent, line.trim().to_string()));
+            }
+            continue;
+        };
+        let status = match &captures[2] {
+            "✓" | "✔" | "√" => TestStatus::Passed,
+            "×" | "✕" | "✗" | "✘" => TestStatus::Failed,
+            _ => TestStatus::Ignored,
+        };
+        let text = captures[3]
+            .trim_start_matches("skipped ")
+            .trim_start_matches("todo ");
+        let (module, name) = match jest_file.as_ref().filter(|_| in_tree) {
+            Some(file) => {
+                describes.retain(|(level, _)| *level < indent);
+                let mut chain: Vec<&str> =
+                    describes.iter().map(|(_, name)| name.as_str()).collect();
+                chain.push(text);
+                (file.clone(), chain.join(" > "))
+            }
+            None => match text.split_once(" > ") {
+                Some((file, name)) => (file.to_string(), name.to_string()),
+                None => (String::new(), text.to_string()),
+            },
+        };
+        tests.push(TestCase {
+            name,
+            module,
+            status,
+            duration_ms: captures
+                .get(4)
+                .and_then(|ms| ms.as_str().parse::<f64>().ok())
+                .map(|ms| ms.round() as u64),
+            failure: None,
+            location: None,
+        });
+    }
+    tests
+}
+
+/// `--- PASS: TestAdd (0.00s)` lines of `go test -v`, subtests included.
+/// `file_test.go:8: message` lines belong to the test that ran last, and the
+/// package summary (`ok  kata  0.002s`) names the module.
+fn parse_go_test_output(output: &str) -> Vec<TestCase> {
+    static RESULT: OnceLock<Regex> = OnceLock::new();
+    static RUN: OnceLock<Regex> = OnceLock::new();
+    static LOG: OnceLock<Regex> = OnceLock::new();
+    static PACKAGE: OnceLock<Regex> = OnceLock::new();
+    let result = RESULT
+        .get_or_init(|| Regex::new(r"^\s*--- (PASS|FAIL|SKIP): (\S+) \(([\d.]+)s\)").unwrap());
+    let run = RUN.get_or_init(|| Regex::new(r"^=== (?:RUN|CONT)\s+(\S+)").unwrap());
+    let log = LOG.get_or_init(|| Regex::new(r"^\s+(\S+\.go:\d+): (.*)$").unwrap());
+    let package = PACKAGE
+        .get_or_init(|| Regex::new(r"^(?:ok|FAIL)\s+(\S+)\s+(?:[\d.]+s|\(cached\))").unwrap());
+
+    let mut tests: Vec<TestCase> = Vec::new();
+    let mut logs: HashMap<String, Vec<(String, String)>> = HashMap::new();
+    let mut current = None;
+    let mut package_start = 0;
+    for line in output.lines() {
+        if let Some(captures) = run.captures(line) {
+            current = Some(captures[1].to_string());
+        } else if let Some(captures) = result.captures(line) {
+            let status = match &captures[1] {
+                "PASS" => TestStatus::Passed,
+                "FAIL" => TestStatus::Failed,
+                _ => TestStatus::Ignored,
+            };
+            let duration = captures[3].parse::<f64>().ok();
+            tests.push(test_case(&captures[2], status, duration));
+            current = Some(captures[2].to_string());
+        } else if let (Some(name), Some(captures)) = (&current, log.captures(line)) {
+            logs.entry(name.clone())
+                .or_default()
+                .push((captures[1].to_string(), captures[2].to_string()));
+        } else if let Some(captures) = package.captures(line) {
+            for test in &mut tests[package_start..] {
+                test.module = captures[1].to_string();
+            }
+            package_start = tests.len();
+        }
+    }
+    for test in &mut tests {
+        if test.status != TestStatus::Failed {
+            continue;
+        }
+        if let Some(lines) = logs.remove(&test.name) {
+            test.location = lines.first().map(|(location, _)| location.clone());
+            let messages: Vec<&str> = lines.iter().map(|(_, message)| message.as_str()).collect();
+            test.failure = Some(messages.join("\n"));
+        }
+    }
+    tests
+}
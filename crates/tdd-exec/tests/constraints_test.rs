@@ -0,0 +1,147 @@
+use anyhow::Result;
+use std::fs;
+use tdd_core::ConstraintChecker;
+use tdd_exec::{KataConstraints, RustConstraintChecker};
+use tempfile::TempDir;
+
+#[test]
+fn test_parses_constraints_section() -> Result<()> {
+    let kata = r#"
+# Kata
+
+## Rules
+- Write a function that adds numbers
+
+## Constraints
+- Functions ≤ 5 lines
+- No loops
+- No else keyword
+- Max two instance variables per struct
+
+## Getting Started
+- No loops here would not count
+"#;
+
+    let constraints = KataConstraints::from_kata(kata)?;
+
+    assert_eq!(
+        constraints,
+        KataConstraints {
+            max_function_lines: Some(5),
+            no_loops: true,
+            no_else: true,
+            max_instance_variables: Some(2),
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn test_frontmatter_overrides_section() -> Result<()> {
+    let kata = r#"---
+constraints:
+  max_function_lines: 3
+---
+# Kata
+
+## Constraints
+- No loops
+"#;
+
+    let constraints = KataConstraints::from_kata(kata)?;
+
+    assert_eq!(constraints.max_function_lines, Some(3));
+    assert!(!constraints.no_loops);
+    Ok(())
+}
+
+#[test]
+fn test_reports_violations_with_locations() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    fs::create_dir_all(temp_dir.path().join("src"))?;
+    fs::write(
+        temp_dir.path().join("src/lib.rs"),
+        r#"pub struct Rover {
+    x: i32,
+    y: i32,
+    heading: char,
+}
+
+pub fn sum(values: &[i32]) -> i32 {
+    let mut total = 0;
+    for v in values {
+        total += v;
+    }
+    if total > 0 {
+        total
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn loops_are_fine_in_tests() {
+        for _ in 0..3 {}
+    }
+}
+
+pub trait Shape {
+    #[cfg(test)]
+    fn test_helper(&self) {
+        for _ in 0..3 {}
+    }
+}
+
+#[cfg(not(test))]
+pub fn spin() {
+    loop {}
+}
+
+#[cfg(all(test, feature = "slow"))]
+fn slow_helper() {
+    loop {}
+}
+
+#[cfg(feature = "attest")]
+fn attest() {
+    loop {}
+}
+"#,
+    )?;
+    fs::create_dir_all(temp_dir.path().join("crates/rover/tests"))?;
+    fs::write(
+        temp_dir.path().join("crates/rover/tests/moves.rs"),
+        "#[test]\nfn moves() {\n    loop {\n        break;\n    }\n}\n",
+    )?;
+
+    let checker = RustConstraintChecker::new(
+        temp_dir.path(),
+        KataConstraints {
+            max_function_lines: Some(5),
+            no_loops: true,
+            no_else: true,
+            max_instance_variables: Some(2),
+        },
+    );
+
+    let violations: Vec<String> = checker
+        .check()?
+        .iter()
+        .map(|v| format!("{}:{}:{}", v.file, v.line, v.rule))
+        .collect();
+
+    assert_eq!(
+        violations,
+        vec![
+            "src/lib.rs:1:max_instance_variables",
+            "src/lib.rs:7:max_function_lines",
+            "src/lib.rs:9:no_loops",
+            "src/lib.rs:14:no_else",
+            "src/lib.rs:36:no_loops",
+            "src/lib.rs:46:no_loops",
+        ]
+    );
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use anyhow::Result;
+use tdd_core::Runner;
+use tdd_exec::{parse_lcov, CoverageConfig, ProcessRunner};
+use tempfile::TempDir;
+
+#[test]
+fn test_parses_lcov_relative_to_the_root() -> Result<()> {
+    let root = TempDir::new()?;
+    let root_path = root.path().canonicalize()?;
+    let lcov = format!(
+        "SF:{root}/src/lib.rs
+FN:1,add
+DA:1,4
+DA:2,4
+DA:3,0
+DA:2,1
+LF:3
+LH:2
+end_of_record
+SF:/home/user/.cargo/registry/src/dep/lib.rs
+DA:1,0
+end_of_record
+SF:tests/add.rs
+DA:5,1
+end_of_record
+",
+        root = root_path.display()
+    );
+
+    let coverage = parse_lcov(&lcov, root.path());
+
+    assert_eq!(coverage.files.len(), 2);
+    let lib = &coverage.files[0];
+    assert_eq!(lib.path, "src/lib.rs");
+    assert_eq!((lib.lines_covered, lib.lines_total), (2, 3));
+    assert_eq!(lib.uncovered_lines, vec![3]);
+    assert_eq!(coverage.files[1].path, "tests/add.rs");
+    assert_eq!((coverage.lines_covered, coverage.lines_total), (3, 4));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_coverage_is_read_from_the_report_of_a_custom_command() -> Result<()> {
+    let dir = TempDir::new()?;
+    let report = dir.path().join("lcov.info");
+    let script = format!(
+        "printf 'SF:src/lib.rs\\nDA:1,1\\nDA:2,0\\nend_of_record\\n' > {}",
+        report.display()
+    );
+    let config = CoverageConfig {
+        enabled: true,
+        command: Some(vec!["sh".to_string(), "-c".to_string(), script]),
+        report,
+        ..CoverageConfig::default()
+    };
+
+    let off = ProcessRunner::new(vec![], vec![], vec![]);
+    assert_eq!(off.coverage().await?, None);
+
+    let runner = ProcessRunner::new(vec![], vec![], vec![]).with_coverage(&config);
+    let coverage = runner.coverage().await?.unwrap();
+    assert_eq!(coverage.to_string(), "50.0% (1/2 lines)");
+    Ok(())
+}
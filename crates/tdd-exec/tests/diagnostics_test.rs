@@ -0,0 +1,119 @@
+use anyhow::Result;
+use tdd_core::{Diagnostic, Suggestion};
+use tdd_exec::diagnostics::{apply_suggestions, json_command, parse_cargo_messages};
+use tempfile::TempDir;
+
+const CLIPPY: &str = include_str!("fixtures/clippy.jsonl");
+const SOURCE: &str = "pub fn add(a: i32, b: i32) -> i32 { let unused_var = 1; return a + b; }
+pub fn f() -> i32 { let value = 2; valu }
+";
+
+fn strings(parts: &[&str]) -> Vec<String> {
+    parts.iter().map(|part| part.to_string()).collect()
+}
+
+#[test]
+fn test_json_format_is_added_to_cargo_commands_only() {
+    assert_eq!(
+        json_command(&strings(&[
+            "cargo", "clippy", "--all", "--", "-D", "warnings"
+        ])),
+        Some(strings(&[
+            "cargo",
+            "clippy",
+            "--message-format=json",
+            "--all",
+            "--",
+            "-D",
+            "warnings"
+        ]))
+    );
+    assert_eq!(
+        json_command(&strings(&["cargo", "check", "--message-format=short"])),
+        None
+    );
+    assert_eq!(json_command(&strings(&["cargo", "fmt"])), None);
+    assert_eq!(json_command(&strings(&["make", "check"])), None);
+}
+
+#[test]
+fn test_parses_diagnostics_and_keeps_rendered_output() {
+    let stdout = format!("{}{}", CLIPPY, CLIPPY);
+
+    let (diagnostics, rendered, _) = parse_cargo_messages(&stdout);
+
+    assert_eq!(diagnostics.len(), 2);
+    let unresolved = &diagnostics[0];
+    assert_eq!(unresolved.level, "error");
+    assert_eq!(unresolved.code.as_deref(), Some("E0425"));
+    assert_eq!(unresolved.file.as_deref(), Some("src/lib.rs"));
+    assert_eq!((unresolved.line_start, unresolved.column), (2, 36));
+    assert!(!unresolved.suggestions[0].machine_applicable);
+    assert_eq!(
+        unresolved.render_compact(),
+        "error[E0425] src/lib.rs:2:36: cannot find value `valu` in this scope\n  help: a local variable with a similar name exists: `value`\n"
+    );
+    assert!(diagnostics[1].suggestions[0].machine_applicable);
+    assert_eq!(rendered.matches("error[E0425]").count(), 1);
+    assert!(rendered.contains("--> src/lib.rs:1:41"));
+}
+
+#[test]
+fn test_applies_only_machine_applicable_suggestions() -> Result<()> {
+    let dir = TempDir::new()?;
+    std::fs::create_dir(dir.path().join("src"))?;
+    std::fs::write(dir.path().join("src/lib.rs"), SOURCE)?;
+    let (diagnostics, _, _) = parse_cargo_messages(CLIPPY);
+
+    let changed = apply_suggestions(dir.path(), &diagnostics)?;
+
+    assert_eq!(changed, vec!["src/lib.rs".to_string()]);
+    let fixed = std::fs::read_to_string(dir.path().join("src/lib.rs"))?;
+    assert_eq!(fixed, SOURCE.replace("let unused_var", "let _unused_var"));
+    Ok(())
+}
+
+#[test]
+fn test_a_suggestion_is_applied_whole_or_not_at_all() -> Result<()> {
+    let dir = TempDir::new()?;
+    std::fs::create_dir(dir.path().join("src"))?;
+    let source = "fn f() { let x = 1; let y = 2; }\n";
+    std::fs::write(dir.path().join("src/lib.rs"), source)?;
+    let span = |text: &str, replacement: &str| {
+        let start = source.find(text).unwrap();
+        Suggestion {
+            file: "src/lib.rs".to_string(),
+            line: 1,
+            byte_start: start,
+            byte_end: start + text.len(),
+            replacement: replacement.to_string(),
+            machine_applicable: true,
+            group: 0,
+        }
+    };
+    let diagnostic = |suggestions: Vec<Suggestion>| Diagnostic {
+        level: "warning".to_string(),
+        code: None,
+        message: "unused variable".to_string(),
+        file: Some("src/lib.rs".to_string()),
+        line_start: 1,
+        line_end: 1,
+        column: 1,
+        notes: Vec::new(),
+        suggestions,
+    };
+    let diagnostics = vec![
+        diagnostic(vec![span("x", "_x")]),
+        // Its first span overlaps the fix above, so its second is left alone too.
+        diagnostic(vec![span("let x", "let mut x"), span("y", "_y")]),
+    ];
+
+    let changed = apply_suggestions(dir.path(), &diagnostics)?;
+
+    assert_eq!(changed, vec!["src/lib.rs".to_string()]);
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("src/lib.rs"))?,
+        "fn f() { let _x = 1; let y = 2; }\n"
+    );
+    Ok(())
+}
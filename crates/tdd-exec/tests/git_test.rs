@@ -0,0 +1,42 @@
+use anyhow::Result;
+use std::fs;
+use tdd_core::Vcs;
+use tdd_exec::GitVcs;
+use tempfile::TempDir;
+
+#[test]
+fn test_checkout_all_removes_files_of_a_rejected_attempt() -> Result<()> {
+    let dir = TempDir::new()?;
+    let vcs = GitVcs::new(dir.path());
+    vcs.init_if_needed()?;
+    let repo = git2::Repository::open(dir.path())?;
+    repo.config()?.set_str("user.name", "Test")?;
+    repo.config()?.set_str("user.email", "test@local")?;
+    fs::write(dir.path().join(".gitignore"), "/target\n")?;
+    fs::write(dir.path().join("lib.rs"), "pub fn add() {}\n")?;
+    vcs.stage_all()?;
+    vcs.commit("initial")?;
+
+    fs::create_dir_all(dir.path().join("src/nested"))?;
+    fs::write(
+        dir.path().join("src/nested/stray.rs"),
+        "pub fn stray() {}\n",
+    )?;
+    fs::write(dir.path().join("lib.rs"), "pub fn sub() {}\n")?;
+    fs::create_dir_all(dir.path().join("target"))?;
+    fs::write(dir.path().join("target/build.log"), "kept")?;
+    assert!(vcs.working_diff()?.contains("stray"));
+
+    vcs.checkout_all()?;
+
+    let diff = vcs.working_diff()?;
+    assert!(!diff.contains("stray"), "{}", diff);
+    assert!(diff.is_empty(), "{}", diff);
+    assert!(!dir.path().join("src/nested/stray.rs").exists());
+    assert_eq!(
+        fs::read_to_string(dir.path().join("lib.rs"))?,
+        "pub fn add() {}\n"
+    );
+    assert!(dir.path().join("target/build.log").exists());
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use anyhow::Result;
+use std::time::{Duration, Instant};
+use tdd_core::{RunStatus, Runner};
+use tdd_exec::{ProcessRunner, StageTimeouts};
+
+fn sh(script: &str) -> Vec<String> {
+    vec!["sh".to_string(), "-c".to_string(), script.to_string()]
+}
+
+fn runner(test_cmd: Vec<String>) -> ProcessRunner {
+    ProcessRunner::new(Vec::new(), sh("exit 1"), test_cmd)
+}
+
+#[tokio::test]
+async fn test_exit_status_is_reported() -> Result<()> {
+    let runner = runner(sh("echo out; echo err >&2"));
+
+    let test = runner.test().await?;
+    assert!(test.ok);
+    assert_eq!(test.status, RunStatus::Exited);
+    assert_eq!(test.stdout, "out\n");
+    assert_eq!(test.stderr, "err\n");
+
+    let check = runner.check().await?;
+    assert!(!check.ok);
+    assert_eq!(check.verdict(), "FAIL");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_timeout_kills_the_whole_process_group() -> Result<()> {
+    // The background sleep keeps stdout open: only killing the group ends the run early.
+    let runner = runner(sh("sleep 30 & echo started; wait")).with_timeouts(StageTimeouts {
+        test: 1,
+        ..StageTimeouts::default()
+    });
+
+    let started = Instant::now();
+    let outcome = runner.test().await?;
+
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert!(!outcome.ok);
+    assert!(outcome.timed_out());
+    assert_eq!(outcome.verdict(), "TIMEOUT");
+    assert_eq!(outcome.stdout, "started\n");
+    assert!(outcome.stderr.contains("timed out after 1s"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_captured_output_is_capped() -> Result<()> {
+    let runner = runner(sh("echo first; yes filler | head -n 100000; echo last"))
+        .with_max_output_bytes(1000);
+
+    let outcome = runner.test().await?;
+
+    assert!(outcome.ok);
+    assert!(outcome.stdout.len() < 1100);
+    assert!(outcome.stdout.starts_with("first\n"));
+    assert!(outcome.stdout.ends_with("last\n"));
+    assert!(outcome.stdout.contains("bytes of output omitted"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stage_reports_test_cases() -> Result<()> {
+    let runner = runner(sh(
+        "echo 'test math::adds ... ok'; echo 'test math::divides ... FAILED'; exit 101",
+    ));
+
+    let outcome = runner.test().await?;
+
+    assert_eq!(outcome.tests.len(), 2);
+    assert_eq!(
+        outcome.failed_tests().next().unwrap().path(),
+        "math::divides"
+    );
+    assert_eq!(outcome.headline(), "FAIL (1 passed, 1 failed, 0 ignored)");
+    Ok(())
+}
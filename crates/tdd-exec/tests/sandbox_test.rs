@@ -0,0 +1,145 @@
+use anyhow::Result;
+use tdd_core::Runner;
+use tdd_exec::sandbox::{support, NetworkIsolation};
+use tdd_exec::{ProcessRunner, SandboxConfig, SandboxPolicy};
+use tempfile::TempDir;
+
+fn sh(script: &str) -> Vec<String> {
+    vec!["sh".to_string(), "-c".to_string(), script.to_string()]
+}
+
+fn sandboxed(test_cmd: Vec<String>, policy: SandboxPolicy) -> Result<ProcessRunner> {
+    let config = SandboxConfig {
+        test: SandboxPolicy {
+            enabled: true,
+            ..policy
+        },
+        ..SandboxConfig::default()
+    };
+    ProcessRunner::new(
+        Vec::new(),
+        sh("echo ${SANDBOX_TEST_API_KEY:-unset}"),
+        test_cmd,
+    )
+    .with_sandbox(&config)
+}
+
+#[tokio::test]
+async fn test_environment_is_scrubbed_in_sandboxed_stages_only() -> Result<()> {
+    std::env::set_var("SANDBOX_TEST_API_KEY", "sk-secret");
+    let runner = sandboxed(
+        sh("echo ${SANDBOX_TEST_API_KEY:-unset} ${PATH:+path}"),
+        SandboxPolicy::default(),
+    )?;
+
+    assert_eq!(runner.test().await?.stdout, "unset path\n");
+    assert_eq!(runner.check().await?.stdout, "sk-secret\n");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_resource_limits_apply() -> Result<()> {
+    let runner = sandboxed(
+        sh("ulimit -t; ulimit -f"),
+        SandboxPolicy {
+            cpu_secs: 7,
+            file_size_mb: 0,
+            ..SandboxPolicy::default()
+        },
+    )?;
+
+    let outcome = runner.test().await?;
+
+    assert!(outcome.ok, "{}", outcome.stderr);
+    assert_eq!(outcome.stdout, "7\nunlimited\n");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_network_is_cut_off() -> Result<()> {
+    if !matches!(
+        support().network,
+        Ok(NetworkIsolation::Namespace | NetworkIsolation::UserNamespace)
+    ) {
+        return Ok(());
+    }
+    let interfaces = "tail -n +3 /proc/self/net/dev | cut -d: -f1 | tr -d ' '";
+
+    let cut_off = sandboxed(sh(interfaces), SandboxPolicy::default())?;
+    assert_eq!(cut_off.test().await?.stdout, "lo\n");
+
+    let connected = sandboxed(
+        sh(interfaces),
+        SandboxPolicy {
+            network: true,
+            ..SandboxPolicy::default()
+        },
+    )?;
+    assert_eq!(
+        connected.test().await?.stdout,
+        ProcessRunner::new(Vec::new(), Vec::new(), sh(interfaces))
+            .test()
+            .await?
+            .stdout
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_writes_are_limited_to_the_work_and_temp_dirs() -> Result<()> {
+    if support().landlock_abi.is_none() {
+        return Ok(());
+    }
+    let temp = TempDir::new()?;
+    let outside = TempDir::new_in(env!("CARGO_TARGET_TMPDIR"))?;
+    let runner = sandboxed(
+        sh(&format!(
+            "echo ok > {}/allowed; echo no > {}/denied",
+            temp.path().display(),
+            outside.path().display()
+        )),
+        SandboxPolicy::default(),
+    )?;
+
+    let outcome = runner.test().await?;
+
+    assert!(!outcome.ok);
+    assert!(
+        outcome.stderr.contains("Permission denied"),
+        "{}",
+        outcome.stderr
+    );
+    assert!(temp.path().join("allowed").exists());
+    assert!(!outside.path().join("denied").exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_the_runners_work_dir_is_writable_when_it_is_not_the_cwd() -> Result<()> {
+    if support().landlock_abi.is_none() {
+        return Ok(());
+    }
+    // Outside the temp directory, which is writable anyway.
+    let work_dir = TempDir::new_in(env!("CARGO_TARGET_TMPDIR"))?;
+    let config = SandboxConfig {
+        test: SandboxPolicy {
+            enabled: true,
+            ..SandboxPolicy::default()
+        },
+        ..SandboxConfig::default()
+    };
+    let runner = ProcessRunner::new(
+        Vec::new(),
+        Vec::new(),
+        sh("echo ok > kata.txt && mkdir -p target/debug && echo ok > target/debug/build"),
+    )
+    .with_work_dir(work_dir.path())
+    .with_sandbox(&config)?;
+
+    let outcome = runner.test().await?;
+
+    assert!(outcome.ok, "{}", outcome.stderr);
+    assert!(work_dir.path().join("kata.txt").exists());
+    assert!(work_dir.path().join("target/debug/build").exists());
+    Ok(())
+}
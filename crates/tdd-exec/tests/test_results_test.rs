@@ -0,0 +1,239 @@
+use tdd_core::{TestCase, TestOutputFormat, TestStatus};
+use tdd_exec::{parse_test_output, parse_test_output_as};
+
+const LIBTEST: &str = r#"
+running 4 tests
+test tests::adds_two_numbers ... ok
+test tests::fails ... FAILED
+test tests::panics - should panic ... ok
+test tests::slow ... ignored, slow
+
+failures:
+
+---- tests::fails stdout ----
+
+thread 'tests::fails' (3327) panicked at src/lib.rs:9:18:
+assertion `left == right` failed: one plus one
+  left: 2
+ right: 3
+stack backtrace:
+   0: __rustc::rust_begin_unwind
+
+
+failures:
+    tests::fails
+
+test result: FAILED. 2 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out; finished in 0.01s
+"#;
+
+fn find<'a>(tests: &'a [TestCase], name: &str) -> &'a TestCase {
+    tests.iter().find(|test| test.name == name).unwrap()
+}
+
+#[test]
+fn test_parses_libtest_output() {
+    let tests = parse_test_output(LIBTEST, "");
+
+    assert_eq!(tests.len(), 4);
+    let passed = find(&tests, "adds_two_numbers");
+    assert_eq!(passed.module, "tests");
+    assert_eq!(passed.status, TestStatus::Passed);
+    assert_eq!(find(&tests, "panics").status, TestStatus::Passed);
+    assert_eq!(find(&tests, "slow").status, TestStatus::Ignored);
+
+    let failed = find(&tests, "fails");
+    assert_eq!(failed.status, TestStatus::Failed);
+    assert_eq!(failed.location.as_deref(), Some("src/lib.rs:9:18"));
+    assert_eq!(
+        failed.failure.as_deref(),
+        Some("assertion `left == right` failed: one plus one\n  left: 2\n right: 3")
+    );
+}
+
+#[test]
+fn test_parses_libtest_json_and_report_time() {
+    let json = r#"{ "type": "suite", "event": "started", "test_count": 2 }
+{ "type": "test", "event": "started", "name": "tests::adds_two_numbers" }
+{ "type": "test", "name": "tests::adds_two_numbers", "event": "ok", "exec_time": 0.0021 }
+{ "type": "test", "name": "tests::fails", "event": "failed", "exec_time": 0.000071035, "stdout": "\nthread 'tests::fails' (3346) panicked at src/lib.rs:9:18:\nassertion failed: false\nnote: run with `RUST_BACKTRACE=1` environment variable to display a backtrace\n" }
+{ "type": "suite", "event": "failed", "passed": 1, "failed": 1, "ignored": 0, "measured": 0, "filtered_out": 0, "exec_time": 0.000493384 }"#;
+
+    let tests = parse_test_output(json, "");
+
+    assert_eq!(tests.len(), 2);
+    assert_eq!(find(&tests, "adds_two_numbers").duration_ms, Some(2));
+    let failed = find(&tests, "fails");
+    assert_eq!(failed.failure.as_deref(), Some("assertion failed: false"));
+    assert_eq!(failed.location.as_deref(), Some("src/lib.rs:9:18"));
+
+    let timed = parse_test_output("test parser::tests::parses ... ok <0.125s>", "");
+    assert_eq!(timed[0].module, "parser::tests");
+    assert_eq!(timed[0].duration_ms, Some(125));
+}
+
+#[test]
+fn test_parses_nextest_and_old_panic_format() {
+    let stderr = r#"    Starting 2 tests across 1 binary
+        PASS [   0.004s] demo tests::adds_two_numbers
+        FAIL [   0.005s] demo tests::fails
+--- STDERR:              demo tests::fails ---
+thread 'tests::fails' panicked at 'one plus one', src/lib.rs:9:18
+------------
+     Summary [   0.012s] 2 tests run: 1 passed, 1 failed, 0 skipped
+        FAIL [   0.005s] demo tests::fails
+"#;
+
+    let tests = parse_test_output("", stderr);
+
+    assert_eq!(tests.len(), 2);
+    assert_eq!(find(&tests, "adds_two_numbers").duration_ms, Some(4));
+    let failed = find(&tests, "fails");
+    assert_eq!(failed.status, TestStatus::Failed);
+    assert_eq!(failed.failure.as_deref(), Some("one plus one"));
+    assert_eq!(failed.location.as_deref(), Some("src/lib.rs:9:18"));
+}
+
+const PYTEST: &str = r#"
+============================= test session starts ==============================
+collecting ... collected 4 items
+
+tests/test_kata.py::test_adds_two_numbers PASSED                         [ 25%]
+tests/test_kata.py::test_subtracts FAILED                                [ 50%]
+tests/test_kata.py::TestParser::test_parses_empty SKIPPED (not yet)      [ 75%]
+tests/test_kata.py::test_divides[0-1] PASSED                             [100%]
+
+=================================== FAILURES ===================================
+________________________________ test_subtracts ________________________________
+
+    def test_subtracts():
+>       assert subtract(3, 1) == 1
+E       assert 2 == 1
+
+tests/test_kata.py:8: AssertionError
+=========================== short test summary info ============================
+FAILED tests/test_kata.py::test_subtracts - assert 2 == 1
+==================== 1 failed, 2 passed, 1 skipped in 0.02s ====================
+"#;
+
+#[test]
+fn test_parses_pytest_output() {
+    let tests = parse_test_output_as(TestOutputFormat::Pytest, PYTEST, "");
+
+    assert_eq!(tests.len(), 4);
+    assert_eq!(find(&tests, "test_divides[0-1]").status, TestStatus::Passed);
+    let skipped = find(&tests, "test_parses_empty");
+    assert_eq!(skipped.module, "tests/test_kata.py::TestParser");
+    assert_eq!(skipped.status, TestStatus::Ignored);
+    let failed = find(&tests, "test_subtracts");
+    assert_eq!(failed.status, TestStatus::Failed);
+    assert_eq!(failed.failure.as_deref(), Some("assert 2 == 1"));
+    assert_eq!(failed.location.as_deref(), Some("tests/test_kata.py:8"));
+}
+
+const VITEST: &str = r#"
+ RUN  v1.6.0 /kata
+
+ ✓ src/kata.test.ts > add > sums two numbers
+ × src/kata.test.ts > add > handles negatives 3ms
+   → expected 1 to be -1 // Object.is equality
+ ↓ src/kata.test.ts > add > parses strings [skipped]
+
+⎯⎯⎯⎯⎯⎯⎯ Failed Tests 1 ⎯⎯⎯⎯⎯⎯⎯
+
+ FAIL  src/kata.test.ts > add > handles negatives
+AssertionError: expected 1 to be -1 // Object.is equality
+ ❯ src/kata.test.ts:9:25
+      7|   it("handles negatives", () => {
+      8|     expect(add(-2, 1)).toBe(-1);
+"#;
+
+const JEST: &str = r#"
+FAIL src/kata.test.ts
+  add
+    ✓ sums two numbers (2 ms)
+    ✕ handles negatives (3 ms)
+    ○ skipped parses strings
+
+  ● add › handles negatives
+
+    expect(received).toBe(expected) // Object.is equality
+
+    Expected: -1
+    Received: 1
+
+       7 |   it("handles negatives", () => {
+    >  8 |     expect(add(-2, 1)).toBe(-1);
+         |                        ^
+
+      at Object.<anonymous> (src/kata.test.ts:8:24)
+
+Tests:       1 failed, 1 skipped, 1 passed, 3 total
+"#;
+
+#[test]
+fn test_parses_vitest_and_jest_output() {
+    for (output, message, location) in [
+        (
+            VITEST,
+            "AssertionError: expected 1 to be -1 // Object.is equality",
+            "src/kata.test.ts:9:25",
+        ),
+        (
+            JEST,
+            "expect(received).toBe(expected) // Object.is equality\nExpected: -1\nReceived: 1",
+            "src/kata.test.ts:8:24",
+        ),
+    ] {
+        let tests = parse_test_output_as(TestOutputFormat::Jest, output, "");
+
+        assert_eq!(tests.len(), 3, "{}", output);
+        assert_eq!(tests[0].path(), "src/kata.test.ts::add > sums two numbers");
+        assert_eq!(tests[0].status, TestStatus::Passed);
+        assert_eq!(tests[2].name, "add > parses strings");
+        assert_eq!(tests[2].status, TestStatus::Ignored);
+        let failed = &tests[1];
+        assert_eq!(failed.status, TestStatus::Failed);
+        assert_eq!(failed.duration_ms, Some(3));
+        assert_eq!(failed.failure.as_deref(), Some(message));
+        assert_eq!(failed.location.as_deref(), Some(location));
+    }
+}
+
+const GO_TEST: &str = "=== RUN   TestAdd
+--- PASS: TestAdd (0.00s)
+=== RUN   TestSubtract
+    kata_test.go:12: Subtract(3, 1) = 1, want 2
+--- FAIL: TestSubtract (0.01s)
+=== RUN   TestTable
+=== RUN   TestTable/zero
+=== RUN   TestTable/negative
+    kata_test.go:25: got -1, want 1
+--- FAIL: TestTable (0.00s)
+    --- PASS: TestTable/zero (0.00s)
+    --- FAIL: TestTable/negative (0.00s)
+=== RUN   TestSkipped
+    kata_test.go:30: not yet
+--- SKIP: TestSkipped (0.00s)
+FAIL
+FAIL\tkata\t0.002s
+FAIL
+";
+
+#[test]
+fn test_parses_go_test_output() {
+    let tests = parse_test_output_as(TestOutputFormat::GoTest, GO_TEST, "");
+
+    assert_eq!(tests.len(), 6);
+    assert!(tests.iter().all(|test| test.module == "kata"));
+    assert_eq!(find(&tests, "TestSkipped").status, TestStatus::Ignored);
+    let failed = find(&tests, "TestSubtract");
+    assert_eq!(failed.duration_ms, Some(10));
+    assert_eq!(
+        failed.failure.as_deref(),
+        Some("Subtract(3, 1) = 1, want 2")
+    );
+    assert_eq!(failed.location.as_deref(), Some("kata_test.go:12"));
+    let subtest = find(&tests, "TestTable/negative");
+    assert_eq!(subtest.status, TestStatus::Failed);
+    assert_eq!(subtest.failure.as_deref(), Some("got -1, want 1"));
+}
@@ -1,3 +1,7 @@
+pub mod mock_llm;
+
+pub use mock_llm::{Matcher, MockLlmBuilder, MockLlmServer, RecordedRequest, Reply, Rule};
+
 pub const STRING_CALCULATOR_KATA: &str = r#"
 # String Calculator Kata
 
@@ -0,0 +1,418 @@
+//! Scriptable OpenAI-compatible server for end-to-end tests without network.
+//!
+//! Requests are matched against the scripted rules in order; the first rule
+//! that matches answers. Rules marked [`Rule::once`] are used up after one hit.
+
+use regex::Regex;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// What a scripted rule answers with.
+#[derive(Debug, Clone)]
+pub enum Reply {
+    /// An assistant message with this content.
+    Content(String),
+    /// An assistant message asking for tool calls: `(name, arguments JSON)`.
+    ToolCalls(Vec<(String, Value)>),
+    /// An error response in OpenAI's format.
+    Error { status: u16, message: String },
+}
+
+impl Reply {
+    /// Content serialized from a JSON value, e.g. an edit plan.
+    pub fn json(value: Value) -> Self {
+        Reply::Content(value.to_string())
+    }
+
+    /// An edit plan upserting `files` (`(path, content)`).
+    pub fn edit_plan(files: &[(&str, &str)], commit_message: &str) -> Self {
+        let edits: Vec<Value> = files
+            .iter()
+            .map(|(path, content)| json!({"path": path, "action": "upsert", "content": content}))
+            .collect();
+        Self::json(json!({
+            "edits": edits,
+            "commit_message": commit_message,
+            "notes": "scripted",
+        }))
+    }
+}
+
+/// Which requests a rule answers.
+#[derive(Debug, Clone)]
+pub enum Matcher {
+    Any,
+    /// The acting role, e.g. `tester` or `planner`.
+    Role(String),
+    RoleStep(String, u32),
+    /// Any message contains this text.
+    PromptContains(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    matcher: Matcher,
+    reply: Reply,
+    once: bool,
+}
+
+impl Rule {
+    pub fn new(matcher: Matcher, reply: Reply) -> Self {
+        Self {
+            matcher,
+            reply,
+            once: false,
+        }
+    }
+
+    /// Answer a single request, then step aside for later rules.
+    pub fn once(mut self) -> Self {
+        self.once = true;
+        self
+    }
+
+    fn matches(&self, request: &RecordedRequest) -> bool {
+        match &self.matcher {
+            Matcher::Any => true,
+            Matcher::Role(role) => request.role.as_deref() == Some(role),
+            Matcher::RoleStep(role, step) => {
+                request.role.as_deref() == Some(role) && request.step == Some(*step)
+            }
+            Matcher::PromptContains(text) => request.prompt.contains(text),
+        }
+    }
+}
+
+/// A chat request as the server saw it.
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub path: String,
+    pub body: Value,
+    /// From the system prompt ("You are the Tester ..."), or `planner`.
+    pub role: Option<String>,
+    /// From the `Step: N` line of the user prompt.
+    pub step: Option<u32>,
+    /// All message contents, joined.
+    pub prompt: String,
+}
+
+impl RecordedRequest {
+    fn new(path: String, body: Value) -> Self {
+        let messages = body["messages"].as_array().cloned().unwrap_or_default();
+        let text_of = |role: &str| {
+            messages
+                .iter()
+                .filter(|m| m["role"] == role)
+                .filter_map(|m| m["content"].as_str())
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let system = text_of("system");
+        let user = text_of("user");
+
+        let role_re = Regex::new(r"You are the (\w+)").unwrap();
+        let role = match role_re.captures(&system) {
+            Some(captures) => Some(captures[1].to_lowercase()),
+            None if system.contains("You are planning") => Some("planner".to_string()),
+            None => None,
+        };
+        let step_re = Regex::new(r"(?m)^Step: (\d+)").unwrap();
+        let step = step_re
+            .captures(&user)
+            .and_then(|captures| captures[1].parse().ok());
+        let prompt = messages
+            .iter()
+            .filter_map(|m| m["content"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Self {
+            path,
+            body,
+            role,
+            step,
+            prompt,
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    rules: Vec<Rule>,
+    requests: Vec<RecordedRequest>,
+}
+
+/// Builder for a [`MockLlmServer`].
+#[derive(Default)]
+pub struct MockLlmBuilder {
+    rules: Vec<Rule>,
+    models: Vec<String>,
+}
+
+impl MockLlmBuilder {
+    pub fn rule(mut self, rule: Rule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    pub fn role(self, role: &str, reply: Reply) -> Self {
+        self.rule(Rule::new(Matcher::Role(role.to_string()), reply))
+    }
+
+    pub fn role_step(self, role: &str, step: u32, reply: Reply) -> Self {
+        self.rule(Rule::new(Matcher::RoleStep(role.to_string(), step), reply))
+    }
+
+    pub fn prompt_contains(self, text: &str, reply: Reply) -> Self {
+        self.rule(Rule::new(Matcher::PromptContains(text.to_string()), reply))
+    }
+
+    /// Models listed by `GET /v1/models`; defaults to `mock-model`.
+    pub fn models(mut self, models: &[&str]) -> Self {
+        self.models = models.iter().map(|m| m.to_string()).collect();
+        self
+    }
+
+    pub async fn start(self) -> std::io::Result<MockLlmServer> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let state = Arc::new(Mutex::new(State {
+            rules: self.rules,
+            requests: Vec::new(),
+        }));
+        let models = if self.models.is_empty() {
+            vec!["mock-model".to_string()]
+        } else {
+            self.models
+        };
+
+        let server_state = state.clone();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let state = server_state.clone();
+                let models = models.clone();
+                tokio::spawn(async move {
+                    let _ = serve(socket, state, models).await;
+                });
+            }
+        });
+
+        Ok(MockLlmServer { url, state })
+    }
+}
+
+/// A running mock server; it stops when the test's runtime shuts down.
+pub struct MockLlmServer {
+    url: String,
+    state: Arc<Mutex<State>>,
+}
+
+impl MockLlmServer {
+    pub fn builder() -> MockLlmBuilder {
+        MockLlmBuilder::default()
+    }
+
+    /// Base URL to configure as an OpenAI endpoint (ends in `/v1`).
+    pub fn base_url(&self) -> String {
+        format!("{}/v1", self.url)
+    }
+
+    /// Every chat request received so far, in order.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Acting roles of the chat requests received so far.
+    pub fn roles(&self) -> Vec<String> {
+        self.requests()
+            .into_iter()
+            .map(|r| r.role.unwrap_or_default())
+            .collect()
+    }
+}
+
+async fn serve(
+    mut socket: TcpStream,
+    state: Arc<Mutex<State>>,
+    models: Vec<String>,
+) -> std::io::Result<()> {
+    let Some((method, path, body)) = read_request(&mut socket).await? else {
+        return Ok(());
+    };
+
+    let response = match (method.as_str(), path.trim_start_matches("/v1")) {
+        ("GET", "/models") => {
+            let data: Vec<Value> = models
+                .iter()
+                .map(|id| json!({"id": id, "object": "model", "owned_by": "mock"}))
+                .collect();
+            http_response(
+                200,
+                "application/json",
+                &json!({"object": "list", "data": data}).to_string(),
+            )
+        }
+        ("POST", "/chat/completions") => {
+            let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+            let request = RecordedRequest::new(path.clone(), body.clone());
+            let reply = {
+                let mut state = state.lock().unwrap();
+                let index = state.rules.iter().position(|rule| rule.matches(&request));
+                let reply = index.map(|index| {
+                    let reply = state.rules[index].reply.clone();
+                    if state.rules[index].once {
+                        state.rules.remove(index);
+                    }
+                    reply
+                });
+                state.requests.push(request.clone());
+                reply
+            };
+            match reply {
+                Some(reply) => chat_response(&body, reply),
+                None => error_response(
+                    400,
+                    &format!(
+                        "mock: no scripted reply for role {:?}, step {:?}",
+                        request.role, request.step
+                    ),
+                ),
+            }
+        }
+        _ => error_response(404, &format!("mock: no route for {} {}", method, path)),
+    };
+
+    socket.write_all(response.as_bytes()).await?;
+    socket.shutdown().await
+}
+
+fn chat_response(request: &Value, reply: Reply) -> String {
+    let (content, tool_calls) = match reply {
+        Reply::Content(content) => (content, Vec::new()),
+        Reply::ToolCalls(calls) => {
+            let calls = calls
+                .into_iter()
+                .enumerate()
+                .map(|(i, (name, arguments))| {
+                    json!({
+                        "id": format!("call_{}", i),
+                        "type": "function",
+                        "function": {"name": name, "arguments": arguments.to_string()},
+                    })
+                })
+                .collect();
+            (String::new(), calls)
+        }
+        Reply::Error { status, message } => return error_response(status, &message),
+    };
+    let finish_reason = if tool_calls.is_empty() {
+        "stop"
+    } else {
+        "tool_calls"
+    };
+    // Rough token estimate: four characters per token.
+    let usage = json!({
+        "prompt_tokens": request["messages"].to_string().len() / 4,
+        "completion_tokens": content.len() / 4,
+    });
+
+    if request["stream"] == true {
+        let mut delta = json!({"role": "assistant", "content": content});
+        if !tool_calls.is_empty() {
+            let indexed: Vec<Value> = tool_calls
+                .iter()
+                .enumerate()
+                .map(|(i, call)| {
+                    let mut call = call.clone();
+                    call["index"] = i.into();
+                    call
+                })
+                .collect();
+            delta["tool_calls"] = indexed.into();
+        }
+        let events = [
+            json!({"choices": [{"index": 0, "delta": delta}]}),
+            json!({"choices": [{"index": 0, "delta": {}, "finish_reason": finish_reason}]}),
+            json!({"choices": [], "usage": usage}),
+        ];
+        let mut body: String = events
+            .iter()
+            .map(|event| format!("data: {}\n\n", event))
+            .collect();
+        body.push_str("data: [DONE]\n\n");
+        return http_response(200, "text/event-stream", &body);
+    }
+
+    let mut message = json!({"role": "assistant", "content": content});
+    if !tool_calls.is_empty() {
+        message["tool_calls"] = tool_calls.into();
+    }
+    let body = json!({
+        "id": "chatcmpl-mock",
+        "object": "chat.completion",
+        "model": request["model"],
+        "choices": [{"index": 0, "message": message, "finish_reason": finish_reason}],
+        "usage": usage,
+    });
+    http_response(200, "application/json", &body.to_string())
+}
+
+fn error_response(status: u16, message: &str) -> String {
+    let body = json!({"error": {"message": message, "type": "mock_error"}});
+    http_response(status, "application/json", &body.to_string())
+}
+
+fn http_response(status: u16, content_type: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {} Mock\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+/// Read one request: method, path and body.
+async fn read_request(
+    socket: &mut TcpStream,
+) -> std::io::Result<Option<(String, String, Vec<u8>)>> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let header_end = loop {
+        let n = socket.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim().to_lowercase(), v.trim().to_string()))
+        .collect();
+
+    let length: usize = headers
+        .get("content-length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    while buf.len() < header_end + length {
+        let n = socket.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    let end = buf.len().min(header_end + length);
+    Ok(Some((method, path, buf[header_end..end].to_vec())))
+}
@@ -0,0 +1,140 @@
+use anyhow::Result;
+use serde_json::json;
+use std::sync::Arc;
+use tdd_agents::LlmAgent;
+use tdd_core::{Agent, Role, SessionJournal, StepContext};
+use tdd_fixtures::{Matcher, MockLlmServer, Reply, Rule};
+use tdd_llm::{ChatRequest, GenerationParams, LlmClient, Message, SilentObserver};
+use tempfile::TempDir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+fn context(role: Role, step: u32) -> StepContext {
+    StepContext {
+        role,
+        step_index: step,
+        attempt: 1,
+        kata_description: "add numbers".to_string(),
+        git_last_commit_msg: String::new(),
+        git_last_diff: String::new(),
+        repo_snapshot_paths: Vec::new(),
+        next_test: None,
+        journal: SessionJournal::default(),
+        feedback: None,
+    }
+}
+
+#[tokio::test]
+async fn test_rules_match_in_order_and_requests_are_recorded() -> Result<()> {
+    let server = MockLlmServer::builder()
+        .rule(
+            Rule::new(
+                Matcher::PromptContains("first".to_string()),
+                Reply::Content("one".into()),
+            )
+            .once(),
+        )
+        .prompt_contains("first", Reply::Content("again".into()))
+        .rule(Rule::new(Matcher::Any, Reply::Content("fallback".into())))
+        .start()
+        .await?;
+    let client = LlmClient::new(server.base_url(), String::new());
+    let ask = |text: &str| ChatRequest::new("mock-model", vec![Message::user(text)]);
+
+    assert_eq!(client.complete(&ask("first")).await?.message.content, "one");
+    assert_eq!(
+        client.complete(&ask("first")).await?.message.content,
+        "again"
+    );
+    let streamed = client
+        .clone()
+        .with_stream(Arc::new(SilentObserver))
+        .complete(&ask("other"))
+        .await?;
+    assert_eq!(streamed.message.content, "fallback");
+    assert!(streamed.usage.is_some());
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[0].path, "/v1/chat/completions");
+    assert_eq!(requests[2].body["stream"], true);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unscripted_requests_fail_clearly() -> Result<()> {
+    let server = MockLlmServer::builder()
+        .role("tester", Reply::Content("{}".into()))
+        .start()
+        .await?;
+    let client = LlmClient::new(server.base_url(), String::new());
+
+    let error = client
+        .chat(
+            "mock-model",
+            vec![Message::user("hi")],
+            &GenerationParams::default(),
+        )
+        .await
+        .unwrap_err();
+
+    assert!(error.to_string().contains("no scripted reply"), "{}", error);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_llm_agent_plans_and_edits_against_the_mock() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let server = MockLlmServer::builder()
+        .role_step(
+            "tester",
+            1,
+            Reply::edit_plan(&[("src/lib.rs", "// test\n")], "test: add"),
+        )
+        .start()
+        .await?;
+    let agent = LlmAgent::new(
+        Role::Tester,
+        LlmClient::new(server.base_url(), String::new()),
+        "mock-model".to_string(),
+        GenerationParams::default(),
+        temp_dir.path().to_path_buf(),
+    );
+    let ctx = context(Role::Tester, 1);
+
+    let plan = agent.plan(&ctx).await?;
+    let plan_dir = temp_dir.path().join(".tdd/plan");
+    std::fs::create_dir_all(&plan_dir)?;
+    std::fs::write(plan_dir.join("step-1-tester.md"), &plan)?;
+    let result = agent.edit(&ctx).await?;
+
+    assert_eq!(result.commit_message, "test: add");
+    assert_eq!(
+        std::fs::read_to_string(temp_dir.path().join("src/lib.rs"))?,
+        "// test\n"
+    );
+    let request = &server.requests()[0];
+    assert_eq!(request.role.as_deref(), Some("tester"));
+    assert_eq!(request.step, Some(1));
+    assert_eq!(request.body["response_format"]["type"], "json_schema");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_models_endpoint_lists_models() -> Result<()> {
+    let server = MockLlmServer::builder().models(&["a", "b"]).start().await?;
+    let address = server.base_url().replace("http://", "").replace("/v1", "");
+
+    let mut stream = tokio::net::TcpStream::connect(address).await?;
+    stream
+        .write_all(b"GET /v1/models HTTP/1.1\r\nHost: mock\r\n\r\n")
+        .await?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+
+    let body: serde_json::Value = serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap())?;
+    assert_eq!(
+        body["data"][1],
+        json!({"id": "b", "object": "model", "owned_by": "mock"})
+    );
+    Ok(())
+}
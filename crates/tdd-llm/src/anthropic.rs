@@ -0,0 +1,266 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use crate::provider::{
+    error_message, http_client, send_json, LlmError, LlmProvider, ProviderConfig,
+};
+use crate::stream::{event_json, send_stream, StreamObserver};
+use crate::types::{ChatRequest, ChatResponse, FunctionCall, Message, ToolCall, Usage};
+
+pub const ANTHROPIC_VERSION: &str = "2023-06-01";
+/// The Messages API requires `max_tokens`; used when the request sets none.
+const DEFAULT_MAX_TOKENS: u32 = 8192;
+
+/// Anthropic Messages API (`/v1/messages`).
+pub struct AnthropicProvider {
+    client: reqwest::Client,
+    config: ProviderConfig,
+}
+
+impl AnthropicProvider {
+    pub fn new(config: ProviderConfig) -> Self {
+        Self {
+            client: http_client(&config),
+            config,
+        }
+    }
+}
+
+/// Map OpenAI-style messages onto Anthropic's `system` field plus
+/// alternating user/assistant turns made of content blocks.
+fn to_anthropic(messages: &[Message]) -> (String, Vec<Value>) {
+    let mut system = Vec::new();
+    let mut turns: Vec<(String, Vec<Value>)> = Vec::new();
+
+    for message in messages {
+        let (role, blocks) = match message.role.as_str() {
+            "system" => {
+                system.push(message.content.clone());
+                continue;
+            }
+            "tool" => (
+                "user",
+                vec![json!({
+                    "type": "tool_result",
+                    "tool_use_id": message.tool_call_id,
+                    "content": message.content,
+                })],
+            ),
+            "assistant" => {
+                let mut blocks = Vec::new();
+                if !message.content.is_empty() {
+                    blocks.push(json!({"type": "text", "text": message.content}));
+                }
+                for call in &message.tool_calls {
+                    let input: Value =
+                        serde_json::from_str(&call.function.arguments).unwrap_or(json!({}));
+                    blocks.push(json!({
+                        "type": "tool_use",
+                        "id": call.id,
+                        "name": call.function.name,
+                        "input": input,
+                    }));
+                }
+                ("assistant", blocks)
+            }
+            _ => (
+                "user",
+                vec![json!({"type": "text", "text": message.content})],
+            ),
+        };
+
+        // Consecutive messages of the same role (e.g. several tool results) form one turn.
+        match turns.last_mut() {
+            Some((last_role, last_blocks)) if last_role == role => last_blocks.extend(blocks),
+            _ => turns.push((role.to_string(), blocks)),
+        }
+    }
+
+    let turns = turns
+        .into_iter()
+        .map(|(role, content)| json!({"role": role, "content": content}))
+        .collect();
+    (system.join("\n\n"), turns)
+}
+
+/// Anthropic reports cache reads and writes apart from `input_tokens`.
+fn input_usage(usage: &Value) -> Usage {
+    let count = |field: &str| usage[field].as_u64().unwrap_or(0);
+    let cached_tokens = count("cache_read_input_tokens");
+    Usage {
+        prompt_tokens: count("input_tokens") + cached_tokens + count("cache_creation_input_tokens"),
+        completion_tokens: 0,
+        cached_tokens,
+    }
+}
+
+fn from_anthropic(json: &Value) -> Result<ChatResponse, LlmError> {
+    let blocks = json["content"]
+        .as_array()
+        .ok_or_else(|| LlmError::InvalidResponse("No content in LLM response".to_string()))?;
+
+    let mut message = Message::assistant("");
+    for block in blocks {
+        match block["type"].as_str() {
+            Some("text") => message
+                .content
+                .push_str(block["text"].as_str().unwrap_or_default()),
+            Some("tool_use") => message.tool_calls.push(ToolCall {
+                id: block["id"].as_str().unwrap_or_default().to_string(),
+                kind: "function".to_string(),
+                function: FunctionCall {
+                    name: block["name"].as_str().unwrap_or_default().to_string(),
+                    arguments: block["input"].to_string(),
+                },
+            }),
+            _ => {}
+        }
+    }
+
+    let usage = json.get("usage").map(|usage| Usage {
+        completion_tokens: usage["output_tokens"].as_u64().unwrap_or(0),
+        ..input_usage(usage)
+    });
+
+    Ok(ChatResponse {
+        message,
+        usage,
+        finish_reason: json["stop_reason"].as_str().map(str::to_string),
+    })
+}
+
+impl AnthropicProvider {
+    fn prepare(&self, request: &ChatRequest) -> (reqwest::RequestBuilder, Value) {
+        let url = self.config.url(&request.model, "/messages");
+
+        let (system, messages) = to_anthropic(&request.messages);
+        let mut body = json!({
+            "model": request.model,
+            "max_tokens": request.params.output_limit().unwrap_or(DEFAULT_MAX_TOKENS),
+            "messages": messages,
+        });
+        if !system.is_empty() {
+            body["system"] = system.into();
+        }
+        let params = &request.params;
+        if let Some(temperature) = params.temperature {
+            body["temperature"] = temperature.into();
+        }
+        if let Some(top_p) = params.top_p {
+            body["top_p"] = top_p.into();
+        }
+        if !params.stop.is_empty() {
+            body["stop_sequences"] = params.stop.clone().into();
+        }
+        if !request.tools.is_empty() {
+            body["tools"] = request
+                .tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "name": tool.name,
+                        "description": tool.description,
+                        "input_schema": tool.parameters,
+                    })
+                })
+                .collect();
+        }
+
+        let http = self
+            .config
+            .post(&self.client, &url)
+            .header("anthropic-version", ANTHROPIC_VERSION);
+        (http, body)
+    }
+}
+
+#[async_trait]
+impl LlmProvider for AnthropicProvider {
+    fn name(&self) -> &'static str {
+        "anthropic"
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+        let (http, body) = self.prepare(request);
+        let json = send_json(http, &body, self.config.request_timeout).await?;
+
+        from_anthropic(&json)
+    }
+
+    async fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        observer: &dyn StreamObserver,
+    ) -> Result<ChatResponse, LlmError> {
+        let (http, mut body) = self.prepare(request);
+        body["stream"] = true.into();
+
+        let mut response = ChatResponse {
+            message: Message::assistant(""),
+            usage: None,
+            finish_reason: None,
+        };
+        let mut usage = Usage::default();
+        send_stream(http, &body, self.config.idle_timeout, |event| {
+            let json = event_json(&event)?;
+            match json["type"].as_str().unwrap_or(&event.event) {
+                "message_start" => {
+                    usage = input_usage(&json["message"]["usage"]);
+                }
+                "content_block_start" => {
+                    let block = &json["content_block"];
+                    if block["type"] == "tool_use" {
+                        let name = block["name"].as_str().unwrap_or_default();
+                        observer.on_tool_call(name);
+                        response.message.tool_calls.push(ToolCall {
+                            id: block["id"].as_str().unwrap_or_default().to_string(),
+                            kind: "function".to_string(),
+                            function: FunctionCall {
+                                name: name.to_string(),
+                                arguments: String::new(),
+                            },
+                        });
+                    }
+                }
+                "content_block_delta" => {
+                    let delta = &json["delta"];
+                    match delta["type"].as_str() {
+                        Some("text_delta") => {
+                            let text = delta["text"].as_str().unwrap_or_default();
+                            response.message.content.push_str(text);
+                            observer.on_text(text);
+                        }
+                        Some("input_json_delta") => {
+                            if let Some(call) = response.message.tool_calls.last_mut() {
+                                call.function
+                                    .arguments
+                                    .push_str(delta["partial_json"].as_str().unwrap_or_default());
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                "message_delta" => {
+                    if let Some(reason) = json["delta"]["stop_reason"].as_str() {
+                        response.finish_reason = Some(reason.to_string());
+                    }
+                    usage.completion_tokens = json["usage"]["output_tokens"].as_u64().unwrap_or(0);
+                }
+                "message_stop" => return Ok(true),
+                "error" => return Err(LlmError::Stream(error_message(&event.data))),
+                _ => {}
+            }
+            Ok(false)
+        })
+        .await?;
+
+        // A tool call without input streams no JSON fragments at all.
+        for call in &mut response.message.tool_calls {
+            if call.function.arguments.is_empty() {
+                call.function.arguments = "{}".to_string();
+            }
+        }
+        response.usage = Some(usage);
+        Ok(response)
+    }
+}
@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::types::{ChatRequest, ChatResponse};
+
+/// Where cached responses live.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheLocation {
+    /// `.tdd/cache` in the kata directory.
+    #[default]
+    Project,
+    /// `$XDG_CACHE_HOME/tdd-agent` (or `~/.cache/tdd-agent`), shared by all katas.
+    User,
+}
+
+/// Response cache settings; the cache is off unless `enabled`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    pub location: CacheLocation,
+    /// Entries older than this are ignored and removed.
+    pub ttl_secs: u64,
+    /// Oldest entries are evicted once the cache grows beyond this.
+    pub max_size_mb: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            location: CacheLocation::Project,
+            ttl_secs: 7 * 24 * 60 * 60,
+            max_size_mb: 200,
+        }
+    }
+}
+
+impl CacheConfig {
+    pub fn dir(&self, project_dir: &Path) -> PathBuf {
+        match self.location {
+            CacheLocation::Project => project_dir.join(".tdd").join("cache"),
+            CacheLocation::User => std::env::var_os("XDG_CACHE_HOME")
+                .map(PathBuf::from)
+                .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+                .unwrap_or_else(std::env::temp_dir)
+                .join("tdd-agent"),
+        }
+    }
+}
+
+/// Content-addressed store of chat responses, one JSON file per request.
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttl: Duration,
+    max_bytes: u64,
+    /// Ignore existing entries but store fresh responses.
+    refresh: bool,
+}
+
+impl ResponseCache {
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration, max_bytes: u64) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl,
+            max_bytes,
+            refresh: false,
+        }
+    }
+
+    pub fn from_config(config: &CacheConfig, project_dir: &Path) -> Self {
+        Self::new(
+            config.dir(project_dir),
+            Duration::from_secs(config.ttl_secs),
+            config.max_size_mb * 1024 * 1024,
+        )
+    }
+
+    pub fn refresh(mut self, refresh: bool) -> Self {
+        self.refresh = refresh;
+        self
+    }
+
+    /// Hash of everything that determines the answer: provider, model,
+    /// messages, generation parameters, tools and response format.
+    pub fn key(provider: &str, request: &ChatRequest) -> String {
+        let identity = serde_json::json!({
+            "provider": provider,
+            "model": request.model,
+            "messages": request.messages,
+            "params": request.params,
+            "tools": request.tools,
+            "response_format": request.response_format,
+        });
+        format!("{:x}", Sha256::digest(identity.to_string().as_bytes()))
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    pub fn get(&self, key: &str) -> Option<ChatResponse> {
+        if self.refresh {
+            return None;
+        }
+        let path = self.path(key);
+        let age = fs::metadata(&path)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())?;
+        if age > self.ttl {
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+        serde_json::from_str(&fs::read_to_string(&path).ok()?).ok()
+    }
+
+    pub fn put(&self, key: &str, response: &ChatResponse) -> std::io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        // Write then rename so concurrent readers never see a partial entry.
+        let tmp = self.dir.join(format!("{}.tmp", key));
+        fs::write(&tmp, serde_json::to_string(response)?)?;
+        fs::rename(&tmp, self.path(key))?;
+        self.evict()
+    }
+
+    /// Drop expired entries, then the oldest ones until under the size limit.
+    fn evict(&self) -> std::io::Result<()> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let meta = entry.metadata()?;
+            if entry.path().extension().is_some_and(|ext| ext == "json") {
+                entries.push((meta.modified()?, meta.len(), entry.path()));
+            }
+        }
+        entries.sort();
+
+        let now = SystemTime::now();
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        for (modified, len, path) in entries {
+            let expired = now.duration_since(modified).unwrap_or_default() > self.ttl;
+            if !expired && total <= self.max_bytes {
+                break;
+            }
+            fs::remove_file(path)?;
+            total -= len;
+        }
+        Ok(())
+    }
+}
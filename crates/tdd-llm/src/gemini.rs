@@ -0,0 +1,261 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use crate::provider::{
+    error_message, http_client, send_json, LlmError, LlmProvider, ProviderConfig,
+};
+use crate::stream::{event_json, send_stream, StreamObserver};
+use crate::types::{
+    ChatRequest, ChatResponse, FunctionCall, Message, ResponseFormat, StructuredOutput, ToolCall,
+    Usage,
+};
+
+/// Google Gemini `models/{model}:generateContent`.
+pub struct GeminiProvider {
+    client: reqwest::Client,
+    config: ProviderConfig,
+}
+
+impl GeminiProvider {
+    pub fn new(config: ProviderConfig) -> Self {
+        Self {
+            client: http_client(&config),
+            config,
+        }
+    }
+}
+
+/// Map OpenAI-style messages onto `systemInstruction` plus user/model `contents`.
+///
+/// Gemini function calls carry no id, so tool results are matched back to the
+/// function name through the ids we hand out in [`from_gemini`].
+fn to_gemini(messages: &[Message]) -> (Vec<Value>, Vec<Value>) {
+    let mut system = Vec::new();
+    let mut contents: Vec<(String, Vec<Value>)> = Vec::new();
+    let mut call_names = HashMap::new();
+
+    for message in messages {
+        let (role, parts) = match message.role.as_str() {
+            "system" => {
+                system.push(json!({"text": message.content}));
+                continue;
+            }
+            "tool" => {
+                let id = message.tool_call_id.clone().unwrap_or_default();
+                let name = call_names.get(&id).cloned().unwrap_or(id);
+                (
+                    "user",
+                    vec![json!({
+                        "functionResponse": {
+                            "name": name,
+                            "response": {"content": message.content},
+                        }
+                    })],
+                )
+            }
+            "assistant" => {
+                let mut parts = Vec::new();
+                if !message.content.is_empty() {
+                    parts.push(json!({"text": message.content}));
+                }
+                for call in &message.tool_calls {
+                    call_names.insert(call.id.clone(), call.function.name.clone());
+                    let args: Value =
+                        serde_json::from_str(&call.function.arguments).unwrap_or(json!({}));
+                    parts.push(json!({
+                        "functionCall": {"name": call.function.name, "args": args}
+                    }));
+                }
+                ("model", parts)
+            }
+            _ => ("user", vec![json!({"text": message.content})]),
+        };
+
+        match contents.last_mut() {
+            Some((last_role, last_parts)) if last_role == role => last_parts.extend(parts),
+            _ => contents.push((role.to_string(), parts)),
+        }
+    }
+
+    let contents = contents
+        .into_iter()
+        .map(|(role, parts)| json!({"role": role, "parts": parts}))
+        .collect();
+    (system, contents)
+}
+
+fn from_gemini(json: &Value) -> Result<ChatResponse, LlmError> {
+    if json["candidates"][0]["content"]["parts"]
+        .as_array()
+        .is_none()
+    {
+        return Err(LlmError::InvalidResponse(
+            "No content in LLM response".to_string(),
+        ));
+    }
+    let mut response = ChatResponse {
+        message: Message::assistant(""),
+        usage: None,
+        finish_reason: None,
+    };
+    append_gemini(json, &mut response, &|_| {}, &|_| {});
+    Ok(response)
+}
+
+/// Fold one (possibly partial) `GenerateContentResponse` into `response`.
+fn append_gemini(
+    json: &Value,
+    response: &mut ChatResponse,
+    on_text: &dyn Fn(&str),
+    on_tool_call: &dyn Fn(&str),
+) {
+    let candidate = &json["candidates"][0];
+    let message = &mut response.message;
+    for part in candidate["content"]["parts"]
+        .as_array()
+        .into_iter()
+        .flatten()
+    {
+        if let Some(text) = part["text"].as_str() {
+            message.content.push_str(text);
+            on_text(text);
+        } else if let Some(call) = part.get("functionCall") {
+            let name = call["name"].as_str().unwrap_or_default();
+            on_tool_call(name);
+            message.tool_calls.push(ToolCall {
+                id: format!("call_{}", message.tool_calls.len()),
+                kind: "function".to_string(),
+                function: FunctionCall {
+                    name: name.to_string(),
+                    arguments: call["args"].to_string(),
+                },
+            });
+        }
+    }
+
+    if let Some(usage) = json.get("usageMetadata") {
+        response.usage = Some(Usage {
+            prompt_tokens: usage["promptTokenCount"].as_u64().unwrap_or(0),
+            completion_tokens: usage["candidatesTokenCount"].as_u64().unwrap_or(0),
+            cached_tokens: usage["cachedContentTokenCount"].as_u64().unwrap_or(0),
+        });
+    }
+    if let Some(reason) = candidate["finishReason"].as_str() {
+        response.finish_reason = Some(reason.to_string());
+    }
+}
+
+impl GeminiProvider {
+    fn prepare(&self, request: &ChatRequest, method: &str) -> (reqwest::RequestBuilder, Value) {
+        let url = self.config.url(
+            &request.model,
+            &format!("/models/{}:{}", request.model, method),
+        );
+
+        let (system, contents) = to_gemini(&request.messages);
+        let mut body = json!({ "contents": contents });
+        if !system.is_empty() {
+            body["systemInstruction"] = json!({ "parts": system });
+        }
+        let mut generation_config = serde_json::Map::new();
+        let params = &request.params;
+        if let Some(temperature) = params.temperature {
+            generation_config.insert("temperature".to_string(), temperature.into());
+        }
+        if let Some(max_tokens) = params.output_limit() {
+            generation_config.insert("maxOutputTokens".to_string(), max_tokens.into());
+        }
+        if let Some(top_p) = params.top_p {
+            generation_config.insert("topP".to_string(), top_p.into());
+        }
+        if let Some(seed) = params.seed {
+            generation_config.insert("seed".to_string(), seed.into());
+        }
+        if !params.stop.is_empty() {
+            generation_config.insert("stopSequences".to_string(), params.stop.clone().into());
+        }
+        if let Some(format) = &request.response_format {
+            generation_config.insert("responseMimeType".to_string(), "application/json".into());
+            if let ResponseFormat::JsonSchema { schema, .. } = format {
+                generation_config.insert("responseJsonSchema".to_string(), schema.clone());
+            }
+        }
+        if !generation_config.is_empty() {
+            body["generationConfig"] = generation_config.into();
+        }
+        if !request.tools.is_empty() {
+            let declarations: Vec<Value> = request
+                .tools
+                .iter()
+                .map(|tool| {
+                    let mut declaration = json!({
+                        "name": tool.name,
+                        "description": tool.description,
+                    });
+                    // Gemini rejects object schemas without properties.
+                    if tool.parameters["properties"]
+                        .as_object()
+                        .is_some_and(|p| !p.is_empty())
+                    {
+                        declaration["parameters"] = tool.parameters.clone();
+                    }
+                    declaration
+                })
+                .collect();
+            body["tools"] = json!([{ "functionDeclarations": declarations }]);
+        }
+
+        (self.config.post(&self.client, &url), body)
+    }
+}
+
+#[async_trait]
+impl LlmProvider for GeminiProvider {
+    fn name(&self) -> &'static str {
+        "gemini"
+    }
+
+    fn structured_output(&self) -> StructuredOutput {
+        self.config
+            .structured_output
+            .unwrap_or(StructuredOutput::JsonObject)
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+        let (http, body) = self.prepare(request, "generateContent");
+        let json = send_json(http, &body, self.config.request_timeout).await?;
+
+        from_gemini(&json)
+    }
+
+    async fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        observer: &dyn StreamObserver,
+    ) -> Result<ChatResponse, LlmError> {
+        let (http, body) = self.prepare(request, "streamGenerateContent?alt=sse");
+
+        let mut response = ChatResponse {
+            message: Message::assistant(""),
+            usage: None,
+            finish_reason: None,
+        };
+        send_stream(http, &body, self.config.idle_timeout, |event| {
+            let json = event_json(&event)?;
+            if json.get("error").is_some() {
+                return Err(LlmError::Stream(error_message(&event.data)));
+            }
+            append_gemini(
+                &json,
+                &mut response,
+                &|text| observer.on_text(text),
+                &|name| observer.on_tool_call(name),
+            );
+            Ok(false)
+        })
+        .await?;
+
+        Ok(response)
+    }
+}
@@ -1,72 +1,389 @@
 use anyhow::{Context, Result};
-use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use transcript::{Call, TranscriptEndpoint};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Message {
-    pub role: String,
-    pub content: String,
-}
+pub mod anthropic;
+pub mod cache;
+pub mod gemini;
+pub mod limit;
+pub mod model;
+pub mod ollama;
+pub mod openai;
+pub mod provider;
+pub mod retry;
+pub mod stream;
+pub mod transcript;
+pub mod types;
+pub mod usage;
+
+pub use anthropic::AnthropicProvider;
+pub use cache::{CacheConfig, CacheLocation, ResponseCache};
+pub use gemini::GeminiProvider;
+pub use limit::{RateLimiter, RateLimits, RatePermit};
+pub use model::{ModelCapabilities, ModelRef};
+pub use ollama::{OllamaOptions, OllamaProvider};
+pub use openai::OpenAiProvider;
+pub use provider::{ApiKind, Auth, LlmError, LlmProvider, ProviderConfig};
+pub use retry::RetryPolicy;
+pub use stream::{SilentObserver, SseEvent, SseParser, StreamObserver};
+pub use transcript::{TranscriptLevel, TranscriptLog};
+pub use types::{
+    ChatRequest, ChatResponse, FunctionCall, GenerationParams, Message, ReasoningEffort,
+    ResponseFormat, StructuredOutput, ToolCall, ToolDefinition, Usage,
+};
+pub use usage::{ModelPrice, UsageRecord, UsageRecorder};
 
-#[derive(Debug, Clone)]
+/// Cheap-to-clone handle on an [`LlmProvider`].
+#[derive(Clone)]
 pub struct LlmClient {
-    client: reqwest::Client,
-    base_url: String,
-    api_key: String,
+    provider: Arc<dyn LlmProvider>,
+    retry: RetryPolicy,
+    /// When set, responses are streamed and reported here as they arrive.
+    stream: Option<Arc<dyn StreamObserver>>,
+    usage: Option<Arc<UsageRecorder>>,
+    /// Response cache and the provider label its keys are scoped to.
+    cache: Option<(Arc<ResponseCache>, String)>,
+    transcripts: Option<(Arc<TranscriptLog>, Arc<TranscriptEndpoint>)>,
+    /// Best response format the endpoint accepts, lowered when it rejects one.
+    structured_output: Arc<Mutex<StructuredOutput>>,
+    /// Shared by all clients of the same provider.
+    limiter: Option<Arc<RateLimiter>>,
+    /// Configured capabilities by bare model name, replacing the built-in ones.
+    capabilities: Arc<HashMap<String, ModelCapabilities>>,
+}
+
+/// A 400/422 complaining about the response format rather than the prompt.
+fn rejects_format(error: &LlmError) -> bool {
+    let LlmError::Api {
+        status: 400 | 422,
+        message,
+        ..
+    } = error
+    else {
+        return false;
+    };
+    let message = message.to_lowercase();
+    [
+        "response_format",
+        "json_schema",
+        "json_object",
+        "responsemimetype",
+        "response_mime_type",
+        "responsejsonschema",
+    ]
+    .iter()
+    .any(|needle| message.contains(needle))
+}
+
+/// Drop the `<think>…</think>` reasoning that models such as deepseek-r1 put
+/// before their answer. Some chat templates omit the opening tag, and a
+/// truncated answer may lack the closing one.
+fn strip_think_blocks(content: &str) -> String {
+    const OPEN: &str = "<think>";
+    const CLOSE: &str = "</think>";
+    let mut rest = content;
+    if let Some(end) = rest.find(CLOSE) {
+        if !rest[..end].contains(OPEN) {
+            rest = &rest[end + CLOSE.len()..];
+        }
+    }
+    let mut out = String::new();
+    while let Some(start) = rest.find(OPEN) {
+        out.push_str(&rest[..start]);
+        rest = match rest[start..].find(CLOSE) {
+            Some(end) => &rest[start + end + CLOSE.len()..],
+            None => "",
+        };
+    }
+    out.push_str(rest);
+    out.trim_start().to_string()
+}
+
+impl std::fmt::Debug for LlmClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LlmClient")
+            .field("provider", &self.provider.name())
+            .field("stream", &self.stream.is_some())
+            .finish()
+    }
 }
 
 impl LlmClient {
+    /// Client for an OpenAI-compatible `/chat/completions` endpoint.
     pub fn new(base_url: String, api_key: String) -> Self {
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(120))
-            .build()
-            .unwrap_or_default();
+        Self::with_provider(ApiKind::OpenAi.build(base_url, api_key))
+    }
+
+    pub fn with_provider(provider: Arc<dyn LlmProvider>) -> Self {
         Self {
-            client,
-            base_url: base_url.trim_end_matches('/').to_string(),
-            api_key,
+            structured_output: Arc::new(Mutex::new(provider.structured_output())),
+            provider,
+            retry: RetryPolicy::default(),
+            stream: None,
+            usage: None,
+            cache: None,
+            transcripts: None,
+            limiter: None,
+            capabilities: Arc::default(),
+        }
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Stream responses, reporting them to `observer`. A stalled stream is cut
+    /// off after the provider's idle timeout instead of a fixed total timeout.
+    pub fn with_stream(mut self, observer: Arc<dyn StreamObserver>) -> Self {
+        self.stream = Some(observer);
+        self
+    }
+
+    /// Report the token usage of every successful call to `recorder`.
+    pub fn with_usage(mut self, recorder: Arc<UsageRecorder>) -> Self {
+        self.usage = Some(recorder);
+        self
+    }
+
+    /// Answer repeated requests from `cache`. `provider` distinguishes endpoints
+    /// that serve models of the same name.
+    pub fn with_cache(mut self, cache: Arc<ResponseCache>, provider: impl Into<String>) -> Self {
+        self.cache = Some((cache, provider.into()));
+        self
+    }
+
+    /// Queue requests so they stay within the limiter's rate limits.
+    pub fn with_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Describe models the built-in table does not know, or knows wrongly.
+    pub fn with_capabilities(
+        mut self,
+        capabilities: Arc<HashMap<String, ModelCapabilities>>,
+    ) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Write a transcript of every call to `log`. `config` is the endpoint the
+    /// provider was built from; its credentials are redacted.
+    pub fn with_transcripts(
+        mut self,
+        log: Arc<TranscriptLog>,
+        provider: impl Into<String>,
+        config: &ProviderConfig,
+    ) -> Self {
+        let endpoint = TranscriptEndpoint::new(provider.into(), config);
+        self.transcripts = Some((log, Arc::new(endpoint)));
+        self
+    }
+
+    pub fn provider_name(&self) -> &'static str {
+        self.provider.name()
+    }
+
+    pub fn capabilities(&self, model: &str) -> ModelCapabilities {
+        self.capabilities
+            .get(model)
+            .cloned()
+            .unwrap_or_else(|| ModelCapabilities::builtin(model))
+    }
+
+    /// Fit the request's parameters to what its model accepts.
+    fn adapt(&self, request: &ChatRequest) -> ChatRequest {
+        let capabilities = self.capabilities(&request.model);
+        let mut request = request.clone();
+        capabilities.apply(&mut request.params);
+        if let Some(window) = capabilities.context_window {
+            let prompt = limit::estimate_tokens(&request);
+            if prompt > u64::from(window) {
+                println!(
+                    "Warning: prompt of about {} tokens exceeds the {} token context window of {}",
+                    prompt, window, request.model
+                );
+            }
         }
+        request
+    }
+
+    /// Send `request`, with its parameters fitted to the model.
+    pub async fn complete(&self, request: &ChatRequest) -> Result<ChatResponse> {
+        let request = &self.adapt(request);
+        let cache_key = self
+            .cache
+            .as_ref()
+            .map(|(cache, provider)| (cache, ResponseCache::key(provider, request)));
+        if let Some((cache, key)) = &cache_key {
+            if let Some(response) = cache.get(key) {
+                println!(
+                    "LLM response served from cache ({}, {})",
+                    request.model,
+                    &key[..12]
+                );
+                if let Some(recorder) = &self.usage {
+                    recorder.record_cache_hit(self.provider.name(), &request.model);
+                }
+                self.log_call(request, Ok(&response), Duration::ZERO, Duration::ZERO, true);
+                return Ok(response);
+            }
+        }
+
+        let started = Instant::now();
+        let queued = Mutex::new(Duration::ZERO);
+        let result = self.send_structured(request, &queued).await;
+        let queued = queued.into_inner().unwrap();
+        self.log_call(request, result.as_ref(), started.elapsed(), queued, false);
+        let mut response = result?;
+        if response.message.content.contains("think>") {
+            response.message.content = strip_think_blocks(&response.message.content);
+        }
+        if let (Some(recorder), Some(usage)) = (&self.usage, response.usage) {
+            recorder.record(self.provider.name(), &request.model, usage);
+        }
+        if let Some((cache, key)) = &cache_key {
+            if let Err(error) = cache.put(key, &response) {
+                println!("Failed to cache LLM response: {}", error);
+            }
+        }
+        Ok(response)
+    }
+
+    fn log_call(
+        &self,
+        request: &ChatRequest,
+        result: Result<&ChatResponse, &LlmError>,
+        latency: Duration,
+        queued: Duration,
+        cache_hit: bool,
+    ) {
+        if let Some((log, endpoint)) = &self.transcripts {
+            let call = Call {
+                request,
+                result,
+                latency,
+                queued,
+                cache_hit,
+            };
+            log.write(endpoint, call);
+        }
+    }
+
+    /// Send with the best response format the endpoint is known to accept,
+    /// stepping down (schema → object → none) when it rejects one.
+    async fn send_structured(
+        &self,
+        request: &ChatRequest,
+        queued: &Mutex<Duration>,
+    ) -> Result<ChatResponse, LlmError> {
+        let Some(format) = &request.response_format else {
+            return self.send(request, queued).await;
+        };
+        loop {
+            let level = *self.structured_output.lock().unwrap();
+            let mut effective = request.clone();
+            effective.response_format = level.apply(format);
+            match self.send(&effective, queued).await {
+                Err(error) if effective.response_format.is_some() && rejects_format(&error) => {
+                    let fallback = StructuredOutput::of(format).min(level).downgrade();
+                    println!(
+                        "{} rejected the response format ({}); falling back to {:?}",
+                        self.provider.name(),
+                        error,
+                        fallback
+                    );
+                    *self.structured_output.lock().unwrap() = fallback;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Send with retries, queueing every attempt behind the rate limiter and
+    /// adding the time spent waiting to `queued`.
+    async fn send(
+        &self,
+        request: &ChatRequest,
+        queued: &Mutex<Duration>,
+    ) -> Result<ChatResponse, LlmError> {
+        let provider = self.provider.as_ref();
+        let observer = self.stream.as_deref();
+        let limiter = self.limiter.as_deref();
+        self.retry
+            .run(provider.name(), || async move {
+                let permit = match limiter {
+                    Some(limiter) => Some(limiter.acquire(limit::estimate_tokens(request)).await),
+                    None => None,
+                };
+                let response = match observer {
+                    Some(observer) => {
+                        let response = provider.chat_stream(request, observer).await;
+                        observer.on_finish();
+                        response
+                    }
+                    None => provider.chat(request).await,
+                };
+                if let Some(permit) = permit {
+                    *queued.lock().unwrap() += permit.waited();
+                    permit.settle(response.as_ref().ok().and_then(|r| r.usage));
+                }
+                response
+            })
+            .await
+    }
+
+    /// Ask for JSON in the given format; endpoints without support fall back
+    /// to prompt-only JSON.
+    pub async fn chat_json(
+        &self,
+        model: &str,
+        messages: Vec<Message>,
+        params: &GenerationParams,
+        format: ResponseFormat,
+    ) -> Result<String> {
+        let request = ChatRequest::new(model, messages)
+            .params(params.clone())
+            .response_format(format);
+        self.content(&request).await
     }
 
     pub async fn chat(
         &self,
         model: &str,
         messages: Vec<Message>,
-        temperature: f32,
+        params: &GenerationParams,
     ) -> Result<String> {
-        let url = format!("{}/chat/completions", self.base_url);
-
-        let body = serde_json::json!({
-            "model": model,
-            "messages": messages,
-            "temperature": temperature,
-        });
-
-        let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&body)
-            .send()
-            .await
-            .context("Failed to send LLM request")?;
+        let request = ChatRequest::new(model, messages).params(params.clone());
+        self.content(&request).await
+    }
 
-        if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("LLM API error: {}", error_text);
+    async fn content(&self, request: &ChatRequest) -> Result<String> {
+        let response = self.complete(request).await?;
+        if response.message.content.is_empty() {
+            return Err(LlmError::InvalidResponse(
+                "No content in LLM response".to_string(),
+            ))
+            .context(format!("provider: {}", self.provider.name()));
         }
+        Ok(response.message.content)
+    }
 
-        let json: serde_json::Value = response
-            .json()
-            .await
-            .context("Failed to parse LLM response")?;
-
-        let content = json["choices"][0]["message"]["content"]
-            .as_str()
-            .context("No content in LLM response")?
-            .to_string();
-
-        Ok(content)
+    /// Chat with function calling enabled; the returned assistant message either
+    /// carries `tool_calls` to execute or the final `content`.
+    pub async fn chat_with_tools(
+        &self,
+        model: &str,
+        messages: &[Message],
+        params: &GenerationParams,
+        tools: &[ToolDefinition],
+    ) -> Result<Message> {
+        let request = ChatRequest::new(model, messages.to_vec())
+            .params(params.clone())
+            .tools(tools.to_vec());
+        Ok(self.complete(&request).await?.message)
     }
 }
@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::time::Instant;
+
+use crate::types::{ChatRequest, Usage};
+
+/// Requests and tokens are counted over a sliding minute.
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Per-provider request limits; unset limits are not enforced.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimits {
+    pub requests_per_minute: Option<u32>,
+    /// Prompt plus completion tokens. Requests are charged an estimate up
+    /// front, corrected once the provider reports the actual usage.
+    pub tokens_per_minute: Option<u64>,
+    pub max_in_flight: Option<usize>,
+}
+
+impl RateLimits {
+    pub fn is_unlimited(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Queues requests to one provider so they stay within its [`RateLimits`].
+/// Shared by every client of that provider.
+#[derive(Debug)]
+pub struct RateLimiter {
+    name: String,
+    limits: RateLimits,
+    in_flight: Option<Semaphore>,
+    window: Mutex<Window>,
+}
+
+#[derive(Debug, Default)]
+struct Window {
+    next_id: u64,
+    /// Requests started within the last [`WINDOW`], oldest first.
+    entries: VecDeque<Entry>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    id: u64,
+    at: Instant,
+    tokens: u64,
+}
+
+impl Window {
+    fn prune(&mut self, now: Instant) {
+        while self
+            .entries
+            .front()
+            .is_some_and(|entry| now.duration_since(entry.at) >= WINDOW)
+        {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Time until the oldest request leaves the window.
+    fn until_oldest_expires(&self, now: Instant) -> Duration {
+        self.entries
+            .front()
+            .map(|entry| (entry.at + WINDOW).saturating_duration_since(now))
+            .unwrap_or_default()
+    }
+}
+
+impl RateLimiter {
+    pub fn new(name: impl Into<String>, limits: RateLimits) -> Self {
+        Self {
+            name: name.into(),
+            in_flight: limits.max_in_flight.map(|max| Semaphore::new(max.max(1))),
+            limits,
+            window: Mutex::default(),
+        }
+    }
+
+    /// Wait until a request of about `tokens` tokens may be sent. The slot is
+    /// held until the permit is dropped.
+    pub async fn acquire(&self, tokens: u64) -> RatePermit<'_> {
+        let started = Instant::now();
+        let mut announced = false;
+        let slot = match &self.in_flight {
+            Some(semaphore) => Some(match semaphore.try_acquire() {
+                Ok(slot) => slot,
+                Err(_) => {
+                    println!(
+                        "{}: {} requests in flight, queueing",
+                        self.name,
+                        self.limits.max_in_flight.unwrap_or_default()
+                    );
+                    announced = true;
+                    semaphore
+                        .acquire()
+                        .await
+                        .expect("limiter semaphore is never closed")
+                }
+            }),
+            None => None,
+        };
+
+        let mut waiting_on_window = false;
+        let id = loop {
+            let (wait, reason) = {
+                let mut window = self.window.lock().unwrap();
+                let now = Instant::now();
+                window.prune(now);
+                match self.blocked_by(&window, tokens) {
+                    Some(reason) => (window.until_oldest_expires(now), reason),
+                    None => {
+                        let id = window.next_id;
+                        window.next_id += 1;
+                        window.entries.push_back(Entry {
+                            id,
+                            at: now,
+                            tokens,
+                        });
+                        break id;
+                    }
+                }
+            };
+            if !waiting_on_window {
+                println!(
+                    "{}: {} limit reached, waiting {:.1}s",
+                    self.name,
+                    reason,
+                    wait.as_secs_f64()
+                );
+                waiting_on_window = true;
+            }
+            tokio::time::sleep(wait).await;
+        };
+
+        let waited = started.elapsed();
+        if announced || waiting_on_window {
+            println!(
+                "{}: sent after {:.1}s in queue",
+                self.name,
+                waited.as_secs_f64()
+            );
+        }
+        RatePermit {
+            limiter: self,
+            id,
+            waited,
+            _slot: slot,
+        }
+    }
+
+    fn blocked_by(&self, window: &Window, tokens: u64) -> Option<&'static str> {
+        if let Some(rpm) = self.limits.requests_per_minute {
+            if window.entries.len() >= rpm.max(1) as usize {
+                return Some("requests per minute");
+            }
+        }
+        if let Some(tpm) = self.limits.tokens_per_minute {
+            let used: u64 = window.entries.iter().map(|entry| entry.tokens).sum();
+            // A request larger than the whole budget still goes once the window is empty.
+            if !window.entries.is_empty() && used + tokens > tpm {
+                return Some("tokens per minute");
+            }
+        }
+        None
+    }
+}
+
+/// A granted request; dropping it frees its in-flight slot.
+#[derive(Debug)]
+pub struct RatePermit<'a> {
+    limiter: &'a RateLimiter,
+    id: u64,
+    waited: Duration,
+    _slot: Option<SemaphorePermit<'a>>,
+}
+
+impl RatePermit<'_> {
+    /// Time spent queueing for this permit.
+    pub fn waited(&self) -> Duration {
+        self.waited
+    }
+
+    /// Replace the token estimate with the usage the provider reported.
+    pub fn settle(self, usage: Option<Usage>) {
+        let Some(usage) = usage else {
+            return;
+        };
+        let mut window = self.limiter.window.lock().unwrap();
+        if let Some(entry) = window.entries.iter_mut().find(|entry| entry.id == self.id) {
+            entry.tokens = usage.prompt_tokens + usage.completion_tokens;
+        }
+    }
+}
+
+/// Rough prompt size in tokens (about four bytes each) for the limiter.
+pub(crate) fn estimate_tokens(request: &ChatRequest) -> u64 {
+    let messages = serde_json::to_string(&request.messages).map_or(0, |s| s.len());
+    let tools = serde_json::to_string(&request.tools).map_or(0, |s| s.len());
+    ((messages + tools) / 4) as u64 + 1
+}
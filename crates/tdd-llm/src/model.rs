@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+
+use crate::types::GenerationParams;
+
+/// A role's model string, `provider:model` or a bare `model`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelRef {
+    pub provider: Option<String>,
+    pub model: String,
+}
+
+impl ModelRef {
+    /// Split off a provider prefix, but only when `is_provider` recognises it:
+    /// model names such as `llama3:8b` contain colons of their own.
+    pub fn parse(spec: &str, is_provider: impl Fn(&str) -> bool) -> Self {
+        match spec.split_once(':') {
+            Some((provider, model)) if is_provider(provider) => Self {
+                provider: Some(provider.to_string()),
+                model: model.to_string(),
+            },
+            _ => Self {
+                provider: None,
+                model: spec.to_string(),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for ModelRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.provider {
+            Some(provider) => write!(f, "{}:{}", provider, self.model),
+            None => write!(f, "{}", self.model),
+        }
+    }
+}
+
+/// What a model accepts and how much it can take, used to shape requests.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelCapabilities {
+    /// Accepts `temperature` and `top_p`; reasoning models reject them.
+    pub sampling: bool,
+    /// A reasoning model: takes `reasoning_effort`, and its length limit as
+    /// `max_completion_tokens`.
+    pub reasoning: bool,
+    pub seed: bool,
+    pub stop: bool,
+    /// Prompt plus answer, in tokens.
+    pub context_window: Option<u32>,
+    pub max_output_tokens: Option<u32>,
+}
+
+impl Default for ModelCapabilities {
+    fn default() -> Self {
+        Self {
+            sampling: true,
+            reasoning: false,
+            seed: true,
+            stop: true,
+            context_window: None,
+            max_output_tokens: None,
+        }
+    }
+}
+
+impl ModelCapabilities {
+    fn reasoning(context_window: u32, max_output_tokens: u32) -> Self {
+        Self {
+            sampling: false,
+            reasoning: true,
+            stop: false,
+            context_window: Some(context_window),
+            max_output_tokens: Some(max_output_tokens),
+            ..Self::default()
+        }
+    }
+
+    fn limits(context_window: u32, max_output_tokens: u32) -> Self {
+        Self {
+            context_window: Some(context_window),
+            max_output_tokens: Some(max_output_tokens),
+            ..Self::default()
+        }
+    }
+
+    /// Known models, matched by name prefix; anything else accepts every
+    /// parameter and has no known limits.
+    pub fn builtin(model: &str) -> Self {
+        // Gateways often prefix the vendor, e.g. `openai/o3-mini`.
+        let model = model.rsplit('/').next().unwrap_or(model);
+        let is = |prefix: &str| model.starts_with(prefix);
+        if is("gpt-5-chat") {
+            Self::limits(128_000, 16_384)
+        } else if is("gpt-5") {
+            Self::reasoning(400_000, 128_000)
+        } else if is("o1") || is("o3") || is("o4") {
+            Self::reasoning(200_000, 100_000)
+        } else if is("gpt-4.1") {
+            Self::limits(1_047_576, 32_768)
+        } else if is("gpt-4o") {
+            Self::limits(128_000, 16_384)
+        } else if is("claude") {
+            let max_output_tokens = if is("claude-opus-4") {
+                32_000
+            } else if is("claude-sonnet-4") || is("claude-3-7") {
+                64_000
+            } else {
+                8_192
+            };
+            Self {
+                seed: false,
+                ..Self::limits(200_000, max_output_tokens)
+            }
+        } else if is("gemini-2") {
+            Self::limits(1_048_576, 65_536)
+        } else {
+            Self::default()
+        }
+    }
+
+    /// Drop the parameters the model would reject, move the length limit to
+    /// the field it expects and cap it at the model's maximum.
+    pub fn apply(&self, params: &mut GenerationParams) {
+        if !self.sampling {
+            params.temperature = None;
+            params.top_p = None;
+        }
+        if !self.seed {
+            params.seed = None;
+        }
+        if !self.stop {
+            params.stop.clear();
+        }
+        if !self.reasoning {
+            params.reasoning_effort = None;
+        }
+        let limit = params
+            .output_limit()
+            .map(|limit| match self.max_output_tokens {
+                Some(max) => limit.min(max),
+                None => limit,
+            });
+        if self.reasoning {
+            params.max_tokens = None;
+            params.max_completion_tokens = limit;
+        } else {
+            params.max_tokens = limit;
+            params.max_completion_tokens = None;
+        }
+    }
+}
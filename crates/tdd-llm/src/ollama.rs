@@ -0,0 +1,261 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::provider::{
+    check_status, error_message, http_client, send_json, LlmError, LlmProvider, ProviderConfig,
+};
+use crate::stream::{send_ndjson, StreamObserver};
+use crate::types::{
+    ChatRequest, ChatResponse, FunctionCall, Message, ResponseFormat, StructuredOutput, ToolCall,
+    Usage,
+};
+
+/// Ollama-specific request settings.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OllamaOptions {
+    /// Context window in tokens; Ollama's default (2048 on older versions)
+    /// silently truncates long prompts.
+    pub num_ctx: Option<u32>,
+    /// How long the model stays loaded after a request, e.g. `10m` or `-1`.
+    pub keep_alive: Option<String>,
+}
+
+/// Ollama's native `/api/chat`.
+pub struct OllamaProvider {
+    client: reqwest::Client,
+    config: ProviderConfig,
+}
+
+impl OllamaProvider {
+    pub fn new(config: ProviderConfig) -> Self {
+        Self {
+            client: http_client(&config),
+            config,
+        }
+    }
+
+    /// Names of the locally available models (`/api/tags`), e.g. `llama3:8b`.
+    pub async fn list_models(&self) -> Result<Vec<String>, LlmError> {
+        let url = self.config.url("", "/api/tags");
+        let response = self
+            .config
+            .get(&self.client, &url)
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .map_err(LlmError::Transport)?;
+        let json: Value = check_status(response)
+            .await?
+            .json()
+            .await
+            .map_err(|e| LlmError::InvalidResponse(e.to_string()))?;
+        Ok(json["models"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|model| model["name"].as_str().map(str::to_string))
+            .collect())
+    }
+}
+
+/// Ollama wants tool arguments as objects and names tool results by function
+/// rather than call id.
+fn to_ollama(messages: &[Message]) -> Vec<Value> {
+    let mut call_names = HashMap::new();
+    messages
+        .iter()
+        .map(|message| {
+            let mut out = json!({"role": message.role, "content": message.content});
+            if !message.tool_calls.is_empty() {
+                out["tool_calls"] = message
+                    .tool_calls
+                    .iter()
+                    .map(|call| {
+                        call_names.insert(call.id.clone(), call.function.name.clone());
+                        let arguments: Value =
+                            serde_json::from_str(&call.function.arguments).unwrap_or(json!({}));
+                        json!({"function": {"name": call.function.name, "arguments": arguments}})
+                    })
+                    .collect();
+            }
+            if let Some(name) = message
+                .tool_call_id
+                .as_ref()
+                .and_then(|id| call_names.get(id))
+            {
+                out["tool_name"] = name.clone().into();
+            }
+            out
+        })
+        .collect()
+}
+
+/// Fold one response object (or streamed line) into `response`.
+fn append_ollama(
+    json: &Value,
+    response: &mut ChatResponse,
+    on_text: &dyn Fn(&str),
+    on_tool_call: &dyn Fn(&str),
+) {
+    let message = &mut response.message;
+    if let Some(text) = json["message"]["content"].as_str() {
+        message.content.push_str(text);
+        on_text(text);
+    }
+    // Calls carry no id; hand out our own so tool results can refer to them.
+    for call in json["message"]["tool_calls"]
+        .as_array()
+        .into_iter()
+        .flatten()
+    {
+        let name = call["function"]["name"].as_str().unwrap_or_default();
+        on_tool_call(name);
+        message.tool_calls.push(ToolCall {
+            id: format!("call_{}", message.tool_calls.len()),
+            kind: "function".to_string(),
+            function: FunctionCall {
+                name: name.to_string(),
+                arguments: call["function"]["arguments"].to_string(),
+            },
+        });
+    }
+
+    if json["done"].as_bool() == Some(true) {
+        response.usage = Some(Usage {
+            prompt_tokens: json["prompt_eval_count"].as_u64().unwrap_or(0),
+            completion_tokens: json["eval_count"].as_u64().unwrap_or(0),
+            cached_tokens: 0,
+        });
+        response.finish_reason = json["done_reason"].as_str().map(str::to_string);
+    }
+}
+
+impl OllamaProvider {
+    fn prepare(&self, request: &ChatRequest, stream: bool) -> (reqwest::RequestBuilder, Value) {
+        let url = self.config.url(&request.model, "/api/chat");
+
+        let mut body = json!({
+            "model": request.model,
+            "messages": to_ollama(&request.messages),
+            "stream": stream,
+        });
+        let params = &request.params;
+        let mut options = serde_json::Map::new();
+        if let Some(temperature) = params.temperature {
+            options.insert("temperature".to_string(), temperature.into());
+        }
+        if let Some(max_tokens) = params.output_limit() {
+            options.insert("num_predict".to_string(), max_tokens.into());
+        }
+        if let Some(top_p) = params.top_p {
+            options.insert("top_p".to_string(), top_p.into());
+        }
+        if let Some(seed) = params.seed {
+            options.insert("seed".to_string(), seed.into());
+        }
+        if !params.stop.is_empty() {
+            options.insert("stop".to_string(), params.stop.clone().into());
+        }
+        if let Some(num_ctx) = self.config.ollama.num_ctx {
+            options.insert("num_ctx".to_string(), num_ctx.into());
+        }
+        if !options.is_empty() {
+            body["options"] = options.into();
+        }
+        if let Some(keep_alive) = &self.config.ollama.keep_alive {
+            // Ollama takes a number of seconds or a duration string.
+            body["keep_alive"] = keep_alive
+                .parse::<i64>()
+                .map(Value::from)
+                .unwrap_or_else(|_| keep_alive.clone().into());
+        }
+        match &request.response_format {
+            Some(ResponseFormat::JsonObject) => body["format"] = "json".into(),
+            Some(ResponseFormat::JsonSchema { schema, .. }) => body["format"] = schema.clone(),
+            None => {}
+        }
+        if !request.tools.is_empty() {
+            body["tools"] = request
+                .tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "type": "function",
+                        "function": {
+                            "name": tool.name,
+                            "description": tool.description,
+                            "parameters": tool.parameters,
+                        }
+                    })
+                })
+                .collect();
+        }
+
+        (self.config.post(&self.client, &url), body)
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OllamaProvider {
+    fn name(&self) -> &'static str {
+        "ollama"
+    }
+
+    /// `format: "json"` works on every Ollama version; schemas need 0.5+.
+    fn structured_output(&self) -> StructuredOutput {
+        self.config
+            .structured_output
+            .unwrap_or(StructuredOutput::JsonObject)
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+        let (http, body) = self.prepare(request, false);
+        let json = send_json(http, &body, self.config.request_timeout).await?;
+        if json["message"].is_null() {
+            return Err(LlmError::InvalidResponse(
+                "No message in LLM response".to_string(),
+            ));
+        }
+
+        let mut response = ChatResponse {
+            message: Message::assistant(""),
+            usage: None,
+            finish_reason: None,
+        };
+        append_ollama(&json, &mut response, &|_| {}, &|_| {});
+        Ok(response)
+    }
+
+    async fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        observer: &dyn StreamObserver,
+    ) -> Result<ChatResponse, LlmError> {
+        let (http, body) = self.prepare(request, true);
+
+        let mut response = ChatResponse {
+            message: Message::assistant(""),
+            usage: None,
+            finish_reason: None,
+        };
+        send_ndjson(http, &body, self.config.idle_timeout, |json| {
+            if json.get("error").is_some() {
+                return Err(LlmError::Stream(error_message(&json.to_string())));
+            }
+            append_ollama(
+                &json,
+                &mut response,
+                &|text| observer.on_text(text),
+                &|name| observer.on_tool_call(name),
+            );
+            Ok(json["done"].as_bool() == Some(true))
+        })
+        .await?;
+
+        Ok(response)
+    }
+}
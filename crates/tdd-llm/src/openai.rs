@@ -0,0 +1,192 @@
+use async_trait::async_trait;
+
+use crate::provider::{
+    error_message, http_client, send_json, LlmError, LlmProvider, ProviderConfig,
+};
+use crate::stream::{event_json, send_stream, StreamObserver};
+use crate::types::{
+    ChatRequest, ChatResponse, FunctionCall, Message, ResponseFormat, StructuredOutput, ToolCall,
+    ToolDefinition, Usage,
+};
+
+/// OpenAI `/chat/completions` and compatible endpoints.
+pub struct OpenAiProvider {
+    client: reqwest::Client,
+    config: ProviderConfig,
+}
+
+impl OpenAiProvider {
+    pub fn new(config: ProviderConfig) -> Self {
+        Self {
+            client: http_client(&config),
+            config,
+        }
+    }
+}
+
+fn tool_to_openai(tool: &ToolDefinition) -> serde_json::Value {
+    serde_json::json!({
+        "type": "function",
+        "function": {
+            "name": tool.name,
+            "description": tool.description,
+            "parameters": tool.parameters,
+        }
+    })
+}
+
+impl OpenAiProvider {
+    fn prepare(&self, request: &ChatRequest) -> (reqwest::RequestBuilder, serde_json::Value) {
+        let url = self.config.url(&request.model, "/chat/completions");
+
+        let mut body = serde_json::json!({
+            "model": request.model,
+            "messages": request.messages,
+        });
+        // The parameters carry OpenAI's own names.
+        if let serde_json::Value::Object(params) = serde_json::json!(request.params) {
+            for (name, value) in params {
+                body[name] = value;
+            }
+        }
+        if !request.tools.is_empty() {
+            body["tools"] = request.tools.iter().map(tool_to_openai).collect();
+            body["tool_choice"] = "auto".into();
+        }
+        match &request.response_format {
+            Some(ResponseFormat::JsonObject) => {
+                body["response_format"] = serde_json::json!({"type": "json_object"});
+            }
+            Some(ResponseFormat::JsonSchema {
+                name,
+                schema,
+                strict,
+            }) => {
+                body["response_format"] = serde_json::json!({
+                    "type": "json_schema",
+                    "json_schema": {"name": name, "schema": schema, "strict": strict},
+                });
+            }
+            None => {}
+        }
+
+        (self.config.post(&self.client, &url), body)
+    }
+}
+
+fn usage_from_openai(usage: &serde_json::Value) -> Usage {
+    Usage {
+        prompt_tokens: usage["prompt_tokens"].as_u64().unwrap_or(0),
+        completion_tokens: usage["completion_tokens"].as_u64().unwrap_or(0),
+        cached_tokens: usage["prompt_tokens_details"]["cached_tokens"]
+            .as_u64()
+            .unwrap_or(0),
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiProvider {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    fn structured_output(&self) -> StructuredOutput {
+        self.config
+            .structured_output
+            .unwrap_or(StructuredOutput::JsonSchema)
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+        let (http, body) = self.prepare(request);
+        let json = send_json(http, &body, self.config.request_timeout).await?;
+
+        let choice = &json["choices"][0];
+        let message: Message = serde_json::from_value(choice["message"].clone())
+            .map_err(|_| LlmError::InvalidResponse("No message in LLM response".to_string()))?;
+        let usage = json.get("usage").map(usage_from_openai);
+
+        Ok(ChatResponse {
+            message,
+            usage,
+            finish_reason: choice["finish_reason"].as_str().map(str::to_string),
+        })
+    }
+
+    async fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        observer: &dyn StreamObserver,
+    ) -> Result<ChatResponse, LlmError> {
+        let (http, mut body) = self.prepare(request);
+        body["stream"] = true.into();
+        body["stream_options"] = serde_json::json!({"include_usage": true});
+
+        let mut response = ChatResponse {
+            message: Message::assistant(""),
+            usage: None,
+            finish_reason: None,
+        };
+        send_stream(http, &body, self.config.idle_timeout, |event| {
+            if event.data == "[DONE]" {
+                return Ok(true);
+            }
+            let json = event_json(&event)?;
+            if json.get("error").is_some() {
+                return Err(LlmError::Stream(error_message(&event.data)));
+            }
+            if let Some(usage) = json.get("usage").filter(|usage| usage.is_object()) {
+                response.usage = Some(usage_from_openai(usage));
+            }
+
+            let choice = &json["choices"][0];
+            let delta = &choice["delta"];
+            if let Some(text) = delta["content"].as_str() {
+                response.message.content.push_str(text);
+                observer.on_text(text);
+            }
+            // Tool calls arrive in fragments keyed by their index.
+            for fragment in delta["tool_calls"].as_array().into_iter().flatten() {
+                let index = fragment["index"].as_u64().unwrap_or(0);
+                let calls = &mut response.message.tool_calls;
+                // A fragment continues a call or starts the next one.
+                let index = usize::try_from(index)
+                    .ok()
+                    .filter(|index| *index <= calls.len())
+                    .ok_or_else(|| {
+                        LlmError::InvalidResponse(format!(
+                            "Tool call index {} out of order in stream",
+                            index
+                        ))
+                    })?;
+                if index == calls.len() {
+                    calls.push(ToolCall {
+                        id: String::new(),
+                        kind: "function".to_string(),
+                        function: FunctionCall {
+                            name: String::new(),
+                            arguments: String::new(),
+                        },
+                    });
+                }
+                let call = &mut calls[index];
+                if let Some(id) = fragment["id"].as_str() {
+                    call.id = id.to_string();
+                }
+                if let Some(name) = fragment["function"]["name"].as_str() {
+                    call.function.name.push_str(name);
+                    observer.on_tool_call(name);
+                }
+                if let Some(arguments) = fragment["function"]["arguments"].as_str() {
+                    call.function.arguments.push_str(arguments);
+                }
+            }
+            if let Some(reason) = choice["finish_reason"].as_str() {
+                response.finish_reason = Some(reason.to_string());
+            }
+            Ok(false)
+        })
+        .await?;
+
+        Ok(response)
+    }
+}
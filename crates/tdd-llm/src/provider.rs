@@ -0,0 +1,381 @@
+use anyhow::Context;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::anthropic::AnthropicProvider;
+use crate::gemini::GeminiProvider;
+use crate::ollama::{OllamaOptions, OllamaProvider};
+use crate::openai::OpenAiProvider;
+use crate::stream::StreamObserver;
+use crate::types::{ChatRequest, ChatResponse, StructuredOutput};
+
+/// Default upper bound for a non-streaming request.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+/// Default time allowed to establish a connection.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Default time a stream may go without data before it is abandoned.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Error)]
+pub enum LlmError {
+    /// The provider answered with a non-2xx status.
+    #[error("LLM API error ({status}): {message}{}", status_hint(*status))]
+    Api {
+        status: u16,
+        message: String,
+        /// How long the provider asked us to wait (`Retry-After`).
+        retry_after: Option<Duration>,
+    },
+    /// The request never got a response (DNS, connect, timeout, reset...).
+    #[error("Failed to send LLM request: {0}")]
+    Transport(#[source] reqwest::Error),
+    /// The provider answered 2xx with something we cannot understand.
+    #[error("Invalid LLM response: {0}")]
+    InvalidResponse(String),
+    /// A streamed response went quiet for longer than the idle timeout.
+    #[error("LLM stream stalled: no data for {}s", .0.as_secs())]
+    Stalled(Duration),
+    /// The provider reported an error in the middle of a stream.
+    #[error("LLM stream error: {0}")]
+    Stream(String),
+}
+
+impl LlmError {
+    /// Rate limits, overload, server errors and network failures are transient.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            LlmError::Api { status, .. } => matches!(status, 408 | 409 | 429 | 500..=599),
+            LlmError::Transport(e) => e.is_timeout() || e.is_connect() || e.is_request(),
+            LlmError::Stalled(_) | LlmError::Stream(_) => true,
+            LlmError::InvalidResponse(_) => false,
+        }
+    }
+}
+
+fn status_hint(status: u16) -> &'static str {
+    match status {
+        401 | 403 => " (check the API key and its permissions)",
+        400 | 422 => " (the provider rejected the request; check model name and parameters)",
+        404 => " (check base_url and model name)",
+        _ => "",
+    }
+}
+
+/// One LLM wire protocol.
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Response formats the endpoint is expected to accept.
+    fn structured_output(&self) -> StructuredOutput {
+        StructuredOutput::Unsupported
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError>;
+
+    /// Like [`chat`](Self::chat), but streams the response and reports it to
+    /// `observer` as it arrives. Providers without streaming report it whole.
+    async fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        observer: &dyn StreamObserver,
+    ) -> Result<ChatResponse, LlmError> {
+        let response = self.chat(request).await?;
+        observer.on_text(&response.message.content);
+        Ok(response)
+    }
+}
+
+/// Which wire protocol an endpoint speaks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiKind {
+    #[default]
+    OpenAi,
+    Anthropic,
+    Gemini,
+    /// Ollama's native `/api/chat`.
+    Ollama,
+}
+
+impl ApiKind {
+    pub fn build(self, base_url: String, api_key: String) -> Arc<dyn LlmProvider> {
+        ProviderConfig::new(self, base_url, api_key).build()
+    }
+
+    /// How the protocol's own endpoints expect the API key.
+    pub fn default_auth(self) -> Auth {
+        match self {
+            ApiKind::OpenAi | ApiKind::Ollama => Auth::Bearer,
+            ApiKind::Anthropic => Auth::Header("x-api-key".to_string()),
+            ApiKind::Gemini => Auth::Header("x-goog-api-key".to_string()),
+        }
+    }
+}
+
+/// How the API key is sent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Auth {
+    /// `Authorization: Bearer <key>`.
+    Bearer,
+    /// `api-key: <key>`, as Azure OpenAI expects.
+    ApiKey,
+    /// The bare key in a header of this name.
+    Header(String),
+}
+
+impl Auth {
+    pub fn header_name(&self) -> &str {
+        match self {
+            Auth::Bearer => "Authorization",
+            Auth::ApiKey => "api-key",
+            Auth::Header(name) => name,
+        }
+    }
+}
+
+/// Where and how to reach one LLM endpoint.
+#[derive(Debug, Clone)]
+pub struct ProviderConfig {
+    pub api: ApiKind,
+    /// Endpoint root; `{model}` is replaced by the request's model, for
+    /// per-deployment URLs such as Azure OpenAI's.
+    pub base_url: String,
+    /// Sent as `auth` says; omitted when empty.
+    pub api_key: String,
+    /// Defaults to the protocol's own scheme.
+    pub auth: Option<Auth>,
+    /// Extra headers sent with every request.
+    pub headers: BTreeMap<String, String>,
+    /// Query parameters added to every request, e.g. `api-version`.
+    pub query: BTreeMap<String, String>,
+    pub proxy: Option<reqwest::Proxy>,
+    /// Trusted in addition to the system's root certificates.
+    pub ca_certificates: Vec<reqwest::Certificate>,
+    pub connect_timeout: Duration,
+    /// Upper bound for a non-streaming request.
+    pub request_timeout: Duration,
+    /// How long a streamed response may go without data.
+    pub idle_timeout: Duration,
+    /// Overrides the protocol's default response-format support, e.g. for
+    /// OpenAI-compatible servers without JSON schema support.
+    pub structured_output: Option<StructuredOutput>,
+    /// Context size and keep-alive for [`ApiKind::Ollama`].
+    pub ollama: OllamaOptions,
+}
+
+impl ProviderConfig {
+    pub fn new(api: ApiKind, base_url: String, api_key: String) -> Self {
+        Self {
+            api,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            auth: None,
+            headers: BTreeMap::new(),
+            query: BTreeMap::new(),
+            proxy: None,
+            ca_certificates: Vec::new(),
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            structured_output: None,
+            ollama: OllamaOptions::default(),
+        }
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn auth(mut self, auth: Auth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    pub fn query(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.query.insert(name.into(), value.into());
+        self
+    }
+
+    /// Send all requests through the proxy at `url` (http, https or socks5).
+    pub fn proxy(mut self, url: &str) -> anyhow::Result<Self> {
+        let proxy = reqwest::Proxy::all(url).with_context(|| format!("Invalid proxy: {}", url))?;
+        self.proxy = Some(proxy);
+        Ok(self)
+    }
+
+    /// Also trust the PEM certificates in `path`, e.g. a corporate CA.
+    pub fn ca_bundle(mut self, path: &Path) -> anyhow::Result<Self> {
+        let pem = std::fs::read(path)
+            .with_context(|| format!("Failed to read CA bundle {}", path.display()))?;
+        let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+            .with_context(|| format!("Invalid CA bundle {}", path.display()))?;
+        anyhow::ensure!(
+            !certificates.is_empty(),
+            "No certificates in CA bundle {}",
+            path.display()
+        );
+        self.ca_certificates.extend(certificates);
+        Ok(self)
+    }
+
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+
+    pub fn request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.request_timeout = request_timeout;
+        self
+    }
+
+    pub fn idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    pub fn structured_output(mut self, structured_output: StructuredOutput) -> Self {
+        self.structured_output = Some(structured_output);
+        self
+    }
+
+    pub fn ollama(mut self, options: OllamaOptions) -> Self {
+        self.ollama = options;
+        self
+    }
+
+    pub fn build(self) -> Arc<dyn LlmProvider> {
+        match self.api {
+            ApiKind::OpenAi => Arc::new(OpenAiProvider::new(self)),
+            ApiKind::Anthropic => Arc::new(AnthropicProvider::new(self)),
+            ApiKind::Gemini => Arc::new(GeminiProvider::new(self)),
+            ApiKind::Ollama => Arc::new(OllamaProvider::new(self)),
+        }
+    }
+
+    /// The scheme the API key is sent with.
+    pub fn effective_auth(&self) -> Auth {
+        self.auth.clone().unwrap_or_else(|| self.api.default_auth())
+    }
+
+    /// `path` under the base URL, with `{model}` filled in.
+    pub(crate) fn url(&self, model: &str, path: &str) -> String {
+        format!("{}{}", self.base_url.replace("{model}", model), path)
+    }
+
+    /// Start a POST to `url` carrying the credentials, extra headers and query.
+    pub(crate) fn post(&self, client: &reqwest::Client, url: &str) -> reqwest::RequestBuilder {
+        self.prepare(client.post(url))
+    }
+
+    /// Start a GET to `url` carrying the credentials, extra headers and query.
+    pub(crate) fn get(&self, client: &reqwest::Client, url: &str) -> reqwest::RequestBuilder {
+        self.prepare(client.get(url))
+    }
+
+    fn prepare(&self, mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if !self.api_key.is_empty() {
+            request = match self.effective_auth() {
+                Auth::Bearer => request.bearer_auth(&self.api_key),
+                auth => request.header(auth.header_name(), &self.api_key),
+            };
+        }
+        if !self.query.is_empty() {
+            request = request.query(&self.query);
+        }
+        self.headers.iter().fold(request, |request, (name, value)| {
+            request.header(name, value)
+        })
+    }
+}
+
+/// HTTP client honouring the endpoint's proxy, CA bundle and connect timeout.
+pub(crate) fn http_client(config: &ProviderConfig) -> reqwest::Client {
+    let mut builder = reqwest::Client::builder().connect_timeout(config.connect_timeout);
+    if let Some(proxy) = &config.proxy {
+        builder = builder.proxy(proxy.clone());
+    }
+    for certificate in &config.ca_certificates {
+        builder = builder.add_root_certificate(certificate.clone());
+    }
+    builder.build().unwrap_or_default()
+}
+
+/// Send a JSON request and map failures onto [`LlmError`].
+pub(crate) async fn send_json(
+    request: reqwest::RequestBuilder,
+    body: &serde_json::Value,
+    timeout: Duration,
+) -> Result<serde_json::Value, LlmError> {
+    let response = request
+        .header("Content-Type", "application/json")
+        .timeout(timeout)
+        .json(body)
+        .send()
+        .await
+        .map_err(LlmError::Transport)?;
+
+    // A body that times out is a transport failure (and worth retrying),
+    // one that does not parse is not.
+    let bytes = check_status(response)
+        .await?
+        .bytes()
+        .await
+        .map_err(LlmError::Transport)?;
+    serde_json::from_slice(&bytes).map_err(|e| LlmError::InvalidResponse(e.to_string()))
+}
+
+/// Turn a non-2xx response into [`LlmError::Api`].
+pub(crate) async fn check_status(
+    response: reqwest::Response,
+) -> Result<reqwest::Response, LlmError> {
+    let status = response.status();
+    if !status.is_success() {
+        let retry_after = retry_after(response.headers());
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(LlmError::Api {
+            status: status.as_u16(),
+            message: error_message(&error_text),
+            retry_after,
+        });
+    }
+    Ok(response)
+}
+
+/// `retry-after-ms`, or `Retry-After` as seconds or an HTTP date.
+fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let header = |name| headers.get(name).and_then(|v| v.to_str().ok());
+    if let Some(ms) = header("retry-after-ms").and_then(|v| v.parse().ok()) {
+        return Some(Duration::from_millis(ms));
+    }
+    let value = header("retry-after")?;
+    if let Ok(seconds) = value.parse::<f64>() {
+        // Negative, infinite or NaN values are ignored like unparseable ones.
+        return Duration::try_from_secs_f64(seconds).ok();
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(
+        date.duration_since(std::time::SystemTime::now())
+            .unwrap_or_default(),
+    )
+}
+
+/// The human-readable reason: `error.message`, or a bare `error` string (Ollama).
+pub(crate) fn error_message(body: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|json| {
+            json["error"]["message"]
+                .as_str()
+                .or_else(|| json["error"].as_str())
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| body.to_string())
+}
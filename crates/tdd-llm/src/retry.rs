@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::provider::LlmError;
+
+/// How transient LLM failures are retried.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; 0 disables retrying.
+    pub max_retries: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub multiplier: f64,
+    /// Total time that may be spent waiting between attempts.
+    pub max_elapsed_secs: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 4,
+            initial_backoff_ms: 1000,
+            max_backoff_ms: 30_000,
+            multiplier: 2.0,
+            max_elapsed_secs: 300,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before retry number `retry` (1-based): exponential backoff with
+    /// jitter, unless the provider asked for a specific `Retry-After`. Either
+    /// is capped at `max_backoff_ms`.
+    pub fn delay(&self, retry: u32, error: &LlmError) -> Duration {
+        if let LlmError::Api {
+            retry_after: Some(retry_after),
+            ..
+        } = error
+        {
+            return (*retry_after).min(Duration::from_millis(self.max_backoff_ms));
+        }
+        let base = self.initial_backoff_ms as f64 * self.multiplier.powi(retry as i32 - 1);
+        let base = base.min(self.max_backoff_ms as f64);
+        // Equal jitter: half fixed, half random, so concurrent clients spread out.
+        Duration::from_millis((base / 2.0 + fastrand::f64() * base / 2.0) as u64)
+    }
+
+    /// Run `call`, retrying retryable errors within the policy's budget.
+    pub(crate) async fn run<T, F, Fut>(&self, provider: &str, mut call: F) -> Result<T, LlmError>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T, LlmError>>,
+    {
+        let started = Instant::now();
+        let budget = Duration::from_secs(self.max_elapsed_secs);
+        let mut retry = 0;
+        loop {
+            let error = match call().await {
+                Ok(value) => return Ok(value),
+                Err(error) => error,
+            };
+            retry += 1;
+            if !error.is_retryable() || retry > self.max_retries {
+                return Err(error);
+            }
+            let delay = self.delay(retry, &error);
+            if started.elapsed() + delay > budget {
+                println!("LLM retry budget of {:?} exhausted ({})", budget, provider);
+                return Err(error);
+            }
+            println!(
+                "LLM request to {} failed: {}. Retrying in {:.1}s ({}/{})...",
+                provider,
+                error,
+                delay.as_secs_f64(),
+                retry,
+                self.max_retries
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::time::Duration;
+
+use crate::provider::{check_status, LlmError};
+
+/// Receives a streamed response as it arrives, e.g. to render it live.
+pub trait StreamObserver: Send + Sync {
+    fn on_text(&self, text: &str);
+    /// The model started a tool call.
+    fn on_tool_call(&self, _name: &str) {}
+    /// The attempt ended, successfully or not.
+    fn on_finish(&self) {}
+}
+
+/// Discards everything; streaming is then only used for the inactivity timeout.
+pub struct SilentObserver;
+
+impl StreamObserver for SilentObserver {
+    fn on_text(&self, _text: &str) {}
+}
+
+/// One server-sent event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    /// The `event:` field; empty when the server sends only `data:`.
+    pub event: String,
+    pub data: String,
+}
+
+/// Incremental `text/event-stream` parser; chunks may split events anywhere.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+}
+
+impl SseParser {
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer
+            .extend(chunk.iter().filter(|byte| **byte != b'\r'));
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+            let block: Vec<u8> = self.buffer.drain(..end + 2).collect();
+            if let Some(event) = parse_event(&String::from_utf8_lossy(&block)) {
+                events.push(event);
+            }
+        }
+        events
+    }
+}
+
+fn parse_event(block: &str) -> Option<SseEvent> {
+    let mut event = String::new();
+    let mut data = Vec::new();
+    for line in block.lines() {
+        if let Some(value) = line.strip_prefix("data:") {
+            data.push(value.strip_prefix(' ').unwrap_or(value));
+        } else if let Some(value) = line.strip_prefix("event:") {
+            event = value.trim().to_string();
+        }
+    }
+    if data.is_empty() {
+        return None;
+    }
+    Some(SseEvent {
+        event,
+        data: data.join("\n"),
+    })
+}
+
+/// Send a streaming JSON request and feed each event to `on_event` until it
+/// returns `true` or the stream ends. Fails with [`LlmError::Stalled`] when no
+/// data arrives for `idle_timeout`.
+pub(crate) async fn send_stream(
+    request: reqwest::RequestBuilder,
+    body: &serde_json::Value,
+    idle_timeout: Duration,
+    mut on_event: impl FnMut(SseEvent) -> Result<bool, LlmError>,
+) -> Result<(), LlmError> {
+    let mut parser = SseParser::default();
+    send_chunks(request, body, "text/event-stream", idle_timeout, |chunk| {
+        for event in parser.push(chunk) {
+            if on_event(event)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    })
+    .await
+}
+
+/// Like [`send_stream`], for endpoints that stream one JSON object per line
+/// (`application/x-ndjson`).
+pub(crate) async fn send_ndjson(
+    request: reqwest::RequestBuilder,
+    body: &serde_json::Value,
+    idle_timeout: Duration,
+    mut on_line: impl FnMut(serde_json::Value) -> Result<bool, LlmError>,
+) -> Result<(), LlmError> {
+    let mut buffer = Vec::new();
+    send_chunks(
+        request,
+        body,
+        "application/x-ndjson",
+        idle_timeout,
+        |chunk| {
+            buffer.extend_from_slice(chunk);
+            while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let json = serde_json::from_str(line.trim())
+                    .map_err(|e| LlmError::InvalidResponse(format!("bad stream line: {}", e)))?;
+                if on_line(json)? {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        },
+    )
+    .await
+}
+
+/// Send `body` and feed the response to `on_chunk` until it returns true or
+/// the body ends, giving up when no data arrives within `idle_timeout`.
+async fn send_chunks(
+    request: reqwest::RequestBuilder,
+    body: &serde_json::Value,
+    accept: &str,
+    idle_timeout: Duration,
+    mut on_chunk: impl FnMut(&[u8]) -> Result<bool, LlmError>,
+) -> Result<(), LlmError> {
+    let send = request
+        .header("Content-Type", "application/json")
+        .header("Accept", accept)
+        .json(body)
+        .send();
+    let response = tokio::time::timeout(idle_timeout, send)
+        .await
+        .map_err(|_| LlmError::Stalled(idle_timeout))?
+        .map_err(LlmError::Transport)?;
+    let mut response = check_status(response).await?;
+
+    loop {
+        let chunk = tokio::time::timeout(idle_timeout, response.chunk())
+            .await
+            .map_err(|_| LlmError::Stalled(idle_timeout))?
+            .map_err(LlmError::Transport)?;
+        let Some(chunk) = chunk else {
+            return Ok(());
+        };
+        if on_chunk(&chunk)? {
+            return Ok(());
+        }
+    }
+}
+
+/// Parse an event's JSON payload.
+pub(crate) fn event_json(event: &SseEvent) -> Result<serde_json::Value, LlmError> {
+    serde_json::from_str(&event.data)
+        .map_err(|e| LlmError::InvalidResponse(format!("bad stream event: {}", e)))
+}
@@ -0,0 +1,226 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::provider::{ApiKind, LlmError, ProviderConfig};
+use crate::types::{ChatRequest, ChatResponse};
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Key formats redacted from every transcript, on top of configured patterns.
+const DEFAULT_SECRET_PATTERNS: &[&str] = &[
+    r"sk-[A-Za-z0-9_-]{16,}",
+    r"AIza[0-9A-Za-z_-]{30,}",
+    r"(?i)bearer\s+[A-Za-z0-9._~+/=-]+",
+];
+
+/// How much of each LLM call is written to the transcript log.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptLevel {
+    Off,
+    /// Endpoint, parameters, latency, usage and errors, without message text.
+    #[default]
+    Summary,
+    /// Also the full message list, tool definitions and the model's raw answer.
+    /// Opt-in: prompts carry the kata's sources and command output.
+    Full,
+}
+
+/// One JSON file per LLM call, numbered in call order.
+#[derive(Debug)]
+pub struct TranscriptLog {
+    dir: PathBuf,
+    level: TranscriptLevel,
+    patterns: Vec<Regex>,
+    next: AtomicU64,
+}
+
+impl TranscriptLog {
+    /// Numbering continues after the transcripts already in `dir`.
+    pub fn new(dir: impl Into<PathBuf>, level: TranscriptLevel) -> Self {
+        let dir = dir.into();
+        let existing = fs::read_dir(&dir)
+            .map(|entries| entries.count())
+            .unwrap_or(0);
+        Self {
+            dir,
+            level,
+            patterns: DEFAULT_SECRET_PATTERNS
+                .iter()
+                .map(|pattern| Regex::new(pattern).expect("valid built-in pattern"))
+                .collect(),
+            next: AtomicU64::new(existing as u64 + 1),
+        }
+    }
+
+    /// Also redact text matching these regular expressions.
+    pub fn redact_patterns(mut self, patterns: &[String]) -> Result<Self, regex::Error> {
+        for pattern in patterns {
+            self.patterns.push(Regex::new(pattern)?);
+        }
+        Ok(self)
+    }
+
+    pub fn level(&self) -> TranscriptLevel {
+        self.level
+    }
+
+    fn redact(&self, text: &str, secrets: &[String]) -> String {
+        let mut text = text.to_string();
+        for secret in secrets.iter().filter(|secret| !secret.is_empty()) {
+            text = text.replace(secret.as_str(), REDACTED);
+        }
+        for pattern in &self.patterns {
+            text = pattern.replace_all(&text, REDACTED).into_owned();
+        }
+        text
+    }
+
+    pub(crate) fn write(&self, endpoint: &TranscriptEndpoint, call: Call<'_>) {
+        if self.level == TranscriptLevel::Off {
+            return;
+        }
+        let entry = self.entry(endpoint, call);
+        let text = match serde_json::to_string_pretty(&entry) {
+            Ok(text) => self.redact(&text, &endpoint.secrets),
+            Err(error) => return println!("Failed to write LLM transcript: {}", error),
+        };
+        let index = self.next.fetch_add(1, Ordering::SeqCst);
+        let model: String = call
+            .request
+            .model
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let path = self.dir.join(format!("{:04}-{}.json", index, model));
+        if let Err(error) = fs::create_dir_all(&self.dir).and_then(|_| fs::write(&path, text)) {
+            println!("Failed to write LLM transcript {:?}: {}", path, error);
+        }
+    }
+
+    fn entry(&self, endpoint: &TranscriptEndpoint, call: Call<'_>) -> Value {
+        let request = call.request;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let mut entry = json!({
+            "timestamp": timestamp,
+            "provider": endpoint.provider,
+            "api": endpoint.api,
+            "base_url": endpoint.base_url,
+            "headers": endpoint.headers,
+            "model": request.model,
+            "parameters": json!(request.params),
+            "latency_ms": call.latency.as_millis() as u64,
+            "queue_ms": call.queued.as_millis() as u64,
+            "cache_hit": call.cache_hit,
+        });
+        entry["parameters"]["response_format"] = json!(request.response_format);
+        entry["parameters"]["tools"] = request
+            .tools
+            .iter()
+            .map(|tool| tool.name.as_str())
+            .collect();
+        match call.result {
+            Ok(response) => {
+                entry["usage"] = json!(response.usage);
+                entry["finish_reason"] = json!(response.finish_reason);
+            }
+            Err(error) => entry["error"] = error.to_string().into(),
+        }
+
+        match self.level {
+            TranscriptLevel::Full => {
+                entry["messages"] = json!(request.messages);
+                entry["tools"] = json!(request.tools);
+                if let Ok(response) = call.result {
+                    entry["response"] = json!(response.message);
+                }
+            }
+            _ => {
+                let chars: usize = request.messages.iter().map(|m| m.content.len()).sum();
+                entry["messages"] = json!({"count": request.messages.len(), "chars": chars});
+                if let Ok(response) = call.result {
+                    entry["response"] = json!({
+                        "chars": response.message.content.len(),
+                        "tool_calls": response
+                            .message
+                            .tool_calls
+                            .iter()
+                            .map(|call| &call.function.name)
+                            .collect::<Vec<_>>(),
+                    });
+                }
+            }
+        }
+        entry
+    }
+}
+
+/// What a transcript says about the endpoint, with credentials masked.
+#[derive(Debug, Clone)]
+pub(crate) struct TranscriptEndpoint {
+    provider: String,
+    api: ApiKind,
+    base_url: String,
+    headers: BTreeMap<String, String>,
+    /// Literal values to scrub from the transcript text.
+    secrets: Vec<String>,
+}
+
+impl TranscriptEndpoint {
+    pub(crate) fn new(provider: String, config: &ProviderConfig) -> Self {
+        let mut headers = BTreeMap::new();
+        let mut secrets = vec![config.api_key.clone()];
+        if !config.api_key.is_empty() {
+            let auth = config.effective_auth();
+            headers.insert(auth.header_name().to_string(), REDACTED.to_string());
+        }
+        for (name, value) in &config.headers {
+            if is_sensitive_header(name) {
+                secrets.push(value.clone());
+                headers.insert(name.clone(), REDACTED.to_string());
+            } else {
+                headers.insert(name.clone(), value.clone());
+            }
+        }
+        Self {
+            provider,
+            api: config.api,
+            base_url: config.base_url.clone(),
+            headers,
+            secrets,
+        }
+    }
+}
+
+/// One finished call, as seen by the client.
+#[derive(Clone, Copy)]
+pub(crate) struct Call<'a> {
+    pub request: &'a ChatRequest,
+    pub result: Result<&'a ChatResponse, &'a LlmError>,
+    pub latency: Duration,
+    /// Part of `latency` spent waiting for the rate limiter.
+    pub queued: Duration,
+    pub cache_hit: bool,
+}
+
+fn is_sensitive_header(name: &str) -> bool {
+    let name = name.to_lowercase();
+    ["auth", "key", "token", "secret", "cookie"]
+        .iter()
+        .any(|needle| name.contains(needle))
+}
@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::types::Usage;
+
+/// USD prices per million tokens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+    /// Price of cache reads; defaults to `input_per_mtok`.
+    #[serde(default)]
+    pub cached_input_per_mtok: Option<f64>,
+}
+
+impl ModelPrice {
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        let cached = usage.cached_tokens.min(usage.prompt_tokens);
+        let uncached = usage.prompt_tokens - cached;
+        let cached_price = self.cached_input_per_mtok.unwrap_or(self.input_per_mtok);
+        (uncached as f64 * self.input_per_mtok
+            + cached as f64 * cached_price
+            + usage.completion_tokens as f64 * self.output_per_mtok)
+            / 1_000_000.0
+    }
+}
+
+/// Usage of one LLM call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub provider: String,
+    pub model: String,
+    pub usage: Usage,
+    /// `None` when the model has no entry in the pricing table.
+    pub cost_usd: Option<f64>,
+    /// Answered from the response cache; nothing was spent.
+    #[serde(default)]
+    pub cache_hit: bool,
+}
+
+/// Collects the usage of every call made by the clients it is attached to.
+#[derive(Debug, Default)]
+pub struct UsageRecorder {
+    /// Prices keyed by bare model name.
+    pricing: HashMap<String, ModelPrice>,
+    pending: Mutex<Vec<UsageRecord>>,
+}
+
+impl UsageRecorder {
+    pub fn new(pricing: HashMap<String, ModelPrice>) -> Self {
+        Self {
+            pricing,
+            pending: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn record(&self, provider: &str, model: &str, usage: Usage) {
+        let cost_usd = self.pricing.get(model).map(|price| price.cost(&usage));
+        self.pending.lock().unwrap().push(UsageRecord {
+            provider: provider.to_string(),
+            model: model.to_string(),
+            usage,
+            cost_usd,
+            cache_hit: false,
+        });
+    }
+
+    pub fn record_cache_hit(&self, provider: &str, model: &str) {
+        self.pending.lock().unwrap().push(UsageRecord {
+            provider: provider.to_string(),
+            model: model.to_string(),
+            usage: Usage::default(),
+            cost_usd: None,
+            cache_hit: true,
+        });
+    }
+
+    /// Records since the previous call.
+    pub fn drain(&self) -> Vec<UsageRecord> {
+        std::mem::take(&mut *self.pending.lock().unwrap())
+    }
+}
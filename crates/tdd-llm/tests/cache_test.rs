@@ -0,0 +1,90 @@
+mod common;
+
+use anyhow::Result;
+use common::StubServer;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tdd_llm::{
+    ApiKind, ChatRequest, ChatResponse, LlmClient, Message, ResponseCache, UsageRecorder,
+};
+use tempfile::TempDir;
+
+fn request(temperature: f32) -> ChatRequest {
+    ChatRequest::new("gpt", vec![Message::user("hello")]).temperature(temperature)
+}
+
+fn response(content: &str) -> ChatResponse {
+    ChatResponse {
+        message: Message::assistant(content),
+        usage: None,
+        finish_reason: None,
+    }
+}
+
+#[tokio::test]
+async fn test_repeated_requests_are_served_from_cache() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let server = StubServer::start(
+        200,
+        json!({
+            "choices": [{"message": {"role": "assistant", "content": "hi"}}],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 2}
+        }),
+    )
+    .await;
+    let cache = Arc::new(ResponseCache::new(
+        temp_dir.path(),
+        Duration::from_secs(60),
+        1024 * 1024,
+    ));
+    let recorder = Arc::new(UsageRecorder::new(HashMap::new()));
+    let client = LlmClient::with_provider(ApiKind::OpenAi.build(server.url.clone(), "sk".into()))
+        .with_cache(cache, "openai")
+        .with_usage(recorder.clone());
+
+    client.complete(&request(0.2)).await?;
+    let cached = client.complete(&request(0.2)).await?;
+    client.complete(&request(0.7)).await?;
+
+    assert_eq!(cached.message.content, "hi");
+    assert_eq!(server.request_count(), 2);
+    let hits: Vec<bool> = recorder.drain().iter().map(|r| r.cache_hit).collect();
+    assert_eq!(hits, vec![false, true, false]);
+    Ok(())
+}
+
+#[test]
+fn test_refresh_ignores_entries_but_stores_new_ones() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let cache = ResponseCache::new(temp_dir.path(), Duration::from_secs(60), 1024 * 1024);
+    let key = ResponseCache::key("openai", &request(0.2));
+    cache.put(&key, &response("old"))?;
+
+    let refreshing = cache.clone().refresh(true);
+    assert!(refreshing.get(&key).is_none());
+    refreshing.put(&key, &response("new"))?;
+
+    assert_eq!(cache.get(&key).unwrap().message.content, "new");
+    assert_ne!(key, ResponseCache::key("local", &request(0.2)));
+    Ok(())
+}
+
+#[test]
+fn test_expired_and_excess_entries_are_evicted() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let expired = ResponseCache::new(temp_dir.path(), Duration::ZERO, 1024 * 1024);
+    expired.put("a", &response("a"))?;
+    std::thread::sleep(Duration::from_millis(20));
+    assert!(expired.get("a").is_none());
+
+    let tiny = ResponseCache::new(temp_dir.path(), Duration::from_secs(60), 150);
+    tiny.put("b", &response("b"))?;
+    std::thread::sleep(Duration::from_millis(20));
+    tiny.put("c", &response("c"))?;
+
+    assert!(tiny.get("b").is_none());
+    assert_eq!(tiny.get("c").unwrap().message.content, "c");
+    Ok(())
+}
@@ -0,0 +1,165 @@
+// Each test binary uses a different subset of the stub.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub path: String,
+    pub headers: HashMap<String, String>,
+    pub body: serde_json::Value,
+}
+
+#[derive(Debug, Clone)]
+pub struct StubResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    /// Keep the connection open after the body, promising more that never comes.
+    pub stall: bool,
+}
+
+impl StubResponse {
+    pub fn json(status: u16, body: serde_json::Value) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.to_string(),
+            stall: false,
+        }
+    }
+
+    /// A `text/event-stream` body of `data:` events.
+    pub fn sse(events: &[serde_json::Value]) -> Self {
+        let body = events
+            .iter()
+            .map(|event| format!("data: {}\n\n", event))
+            .collect();
+        Self {
+            status: 200,
+            headers: Vec::new(),
+            body,
+            stall: false,
+        }
+    }
+
+    /// An `application/x-ndjson` body, one JSON object per line.
+    pub fn ndjson(lines: &[serde_json::Value]) -> Self {
+        let body = lines.iter().map(|line| format!("{}\n", line)).collect();
+        Self {
+            status: 200,
+            headers: Vec::new(),
+            body,
+            stall: false,
+        }
+    }
+
+    pub fn stalled(mut self) -> Self {
+        self.stall = true;
+        self
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// Minimal HTTP/1.1 server replaying canned responses in order; the last one repeats.
+pub struct StubServer {
+    pub url: String,
+    pub requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl StubServer {
+    pub async fn start(status: u16, body: serde_json::Value) -> Self {
+        Self::sequence(vec![StubResponse::json(status, body)]).await
+    }
+
+    pub async fn sequence(responses: Vec<StubResponse>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let request = read_request(&mut socket).await;
+                let index = {
+                    let mut recorded = recorded.lock().unwrap();
+                    recorded.push(request);
+                    recorded.len().min(responses.len()) - 1
+                };
+                let stub = responses[index].clone();
+                tokio::spawn(async move {
+                    let length = stub.body.len() + usize::from(stub.stall);
+                    let mut head = format!(
+                        "HTTP/1.1 {} Stub\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+                        stub.status, length
+                    );
+                    for (name, value) in &stub.headers {
+                        head.push_str(&format!("{}: {}\r\n", name, value));
+                    }
+                    let response = format!("{}\r\n{}", head, stub.body);
+                    let _ = socket.write_all(response.as_bytes()).await;
+                    if stub.stall {
+                        tokio::time::sleep(std::time::Duration::from_secs(3600)).await;
+                    }
+                });
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    pub fn last_request(&self) -> RecordedRequest {
+        self.requests.lock().unwrap().last().cloned().unwrap()
+    }
+
+    pub fn request_count(&self) -> usize {
+        self.requests.lock().unwrap().len()
+    }
+}
+
+async fn read_request(socket: &mut tokio::net::TcpStream) -> RecordedRequest {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        let n = socket.read(&mut chunk).await.unwrap();
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+    let mut lines = head.lines();
+    let path = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .unwrap_or_default()
+        .to_string();
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim().to_lowercase(), v.trim().to_string()))
+        .collect();
+
+    let length: usize = headers
+        .get("content-length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    while buf.len() < header_end + length {
+        let n = socket.read(&mut chunk).await.unwrap();
+        buf.extend_from_slice(&chunk[..n]);
+    }
+
+    RecordedRequest {
+        path,
+        headers,
+        body: serde_json::from_slice(&buf[header_end..header_end + length])
+            .unwrap_or(serde_json::Value::Null),
+    }
+}
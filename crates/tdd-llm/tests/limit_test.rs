@@ -0,0 +1,65 @@
+use std::time::Duration;
+use tdd_llm::{RateLimiter, RateLimits, Usage};
+
+fn limiter(limits: RateLimits) -> RateLimiter {
+    RateLimiter::new("test", limits)
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_requests_per_minute_are_queued() {
+    let limiter = limiter(RateLimits {
+        requests_per_minute: Some(2),
+        ..RateLimits::default()
+    });
+
+    let first = limiter.acquire(1).await.waited();
+    let second = limiter.acquire(1).await.waited();
+    let third = limiter.acquire(1).await.waited();
+
+    assert_eq!(first, Duration::ZERO);
+    assert_eq!(second, Duration::ZERO);
+    assert!(third >= Duration::from_secs(60), "{:?}", third);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_tokens_per_minute_use_reported_usage() {
+    let limiter = limiter(RateLimits {
+        tokens_per_minute: Some(100),
+        ..RateLimits::default()
+    });
+
+    // Estimated at 80, but the provider reports 30.
+    limiter.acquire(80).await.settle(Some(Usage {
+        prompt_tokens: 20,
+        completion_tokens: 10,
+        cached_tokens: 0,
+    }));
+    let fits = limiter.acquire(60).await;
+    assert_eq!(fits.waited(), Duration::ZERO);
+    fits.settle(None);
+
+    let over = limiter.acquire(20).await.waited();
+    assert!(over >= Duration::from_secs(60), "{:?}", over);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_max_in_flight_waits_for_a_free_slot() {
+    let limiter = limiter(RateLimits {
+        max_in_flight: Some(1),
+        ..RateLimits::default()
+    });
+
+    let (_, waited) = tokio::join!(
+        async {
+            let permit = limiter.acquire(1).await;
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            drop(permit);
+        },
+        async {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            limiter.acquire(1).await.waited()
+        }
+    );
+
+    assert!(waited >= Duration::from_millis(4999), "{:?}", waited);
+}
@@ -0,0 +1,60 @@
+use tdd_llm::{GenerationParams, ModelCapabilities, ModelRef, ReasoningEffort};
+
+#[test]
+fn test_model_ref_splits_known_provider_prefix() {
+    let is_provider = |name: &str| ["openai", "ollama"].contains(&name);
+
+    assert_eq!(
+        ModelRef::parse("openai:gpt-4o", is_provider),
+        ModelRef {
+            provider: Some("openai".to_string()),
+            model: "gpt-4o".to_string()
+        }
+    );
+    assert_eq!(
+        ModelRef::parse("ollama:llama3:8b", is_provider).model,
+        "llama3:8b"
+    );
+    assert_eq!(ModelRef::parse("llama3:8b", is_provider).provider, None);
+    assert_eq!(ModelRef::parse("gpt-4o", is_provider).model, "gpt-4o");
+}
+
+fn all_params() -> GenerationParams {
+    GenerationParams {
+        temperature: Some(0.2),
+        max_tokens: Some(200_000),
+        top_p: Some(0.9),
+        seed: Some(7),
+        stop: vec!["END".to_string()],
+        reasoning_effort: Some(ReasoningEffort::High),
+        ..GenerationParams::default()
+    }
+}
+
+#[test]
+fn test_reasoning_models_drop_sampling_and_use_max_completion_tokens() {
+    let mut params = all_params();
+    ModelCapabilities::builtin("openai/o3-mini").apply(&mut params);
+
+    assert_eq!(params.temperature, None);
+    assert_eq!(params.top_p, None);
+    assert!(params.stop.is_empty());
+    assert_eq!(params.max_tokens, None);
+    assert_eq!(params.max_completion_tokens, Some(100_000));
+    assert_eq!(params.reasoning_effort, Some(ReasoningEffort::High));
+}
+
+#[test]
+fn test_chat_models_keep_sampling_and_drop_reasoning_effort() {
+    let mut params = all_params();
+    ModelCapabilities::builtin("gpt-4o-mini").apply(&mut params);
+
+    assert_eq!(params.temperature, Some(0.2));
+    assert_eq!(params.seed, Some(7));
+    assert_eq!(params.max_tokens, Some(16_384));
+    assert_eq!(params.reasoning_effort, None);
+
+    let mut unknown = all_params();
+    ModelCapabilities::builtin("llama3:8b").apply(&mut unknown);
+    assert_eq!(unknown.max_tokens, Some(200_000));
+}
@@ -0,0 +1,154 @@
+mod common;
+
+use anyhow::Result;
+use common::{StubResponse, StubServer};
+use serde_json::json;
+use std::sync::Arc;
+use tdd_llm::{
+    ApiKind, ChatRequest, FunctionCall, GenerationParams, LlmClient, Message, OllamaOptions,
+    OllamaProvider, ProviderConfig, ResponseFormat, RetryPolicy, SilentObserver, ToolCall,
+};
+
+fn provider(url: &str) -> ProviderConfig {
+    ProviderConfig::new(ApiKind::Ollama, url.to_string(), String::new()).ollama(OllamaOptions {
+        num_ctx: Some(16384),
+        keep_alive: Some("10m".to_string()),
+    })
+}
+
+fn client(url: &str) -> LlmClient {
+    LlmClient::with_provider(provider(url).build()).with_retry(RetryPolicy::none())
+}
+
+#[tokio::test]
+async fn test_ollama_chat_request_and_response() -> Result<()> {
+    let server = StubServer::start(
+        200,
+        json!({
+            "message": {"role": "assistant", "content": "", "tool_calls": [
+                {"function": {"name": "read_file", "arguments": {"path": "src/lib.rs"}}}
+            ]},
+            "done": true,
+            "done_reason": "stop",
+            "prompt_eval_count": 12,
+            "eval_count": 3
+        }),
+    )
+    .await;
+    let mut assistant = Message::assistant("");
+    assistant.tool_calls.push(ToolCall {
+        id: "call_0".to_string(),
+        kind: "function".to_string(),
+        function: FunctionCall {
+            name: "list_files".to_string(),
+            arguments: "{}".to_string(),
+        },
+    });
+    let request = ChatRequest::new(
+        "qwen2.5-coder:7b",
+        vec![
+            Message::user("hi"),
+            assistant,
+            Message::tool("call_0", "src/lib.rs"),
+        ],
+    )
+    .temperature(0.2)
+    .response_format(ResponseFormat::JsonObject);
+
+    let response = client(&server.url).complete(&request).await?;
+
+    let call = &response.message.tool_calls[0];
+    assert_eq!(call.function.name, "read_file");
+    assert_eq!(call.function.arguments, r#"{"path":"src/lib.rs"}"#);
+    assert_eq!(response.usage.unwrap().prompt_tokens, 12);
+    let sent = server.last_request();
+    assert_eq!(sent.path, "/api/chat");
+    assert_eq!(sent.body["stream"], false);
+    assert_eq!(sent.body["options"]["num_ctx"], 16384);
+    assert_eq!(sent.body["keep_alive"], "10m");
+    assert_eq!(sent.body["format"], "json");
+    assert_eq!(
+        sent.body["messages"][1]["tool_calls"][0]["function"]["arguments"],
+        json!({})
+    );
+    assert_eq!(sent.body["messages"][2]["tool_name"], "list_files");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_ollama_streams_ndjson() -> Result<()> {
+    let server = StubServer::sequence(vec![StubResponse::ndjson(&[
+        json!({"message": {"role": "assistant", "content": "Hel"}, "done": false}),
+        json!({"message": {"role": "assistant", "content": "lo"}, "done": false}),
+        json!({"message": {"role": "assistant", "content": ""}, "done": true,
+               "done_reason": "stop", "prompt_eval_count": 4, "eval_count": 2}),
+    ])])
+    .await;
+
+    let response = client(&server.url)
+        .with_stream(Arc::new(SilentObserver))
+        .complete(&ChatRequest::new("llama3", vec![Message::user("hi")]))
+        .await?;
+
+    assert_eq!(response.message.content, "Hello");
+    assert_eq!(response.finish_reason.as_deref(), Some("stop"));
+    assert_eq!(response.usage.unwrap().completion_tokens, 2);
+    assert_eq!(server.last_request().body["stream"], true);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_think_blocks_are_stripped() -> Result<()> {
+    for content in [
+        "<think>\nThe user wants JSON.\n</think>\n\n{\"a\":1}",
+        "The user wants JSON.\n</think>{\"a\":1}",
+        "{\"a\":1}<think>and then some",
+    ] {
+        let server = StubServer::start(
+            200,
+            json!({"message": {"role": "assistant", "content": content}, "done": true}),
+        )
+        .await;
+
+        let answer = client(&server.url)
+            .chat(
+                "deepseek-r1",
+                vec![Message::user("hi")],
+                &GenerationParams::default(),
+            )
+            .await?;
+
+        assert_eq!(answer, r#"{"a":1}"#, "{:?}", content);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_list_models_and_errors() -> Result<()> {
+    let server = StubServer::start(
+        200,
+        json!({"models": [{"name": "llama3:latest"}, {"name": "qwen2.5-coder:7b"}]}),
+    )
+    .await;
+    let models = OllamaProvider::new(provider(&server.url))
+        .list_models()
+        .await?;
+    assert_eq!(models, vec!["llama3:latest", "qwen2.5-coder:7b"]);
+    assert_eq!(server.last_request().path, "/api/tags");
+
+    let missing = StubServer::start(404, json!({"error": "model \"llama9\" not found"})).await;
+    let error = client(&missing.url)
+        .chat(
+            "llama9",
+            vec![Message::user("hi")],
+            &GenerationParams::default(),
+        )
+        .await
+        .unwrap_err();
+    assert!(
+        error.to_string().contains("model \"llama9\" not found"),
+        "{}",
+        error
+    );
+    Ok(())
+}
@@ -0,0 +1,328 @@
+mod common;
+
+use anyhow::Result;
+use common::StubResponse;
+use common::StubServer;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tdd_llm::{
+    ApiKind, Auth, ChatRequest, GenerationParams, LlmClient, LlmError, Message, ModelCapabilities,
+    ProviderConfig, ReasoningEffort, RetryPolicy, ToolDefinition, Usage,
+};
+
+fn request() -> ChatRequest {
+    ChatRequest::new(
+        "test-model",
+        vec![Message::system("be brief"), Message::user("hello")],
+    )
+    .temperature(0.2)
+}
+
+#[tokio::test]
+async fn test_openai_adapter() -> Result<()> {
+    let server = StubServer::start(
+        200,
+        json!({
+            "choices": [{"message": {"role": "assistant", "content": "hi"}, "finish_reason": "stop"}],
+            "usage": {"prompt_tokens": 12, "completion_tokens": 3, "prompt_tokens_details": {"cached_tokens": 8}}
+        }),
+    )
+    .await;
+    let client = LlmClient::with_provider(ApiKind::OpenAi.build(server.url.clone(), "sk".into()));
+
+    let response = client.complete(&request()).await?;
+
+    assert_eq!(response.message.content, "hi");
+    assert_eq!(
+        response.usage,
+        Some(Usage {
+            prompt_tokens: 12,
+            completion_tokens: 3,
+            cached_tokens: 8
+        })
+    );
+    let sent = server.last_request();
+    assert_eq!(sent.path, "/chat/completions");
+    assert_eq!(sent.headers["authorization"], "Bearer sk");
+    assert_eq!(sent.body["messages"][0]["role"], "system");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_anthropic_adapter() -> Result<()> {
+    let server = StubServer::start(
+        200,
+        json!({
+            "content": [
+                {"type": "text", "text": "let me look"},
+                {"type": "tool_use", "id": "toolu_1", "name": "read_file", "input": {"path": "src/lib.rs"}}
+            ],
+            "stop_reason": "tool_use",
+            "usage": {"input_tokens": 20, "output_tokens": 5}
+        }),
+    )
+    .await;
+    let client =
+        LlmClient::with_provider(ApiKind::Anthropic.build(server.url.clone(), "key".into()));
+    let tools = vec![ToolDefinition {
+        name: "read_file".to_string(),
+        description: "Read a file".to_string(),
+        parameters: json!({"type": "object", "properties": {"path": {"type": "string"}}}),
+    }];
+
+    let response = client.complete(&request().tools(tools)).await?;
+
+    assert_eq!(response.message.content, "let me look");
+    assert_eq!(response.message.tool_calls[0].id, "toolu_1");
+    assert_eq!(
+        response.message.tool_calls[0].function.arguments,
+        r#"{"path":"src/lib.rs"}"#
+    );
+    assert_eq!(response.usage.unwrap().prompt_tokens, 20);
+    assert_eq!(response.finish_reason.as_deref(), Some("tool_use"));
+
+    let sent = server.last_request();
+    assert_eq!(sent.path, "/messages");
+    assert_eq!(sent.headers["x-api-key"], "key");
+    assert_eq!(sent.headers["anthropic-version"], "2023-06-01");
+    assert_eq!(sent.body["system"], "be brief");
+    assert_eq!(
+        sent.body["messages"],
+        json!([{"role": "user", "content": [{"type": "text", "text": "hello"}]}])
+    );
+    assert_eq!(sent.body["tools"][0]["input_schema"]["type"], "object");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_gemini_adapter() -> Result<()> {
+    let server = StubServer::start(
+        200,
+        json!({
+            "candidates": [{"content": {"role": "model", "parts": [{"text": "hi"}]}, "finishReason": "STOP"}],
+            "usageMetadata": {"promptTokenCount": 7, "candidatesTokenCount": 1}
+        }),
+    )
+    .await;
+    let client = LlmClient::with_provider(ApiKind::Gemini.build(server.url.clone(), "key".into()));
+
+    let response = client.complete(&request()).await?;
+
+    assert_eq!(response.message.content, "hi");
+    assert_eq!(response.usage.unwrap().completion_tokens, 1);
+    let sent = server.last_request();
+    assert_eq!(sent.path, "/models/test-model:generateContent");
+    assert_eq!(sent.headers["x-goog-api-key"], "key");
+    assert_eq!(
+        sent.body["systemInstruction"]["parts"][0]["text"],
+        "be brief"
+    );
+    assert_eq!(sent.body["contents"][0]["parts"][0]["text"], "hello");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_api_errors_are_mapped() -> Result<()> {
+    let server = StubServer::start(
+        401,
+        json!({"type": "error", "error": {"type": "authentication_error", "message": "invalid x-api-key"}}),
+    )
+    .await;
+    let client =
+        LlmClient::with_provider(ApiKind::Anthropic.build(server.url.clone(), "bad".into()));
+
+    let error = client.complete(&request()).await.unwrap_err();
+
+    match error.downcast_ref::<LlmError>() {
+        Some(LlmError::Api {
+            status, message, ..
+        }) => {
+            assert_eq!(*status, 401);
+            assert_eq!(message, "invalid x-api-key");
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_extra_headers_and_keyless_providers() -> Result<()> {
+    let server = StubServer::start(
+        200,
+        json!({"choices": [{"message": {"role": "assistant", "content": "hi"}}]}),
+    )
+    .await;
+    let provider = ProviderConfig::new(ApiKind::OpenAi, server.url.clone(), String::new())
+        .header("X-Team", "kata")
+        .build();
+
+    LlmClient::with_provider(provider)
+        .complete(&request())
+        .await?;
+
+    let sent = server.last_request();
+    assert_eq!(sent.headers["x-team"], "kata");
+    assert!(!sent.headers.contains_key("authorization"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_azure_deployment_with_api_key_and_version() -> Result<()> {
+    let server = StubServer::start(
+        200,
+        json!({"choices": [{"message": {"role": "assistant", "content": "hi"}}]}),
+    )
+    .await;
+    let base_url = format!("{}/openai/deployments/{{model}}", server.url);
+    let provider = ProviderConfig::new(ApiKind::OpenAi, base_url, "azure-key".into())
+        .auth(Auth::ApiKey)
+        .query("api-version", "2024-10-21")
+        .build();
+
+    LlmClient::with_provider(provider)
+        .complete(&ChatRequest::new("gpt4o", vec![Message::user("hello")]))
+        .await?;
+
+    let sent = server.last_request();
+    assert_eq!(
+        sent.path,
+        "/openai/deployments/gpt4o/chat/completions?api-version=2024-10-21"
+    );
+    assert_eq!(sent.headers["api-key"], "azure-key");
+    assert!(!sent.headers.contains_key("authorization"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_custom_auth_header() -> Result<()> {
+    let server = StubServer::start(
+        200,
+        json!({"choices": [{"message": {"role": "assistant", "content": "hi"}}]}),
+    )
+    .await;
+    let provider = ProviderConfig::new(ApiKind::OpenAi, server.url.clone(), "gw-key".into())
+        .auth(Auth::Header("X-Gateway-Key".into()))
+        .build();
+
+    LlmClient::with_provider(provider)
+        .complete(&request())
+        .await?;
+
+    let sent = server.last_request();
+    assert_eq!(sent.headers["x-gateway-key"], "gw-key");
+    assert!(!sent.headers.contains_key("authorization"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_requests_go_through_proxy() -> Result<()> {
+    let proxy = StubServer::start(
+        200,
+        json!({"choices": [{"message": {"role": "assistant", "content": "hi"}}]}),
+    )
+    .await;
+    let provider = ProviderConfig::new(
+        ApiKind::OpenAi,
+        "http://llm.internal/v1".into(),
+        String::new(),
+    )
+    .proxy(&proxy.url)?
+    .build();
+
+    LlmClient::with_provider(provider)
+        .complete(&request())
+        .await?;
+
+    assert_eq!(
+        proxy.last_request().path,
+        "http://llm.internal/v1/chat/completions"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_request_timeout() -> Result<()> {
+    let server = StubServer::sequence(vec![StubResponse::json(200, json!({})).stalled()]).await;
+    let provider = ProviderConfig::new(ApiKind::OpenAi, server.url.clone(), String::new())
+        .request_timeout(Duration::from_millis(200))
+        .build();
+
+    let error = LlmClient::with_provider(provider)
+        .with_retry(RetryPolicy::none())
+        .complete(&request())
+        .await
+        .unwrap_err();
+
+    assert!(
+        matches!(
+            error.downcast_ref::<LlmError>(),
+            Some(LlmError::Transport(_))
+        ),
+        "unexpected error: {:?}",
+        error
+    );
+    Ok(())
+}
+
+#[test]
+fn test_invalid_ca_bundle_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("ca.pem");
+    std::fs::write(&path, "not a certificate").unwrap();
+
+    let config = ProviderConfig::new(ApiKind::OpenAi, "https://x".into(), String::new());
+    assert!(config.clone().ca_bundle(&path).is_err());
+    assert!(config.ca_bundle(&dir.path().join("missing.pem")).is_err());
+}
+
+#[tokio::test]
+async fn test_requests_are_fitted_to_the_model() -> Result<()> {
+    let server = StubServer::start(
+        200,
+        json!({"choices": [{"message": {"role": "assistant", "content": "hi"}}]}),
+    )
+    .await;
+    let capabilities = HashMap::from([(
+        "house-reasoner".to_string(),
+        ModelCapabilities {
+            sampling: false,
+            reasoning: true,
+            ..ModelCapabilities::default()
+        },
+    )]);
+    let client = LlmClient::with_provider(ApiKind::OpenAi.build(server.url.clone(), "sk".into()))
+        .with_capabilities(Arc::new(capabilities));
+    let params = GenerationParams {
+        temperature: Some(0.2),
+        max_tokens: Some(4000),
+        seed: Some(7),
+        reasoning_effort: Some(ReasoningEffort::Low),
+        ..GenerationParams::default()
+    };
+
+    client
+        .chat("o4-mini", vec![Message::user("hi")], &params)
+        .await?;
+    let sent = server.last_request().body;
+    assert!(sent.get("temperature").is_none());
+    assert!(sent.get("max_tokens").is_none());
+    assert_eq!(sent["max_completion_tokens"], 4000);
+    assert_eq!(sent["reasoning_effort"], "low");
+    assert_eq!(sent["seed"], 7);
+
+    client
+        .chat("house-reasoner", vec![Message::user("hi")], &params)
+        .await?;
+    assert_eq!(server.last_request().body["reasoning_effort"], "low");
+
+    client
+        .chat("gpt-4o", vec![Message::user("hi")], &params)
+        .await?;
+    let sent = server.last_request().body;
+    assert_eq!(sent["max_tokens"], 4000);
+    assert!(sent.get("reasoning_effort").is_none());
+    assert!(sent.get("temperature").is_some());
+    Ok(())
+}
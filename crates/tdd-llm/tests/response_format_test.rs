@@ -0,0 +1,104 @@
+mod common;
+
+use anyhow::Result;
+use common::{StubResponse, StubServer};
+use serde_json::json;
+use tdd_llm::{ApiKind, ChatRequest, LlmClient, Message, ResponseFormat, RetryPolicy};
+
+fn schema_request() -> ChatRequest {
+    ChatRequest::new("model", vec![Message::user("reply in JSON")]).response_format(
+        ResponseFormat::JsonSchema {
+            name: "answer".to_string(),
+            schema: json!({"type": "object", "properties": {}, "additionalProperties": false}),
+            strict: true,
+        },
+    )
+}
+
+fn ok_body() -> serde_json::Value {
+    json!({"choices": [{"message": {"role": "assistant", "content": "{}"}}]})
+}
+
+#[tokio::test]
+async fn test_openai_sends_strict_json_schema() -> Result<()> {
+    let server = StubServer::start(200, ok_body()).await;
+    let client = LlmClient::with_provider(ApiKind::OpenAi.build(server.url.clone(), "sk".into()));
+
+    client.complete(&schema_request()).await?;
+
+    let format = &server.last_request().body["response_format"];
+    assert_eq!(format["type"], "json_schema");
+    assert_eq!(format["json_schema"]["name"], "answer");
+    assert_eq!(format["json_schema"]["strict"], true);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rejected_formats_fall_back_and_are_remembered() -> Result<()> {
+    let rejected = |what: &str| {
+        StubResponse::json(
+            400,
+            json!({"error": {"message": format!("Invalid parameter: 'response_format' of type '{}' is not supported", what)}}),
+        )
+    };
+    let server = StubServer::sequence(vec![
+        rejected("json_schema"),
+        rejected("json_object"),
+        StubResponse::json(200, ok_body()),
+    ])
+    .await;
+    let client = LlmClient::with_provider(ApiKind::OpenAi.build(server.url.clone(), "sk".into()))
+        .with_retry(RetryPolicy::none());
+
+    client.complete(&schema_request()).await?;
+    client.complete(&schema_request()).await?;
+
+    let requests = server.requests.lock().unwrap();
+    assert_eq!(requests.len(), 4);
+    assert_eq!(requests[0].body["response_format"]["type"], "json_schema");
+    assert_eq!(requests[1].body["response_format"]["type"], "json_object");
+    assert!(requests[2].body.get("response_format").is_none());
+    assert!(requests[3].body.get("response_format").is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_other_bad_requests_are_not_treated_as_format_rejections() -> Result<()> {
+    let server =
+        StubServer::start(400, json!({"error": {"message": "model does not exist"}})).await;
+    let client = LlmClient::with_provider(ApiKind::OpenAi.build(server.url.clone(), "sk".into()));
+
+    assert!(client.complete(&schema_request()).await.is_err());
+    assert_eq!(server.request_count(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_provider_capabilities_shape_the_request() -> Result<()> {
+    let gemini = StubServer::start(
+        200,
+        json!({"candidates": [{"content": {"role": "model", "parts": [{"text": "{}"}]}}]}),
+    )
+    .await;
+    LlmClient::with_provider(ApiKind::Gemini.build(gemini.url.clone(), "key".into()))
+        .complete(&schema_request())
+        .await?;
+    let config = &gemini.last_request().body["generationConfig"];
+    assert_eq!(config["responseMimeType"], "application/json");
+    assert!(config.get("responseJsonSchema").is_none());
+
+    let anthropic = StubServer::start(
+        200,
+        json!({"content": [{"type": "text", "text": "{}"}], "stop_reason": "end_turn"}),
+    )
+    .await;
+    LlmClient::with_provider(ApiKind::Anthropic.build(anthropic.url.clone(), "key".into()))
+        .complete(&schema_request())
+        .await?;
+    assert!(anthropic
+        .last_request()
+        .body
+        .get("response_format")
+        .is_none());
+    Ok(())
+}
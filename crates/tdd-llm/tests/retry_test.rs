@@ -0,0 +1,113 @@
+mod common;
+
+use anyhow::Result;
+use common::{StubResponse, StubServer};
+use serde_json::json;
+use tdd_llm::{ApiKind, ChatRequest, LlmClient, LlmError, Message, RetryPolicy};
+
+fn fast_retries() -> RetryPolicy {
+    RetryPolicy {
+        max_retries: 3,
+        initial_backoff_ms: 1,
+        max_backoff_ms: 5,
+        ..RetryPolicy::default()
+    }
+}
+
+fn client(server: &StubServer) -> LlmClient {
+    LlmClient::with_provider(ApiKind::OpenAi.build(server.url.clone(), "sk".into()))
+        .with_retry(fast_retries())
+}
+
+fn request() -> ChatRequest {
+    ChatRequest::new("gpt", vec![Message::user("hello")])
+}
+
+fn ok_body() -> serde_json::Value {
+    json!({"choices": [{"message": {"role": "assistant", "content": "hi"}}]})
+}
+
+#[tokio::test]
+async fn test_transient_errors_are_retried() -> Result<()> {
+    let server = StubServer::sequence(vec![
+        StubResponse::json(503, json!({"error": {"message": "overloaded"}})),
+        StubResponse::json(429, json!({"error": {"message": "slow down"}}))
+            .header("Retry-After", "0"),
+        StubResponse::json(200, ok_body()),
+    ])
+    .await;
+
+    let response = client(&server).complete(&request()).await?;
+
+    assert_eq!(response.message.content, "hi");
+    assert_eq!(server.request_count(), 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_client_errors_fail_fast() -> Result<()> {
+    let server = StubServer::sequence(vec![
+        StubResponse::json(401, json!({"error": {"message": "bad key"}})),
+        StubResponse::json(200, ok_body()),
+    ])
+    .await;
+
+    let error = client(&server).complete(&request()).await.unwrap_err();
+
+    assert_eq!(server.request_count(), 1);
+    assert_eq!(
+        error.to_string(),
+        "LLM API error (401): bad key (check the API key and its permissions)"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_retries_stop_at_max_retries() -> Result<()> {
+    let server = StubServer::start(500, json!({"error": {"message": "internal error"}})).await;
+
+    let error = client(&server).complete(&request()).await.unwrap_err();
+
+    assert_eq!(server.request_count(), 4);
+    assert!(matches!(
+        error.downcast_ref::<LlmError>(),
+        Some(LlmError::Api { status: 500, .. })
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_invalid_or_huge_retry_after_is_bounded() -> Result<()> {
+    let server = StubServer::sequence(vec![
+        StubResponse::json(429, json!({"error": {"message": "slow down"}}))
+            .header("Retry-After", "inf"),
+        StubResponse::json(429, json!({"error": {"message": "slow down"}}))
+            .header("Retry-After", "NaN"),
+        StubResponse::json(429, json!({"error": {"message": "slow down"}}))
+            .header("Retry-After", "1e30"),
+        StubResponse::json(200, ok_body()),
+    ])
+    .await;
+
+    let started = std::time::Instant::now();
+    let response = client(&server).complete(&request()).await?;
+
+    assert_eq!(response.message.content, "hi");
+    assert_eq!(server.request_count(), 4);
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    Ok(())
+}
+
+#[test]
+fn test_retry_after_is_capped_at_max_backoff() {
+    let error = LlmError::Api {
+        status: 429,
+        message: "slow down".to_string(),
+        retry_after: Some(std::time::Duration::from_secs(3600)),
+    };
+
+    assert_eq!(
+        fast_retries().delay(1, &error),
+        std::time::Duration::from_millis(5)
+    );
+}
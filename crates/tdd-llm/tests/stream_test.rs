@@ -0,0 +1,225 @@
+mod common;
+
+use anyhow::Result;
+use common::{StubResponse, StubServer};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tdd_llm::{
+    ApiKind, ChatRequest, LlmClient, LlmError, Message, ProviderConfig, RetryPolicy, SseEvent,
+    SseParser, StreamObserver, Usage,
+};
+
+#[derive(Default)]
+struct Recorder {
+    events: Mutex<Vec<String>>,
+}
+
+impl StreamObserver for Recorder {
+    fn on_text(&self, text: &str) {
+        self.events.lock().unwrap().push(text.to_string());
+    }
+
+    fn on_tool_call(&self, name: &str) {
+        self.events.lock().unwrap().push(format!("tool:{}", name));
+    }
+
+    fn on_finish(&self) {
+        self.events.lock().unwrap().push("finish".to_string());
+    }
+}
+
+fn streaming_client(provider: ProviderConfig, recorder: Arc<Recorder>) -> LlmClient {
+    LlmClient::with_provider(provider.build())
+        .with_retry(RetryPolicy::none())
+        .with_stream(recorder)
+}
+
+fn request() -> ChatRequest {
+    ChatRequest::new("test-model", vec![Message::user("hello")])
+}
+
+#[test]
+fn test_sse_parser_reassembles_split_events() {
+    let mut parser = SseParser::default();
+
+    assert!(parser.push(b"event: ping\r\ndata: {\"a\"").is_empty());
+    let events = parser.push(b":1}\r\n\r\n: comment\n\ndata: [DONE]\n\n");
+
+    assert_eq!(
+        events,
+        vec![
+            SseEvent {
+                event: "ping".to_string(),
+                data: r#"{"a":1}"#.to_string()
+            },
+            SseEvent {
+                event: String::new(),
+                data: "[DONE]".to_string()
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_openai_stream_assembles_deltas() -> Result<()> {
+    let server = StubServer::sequence(vec![StubResponse::sse(&[
+        json!({"choices": [{"delta": {"role": "assistant", "content": "Hel"}}]}),
+        json!({"choices": [{"delta": {"content": "lo"}}]}),
+        json!({"choices": [{"delta": {"tool_calls": [
+            {"index": 0, "id": "call_1", "function": {"name": "read_file", "arguments": "{\"pa"}}
+        ]}}]}),
+        json!({"choices": [{"delta": {"tool_calls": [
+            {"index": 0, "function": {"arguments": "th\":\"a\"}"}}
+        ]}, "finish_reason": "tool_calls"}]}),
+        json!({"choices": [], "usage": {"prompt_tokens": 9, "completion_tokens": 4}}),
+    ])])
+    .await;
+    let recorder = Arc::new(Recorder::default());
+    let provider = ProviderConfig::new(ApiKind::OpenAi, server.url.clone(), "sk".into());
+
+    let response = streaming_client(provider, recorder.clone())
+        .complete(&request())
+        .await?;
+
+    assert_eq!(response.message.content, "Hello");
+    assert_eq!(response.message.tool_calls[0].id, "call_1");
+    assert_eq!(
+        response.message.tool_calls[0].function.arguments,
+        r#"{"path":"a"}"#
+    );
+    assert_eq!(response.finish_reason.as_deref(), Some("tool_calls"));
+    assert_eq!(
+        response.usage,
+        Some(Usage {
+            prompt_tokens: 9,
+            completion_tokens: 4,
+            cached_tokens: 0
+        })
+    );
+    assert_eq!(
+        *recorder.events.lock().unwrap(),
+        vec!["Hel", "lo", "tool:read_file", "finish"]
+    );
+    assert_eq!(server.last_request().body["stream"], true);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_anthropic_stream_assembles_blocks() -> Result<()> {
+    let server = StubServer::sequence(vec![StubResponse::sse(&[
+        json!({"type": "message_start", "message": {"usage": {"input_tokens": 5, "cache_read_input_tokens": 15}}}),
+        json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+        json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "ok"}}),
+        json!({"type": "content_block_start", "index": 1, "content_block": {"type": "tool_use", "id": "toolu_1", "name": "list_files"}}),
+        json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "{}"}}),
+        json!({"type": "message_delta", "delta": {"stop_reason": "tool_use"}, "usage": {"output_tokens": 7}}),
+        json!({"type": "message_stop"}),
+    ])])
+    .await;
+    let recorder = Arc::new(Recorder::default());
+    let provider = ProviderConfig::new(ApiKind::Anthropic, server.url.clone(), "key".into());
+
+    let response = streaming_client(provider, recorder)
+        .complete(&request())
+        .await?;
+
+    assert_eq!(response.message.content, "ok");
+    assert_eq!(response.message.tool_calls[0].function.name, "list_files");
+    assert_eq!(response.finish_reason.as_deref(), Some("tool_use"));
+    assert_eq!(
+        response.usage,
+        Some(Usage {
+            prompt_tokens: 20,
+            completion_tokens: 7,
+            cached_tokens: 15
+        })
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_gemini_stream_uses_sse_endpoint() -> Result<()> {
+    let server = StubServer::sequence(vec![StubResponse::sse(&[
+        json!({"candidates": [{"content": {"role": "model", "parts": [{"text": "a"}]}}]}),
+        json!({"candidates": [{"content": {"role": "model", "parts": [{"text": "b"}]}, "finishReason": "STOP"}],
+               "usageMetadata": {"promptTokenCount": 3, "candidatesTokenCount": 2}}),
+    ])])
+    .await;
+    let recorder = Arc::new(Recorder::default());
+    let provider = ProviderConfig::new(ApiKind::Gemini, server.url.clone(), "key".into());
+
+    let response = streaming_client(provider, recorder)
+        .complete(&request())
+        .await?;
+
+    assert_eq!(response.message.content, "ab");
+    assert_eq!(response.usage.unwrap().completion_tokens, 2);
+    assert_eq!(
+        server.last_request().path,
+        "/models/test-model:streamGenerateContent?alt=sse"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stream_errors_are_reported() -> Result<()> {
+    let server = StubServer::sequence(vec![StubResponse::sse(&[
+        json!({"type": "message_start", "message": {"usage": {"input_tokens": 1}}}),
+        json!({"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}}),
+    ])])
+    .await;
+    let provider = ProviderConfig::new(ApiKind::Anthropic, server.url.clone(), "key".into());
+
+    let error = streaming_client(provider, Arc::default())
+        .complete(&request())
+        .await
+        .unwrap_err();
+
+    assert_eq!(error.to_string(), "LLM stream error: Overloaded");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stalled_stream_is_cut_off() -> Result<()> {
+    let server = StubServer::sequence(vec![StubResponse::sse(&[
+        json!({"choices": [{"delta": {"content": "thinking"}}]}),
+    ])
+    .stalled()])
+    .await;
+    let provider = ProviderConfig::new(ApiKind::OpenAi, server.url.clone(), "sk".into())
+        .idle_timeout(Duration::from_millis(200));
+
+    let error = streaming_client(provider, Arc::default())
+        .complete(&request())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        error.downcast_ref::<LlmError>(),
+        Some(LlmError::Stalled(_))
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_out_of_range_tool_call_index_is_rejected() -> Result<()> {
+    let server = StubServer::sequence(vec![StubResponse::sse(&[
+        json!({"choices": [{"delta": {"tool_calls": [
+            {"index": 4294967295u64, "id": "call_1", "function": {"name": "read_file"}}
+        ]}}]}),
+    ])])
+    .await;
+    let provider = ProviderConfig::new(ApiKind::OpenAi, server.url.clone(), "sk".into());
+
+    let error = streaming_client(provider, Arc::default())
+        .complete(&request())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        error.downcast_ref::<LlmError>(),
+        Some(LlmError::InvalidResponse(_))
+    ));
+    Ok(())
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use anyhow::Result;
+use common::StubServer;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tdd_llm::{
+    ApiKind, ChatRequest, LlmClient, Message, ProviderConfig, RetryPolicy, TranscriptLevel,
+    TranscriptLog,
+};
+use tempfile::TempDir;
+
+fn transcripts(dir: &TempDir) -> Result<Vec<Value>> {
+    let mut paths: Vec<_> = std::fs::read_dir(dir.path())?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<_, _>>()?;
+    paths.sort();
+    paths
+        .iter()
+        .map(|path| Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?))
+        .collect()
+}
+
+async fn call(level: TranscriptLevel, dir: &TempDir, prompt: &str) -> Result<StubServer> {
+    let server = StubServer::start(
+        200,
+        json!({
+            "choices": [{"message": {"role": "assistant", "content": "answer"}, "finish_reason": "stop"}],
+            "usage": {"prompt_tokens": 7, "completion_tokens": 2}
+        }),
+    )
+    .await;
+    let config = ProviderConfig::new(ApiKind::OpenAi, server.url.clone(), "sk-live-1234".into())
+        .header("OpenAI-Organization", "org-visible")
+        .header("X-Proxy-Token", "proxy-secret");
+    let log = TranscriptLog::new(dir.path(), level).redact_patterns(&["internal-[0-9]+".into()])?;
+    let client = LlmClient::with_provider(config.clone().build())
+        .with_retry(RetryPolicy::none())
+        .with_transcripts(Arc::new(log), "openai", &config);
+
+    let request = ChatRequest::new("gpt-4o", vec![Message::user(prompt)]).temperature(0.5);
+    client.complete(&request).await?;
+    Ok(server)
+}
+
+#[tokio::test]
+async fn test_full_transcript_is_written_and_redacted() -> Result<()> {
+    let dir = TempDir::new()?;
+
+    call(
+        TranscriptLevel::Full,
+        &dir,
+        "token sk-live-1234, host internal-42, key sk-abcdefghijklmnopqrstu",
+    )
+    .await?;
+
+    let entries = transcripts(&dir)?;
+    assert_eq!(entries.len(), 1);
+    let entry = &entries[0];
+    assert_eq!(entry["provider"], "openai");
+    assert_eq!(entry["model"], "gpt-4o");
+    assert_eq!(entry["parameters"]["temperature"], 0.5);
+    assert_eq!(entry["usage"]["prompt_tokens"], 7);
+    assert_eq!(entry["response"]["content"], "answer");
+    assert!(entry["latency_ms"].is_u64());
+    assert_eq!(entry["headers"]["Authorization"], "[REDACTED]");
+    assert_eq!(entry["headers"]["X-Proxy-Token"], "[REDACTED]");
+    assert_eq!(entry["headers"]["OpenAI-Organization"], "org-visible");
+    assert_eq!(
+        entry["messages"][0]["content"],
+        "token [REDACTED], host [REDACTED], key [REDACTED]"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_summary_transcript_omits_content() -> Result<()> {
+    let dir = TempDir::new()?;
+
+    call(TranscriptLevel::Summary, &dir, "private prompt").await?;
+    call(TranscriptLevel::Summary, &dir, "second call").await?;
+
+    let entries = transcripts(&dir)?;
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["messages"], json!({"count": 1, "chars": 14}));
+    assert_eq!(entries[0]["response"]["chars"], 6);
+    assert!(!entries[0].to_string().contains("private prompt"));
+    Ok(())
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use anyhow::Result;
+use common::StubServer;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tdd_llm::{ApiKind, ChatRequest, LlmClient, Message, ModelPrice, Usage, UsageRecorder};
+
+#[test]
+fn test_cached_tokens_use_the_cached_price() {
+    let price = ModelPrice {
+        input_per_mtok: 2.0,
+        output_per_mtok: 10.0,
+        cached_input_per_mtok: Some(0.5),
+    };
+    let usage = Usage {
+        prompt_tokens: 1_000_000,
+        completion_tokens: 100_000,
+        cached_tokens: 400_000,
+    };
+
+    // 600k uncached * 2.0 + 400k cached * 0.5 + 100k output * 10.0
+    assert!((price.cost(&usage) - 2.4).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn test_client_reports_usage_to_recorder() -> Result<()> {
+    let server = StubServer::start(
+        200,
+        json!({
+            "choices": [{"message": {"role": "assistant", "content": "hi"}}],
+            "usage": {"prompt_tokens": 1000, "completion_tokens": 500}
+        }),
+    )
+    .await;
+    let pricing = HashMap::from([(
+        "priced".to_string(),
+        ModelPrice {
+            input_per_mtok: 1.0,
+            output_per_mtok: 2.0,
+            cached_input_per_mtok: None,
+        },
+    )]);
+    let recorder = Arc::new(UsageRecorder::new(pricing));
+    let client = LlmClient::with_provider(ApiKind::OpenAi.build(server.url.clone(), "sk".into()))
+        .with_usage(recorder.clone());
+
+    client
+        .complete(&ChatRequest::new("priced", vec![Message::user("a")]))
+        .await?;
+    client
+        .complete(&ChatRequest::new("unpriced", vec![Message::user("b")]))
+        .await?;
+
+    let records = recorder.drain();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].provider, "openai");
+    assert_eq!(records[0].usage.prompt_tokens, 1000);
+    assert!((records[0].cost_usd.unwrap() - 0.002).abs() < 1e-9);
+    assert_eq!(records[1].cost_usd, None);
+    assert!(recorder.drain().is_empty());
+    Ok(())
+}